@@ -0,0 +1,169 @@
+//! A [`tokio_util::codec`] for the bytes of a connection: a protocol header, then frames.
+use std::io;
+
+use bytes::{Bytes, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use amqp_types::codec::Encode;
+
+use crate::{
+    definitions::MIN_MAX_FRAME_SIZE,
+    framing::{write_amqp_frame, write_heartbeat, write_sasl_frame, Frame, FrameHeader},
+    version::Version,
+};
+
+/// What the peer sent.
+#[derive(Debug, Clone)]
+pub enum Incoming {
+    /// The protocol header opening the connection, or the next layer after SASL.
+    Header(Version),
+    Frame(FrameBuf),
+}
+
+/// A whole frame, split off the read buffer.
+#[derive(Debug, Clone)]
+pub struct FrameBuf {
+    pub header: FrameHeader,
+    /// the frame, its header included
+    pub bytes: Bytes,
+}
+
+impl FrameBuf {
+    /// The frame, the performative of its [`Frame::amqp_body`] borrowing from `self`.
+    pub fn frame(&self) -> Frame<'_> {
+        let mut bytes = &self.bytes[..];
+        Frame::decode(&mut bytes, self.header.size)
+            .expect("checked when decoded")
+            .expect("a whole frame")
+    }
+}
+
+/// An AMQP frame to write.
+#[derive(Debug, Clone)]
+pub struct AmqpFrame<T> {
+    pub channel: u16,
+    pub performative: T,
+    /// the message data following a transfer, empty for other performatives
+    pub payload: Bytes,
+}
+
+/// A SASL frame to write.
+#[derive(Debug, Clone)]
+pub struct SaslFrame<T>(pub T);
+
+/// An empty frame to write, keeping an idle connection open.
+#[derive(Debug, Clone, Copy)]
+pub struct Heartbeat;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReadState {
+    Header,
+    Frames,
+}
+
+/// Decodes the protocol header then frames, and encodes headers and frames.
+///
+/// Frames are checked against the max frame sizes, both are [`MIN_MAX_FRAME_SIZE`] until the
+/// open frames are exchanged. After a SASL exchange the peer sends a new protocol header, call
+/// [`FrameCodec::expect_header`] before reading it.
+#[derive(Debug)]
+pub struct FrameCodec {
+    read_state: ReadState,
+    max_frame_size: u32,
+    remote_max_frame_size: u32,
+}
+
+impl Default for FrameCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameCodec {
+    pub fn new() -> Self {
+        Self {
+            read_state: ReadState::Header,
+            max_frame_size: MIN_MAX_FRAME_SIZE,
+            remote_max_frame_size: MIN_MAX_FRAME_SIZE,
+        }
+    }
+    /// Read a protocol header next.
+    pub fn expect_header(&mut self) {
+        self.read_state = ReadState::Header;
+    }
+    /// The largest frame accepted from the peer, the `max-frame-size` of our open.
+    pub fn set_max_frame_size(&mut self, max_frame_size: u32) {
+        self.max_frame_size = max_frame_size;
+    }
+    /// The largest frame written, the `max-frame-size` of the open of the peer.
+    pub fn set_remote_max_frame_size(&mut self, max_frame_size: u32) {
+        self.remote_max_frame_size = max_frame_size;
+    }
+}
+
+impl Decoder for FrameCodec {
+    type Item = Incoming;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Incoming>> {
+        let Some(header) = src.get(..8) else {
+            return Ok(None);
+        };
+        let header: [u8; 8] = header.try_into().expect("8 bytes");
+        if self.read_state == ReadState::Header {
+            let version = Version::try_parse(header)?;
+            let _ = src.split_to(8);
+            self.read_state = ReadState::Frames;
+            return Ok(Some(Incoming::Header(version)));
+        }
+        let header = FrameHeader::try_parse(header)?;
+        header.check_size(self.max_frame_size)?;
+        let size = header.size as usize;
+        if src.len() < size {
+            src.reserve(size - src.len());
+            return Ok(None);
+        }
+        let bytes = src.split_to(size).freeze();
+        Ok(Some(Incoming::Frame(FrameBuf { header, bytes })))
+    }
+}
+
+impl Encoder<Version> for FrameCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, version: Version, dst: &mut BytesMut) -> io::Result<()> {
+        dst.extend_from_slice(&version.as_bytes());
+        Ok(())
+    }
+}
+
+impl<T: Encode> Encoder<AmqpFrame<T>> for FrameCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, frame: AmqpFrame<T>, dst: &mut BytesMut) -> io::Result<()> {
+        write_amqp_frame(
+            dst,
+            frame.channel,
+            frame.performative,
+            &frame.payload,
+            self.remote_max_frame_size,
+        )
+    }
+}
+
+impl<T: Encode> Encoder<SaslFrame<T>> for FrameCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, frame: SaslFrame<T>, dst: &mut BytesMut) -> io::Result<()> {
+        write_sasl_frame(dst, frame.0, self.remote_max_frame_size)
+    }
+}
+
+impl Encoder<Heartbeat> for FrameCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, _: Heartbeat, dst: &mut BytesMut) -> io::Result<()> {
+        write_heartbeat(dst);
+        Ok(())
+    }
+}
@@ -1,11 +1,13 @@
-pub struct Channel {
-
-}
-
+/// A channel we send the frames of a session on, chosen by us below the `channel-max` of the
+/// peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct OutgoingChannelEndpoint {
-    number: u16,
+    pub number: u16,
 }
 
+/// A channel the peer sends the frames of a session on, chosen by the peer below our
+/// `channel-max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct IncomingChannelEndpoint {
-    number: u16,
-}
\ No newline at end of file
+    pub number: u16,
+}
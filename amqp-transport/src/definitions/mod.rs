@@ -1,8 +1,19 @@
-use std::{collections::HashMap, time::Duration};
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    io,
+    ops::{Add, AddAssign, Sub},
+    time::Duration,
+};
 
-use amqp_types::{provides::{Require, Provide}, Binary, Symbol, Type, Value};
+use amqp_types::{
+    codec::Encode,
+    primitive::{Binary, OwnedSymbol, Symbol},
+    provides::{self, archetype, Provide, Require, Requires},
+    OwnedValue, Type,
+};
 
-#[derive(Debug, Clone, Type)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Type)]
 #[amqp(restrict(source = bool))]
 pub enum Role {
     #[amqp(choice = false)]
@@ -11,7 +22,7 @@ pub enum Role {
     Receiver,
 }
 
-#[derive(Debug, Clone, Type)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Type)]
 #[amqp(restrict(source = u8))]
 pub enum SenderSettleMode {
     #[amqp(choice = 0)]
@@ -22,7 +33,7 @@ pub enum SenderSettleMode {
     Mixed,
 }
 
-#[derive(Debug, Clone, Type)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Type)]
 #[amqp(restrict(source = u8))]
 pub enum ReceiverSettleMode {
     #[amqp(choice = 0)]
@@ -31,7 +42,7 @@ pub enum ReceiverSettleMode {
     Second,
 }
 
-#[derive(Debug, Clone, Copy, Default, Type, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, Default, Type, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Handle(pub u32);
 
 #[derive(Debug, Clone, Copy, Default, Type, PartialEq, Eq)]
@@ -52,40 +63,140 @@ impl From<Milliseconds> for Duration {
     }
 }
 
-#[derive(Debug, Clone, Default, Type, PartialEq, Eq)]
-#[amqp(restrict(validation = |b: &Binary| b.len() <= 32))]
-pub struct DeliveryTag(pub Binary);
+#[derive(Debug, Clone, Type, PartialEq, Eq, Hash)]
+#[amqp(restrict(validation = |b: &Binary| b.as_bytes().len() <= 32))]
+pub struct DeliveryTag<'amqp>(pub Binary<'amqp>);
 
 pub type DeliveryNumber = SequenceNo;
 pub type TransferNumber = SequenceNo;
 
-#[derive(Debug, Clone, Copy, Default, Type)]
-// should be RFC-1982
+/// A 32 bit serial number, compared and added as in RFC-1982 so it can wrap around.
+#[derive(Debug, Clone, Copy, Default, Type, PartialEq, Eq, Hash)]
 pub struct SequenceNo(pub u32);
 
-#[derive(Debug, Clone, Type)]
-pub struct IetfLanguageTag(pub Symbol);
-pub type Fields = HashMap<Symbol, Value>;
+impl SequenceNo {
+    /// Half the number space, the farthest two numbers can be apart and still be ordered.
+    pub const HALF: u32 = 1 << 31;
+
+    /// The numbers from `self` to `last` inclusive, across the wrap, none when `last` isn't
+    /// after `self`.
+    pub fn range_inclusive(self, last: SequenceNo) -> impl Iterator<Item = SequenceNo> {
+        let len = (self <= last).then(|| last - self);
+        len.into_iter()
+            .flat_map(move |len| (0..=len).map(move |offset| self + offset))
+    }
+}
+
+/// Serial number comparison: `self` is before the numbers up to half the space after it, and
+/// undefined for the number exactly half the space away.
+impl PartialOrd for SequenceNo {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match *other - *self {
+            0 => Some(Ordering::Equal),
+            distance if distance < Self::HALF => Some(Ordering::Less),
+            distance if distance > Self::HALF => Some(Ordering::Greater),
+            _ => None,
+        }
+    }
+}
+
+/// Serial number addition, wrapping around `u32::MAX`.
+impl Add<u32> for SequenceNo {
+    type Output = Self;
+    fn add(self, rhs: u32) -> Self {
+        SequenceNo(self.0.wrapping_add(rhs))
+    }
+}
+
+impl AddAssign<u32> for SequenceNo {
+    fn add_assign(&mut self, rhs: u32) {
+        *self = *self + rhs;
+    }
+}
+
+/// The distance from `rhs` to `self`, counting forward across the wrap.
+impl Sub for SequenceNo {
+    type Output = u32;
+    fn sub(self, rhs: Self) -> u32 {
+        self.0.wrapping_sub(rhs.0)
+    }
+}
+
+#[derive(Debug, Clone, Type, PartialEq, Eq)]
+pub struct IetfLanguageTag<'amqp>(pub Symbol<'amqp>);
+
+pub type Fields = HashMap<OwnedSymbol, OwnedValue>;
 
+/// Details of an error.
 #[derive(Debug, Clone, Type)]
-pub struct Error {
-    pub condition: Symbol,
-    pub description: Option<String>,
+#[amqp(descriptor(name = "amqp:error:list", code = 0x0000_0000_0000_001d))]
+pub struct Error<'amqp> {
+    /// A symbolic value indicating the error condition, read it with
+    /// [`Requires::get`] as an [`AmqpError`], a [`ConnectionError`], a [`SessionError`] or a
+    /// [`LinkError`], conditions defined elsewhere are kept as they came.
+    #[amqp(mandatory)]
+    pub condition: Requires<'amqp, ErrorCondition>,
+    /// descriptive text about the error condition
+    pub description: Option<&'amqp str>,
+    /// map carrying information about the error condition
     pub info: Option<Fields>,
 }
 
-#[derive(Debug, Clone, Type)]
-pub struct ErrorCondition(pub Symbol);
+impl<'a> Error<'a> {
+    /// An error of `condition` with `description`.
+    pub fn new<C: Provide<ErrorCondition> + Encode>(
+        condition: C,
+        description: &'a str,
+    ) -> io::Result<Self> {
+        Ok(Error {
+            condition: Requires::write(condition)?,
+            description: Some(description),
+            info: None,
+        })
+    }
+}
+
+/// The `error-condition` archetype.
+pub struct ErrorCondition;
+
+impl Require for ErrorCondition {
+    const ARCHETYPE: &'static str = archetype::ERROR_CONDITION;
+}
 
-impl Require for ErrorCondition {}
+/// The `delivery-state` archetype.
+pub struct DeliveryState;
 
-const fn sym(s: &'static str) -> Symbol {
-    Symbol::from_static_str(s)
+impl Require for DeliveryState {
+    const ARCHETYPE: &'static str = archetype::DELIVERY_STATE;
+}
+
+/// The `outcome` archetype, the delivery states which are terminal.
+pub struct Outcome;
+
+impl Require for Outcome {
+    const ARCHETYPE: &'static str = archetype::OUTCOME;
+}
+
+/// Register the described types of the crate providing archetypes in the registry of the
+/// process, for [`Requires::provider`] to know them. Registering twice has no effect.
+///
+/// Those are the delivery states, the error conditions being symbols have no provider.
+pub fn register_all() {
+    use crate::delivery::{Accepted, Modified, Received, Rejected, Released};
+    provides::register::<Received>();
+    provides::register::<Accepted>();
+    provides::register::<Rejected>();
+    provides::register::<Released>();
+    provides::register::<Modified>();
+}
+
+const fn sym(s: &'static str) -> OwnedSymbol {
+    OwnedSymbol::from_static(s.as_bytes())
 }
 
 /// Shared error conditions.
-#[derive(Debug, Clone, Copy, Type)]
-#[amqp(restrict(source = Symbol))]
+#[derive(Debug, Clone, PartialEq, Eq, Type)]
+#[amqp(restrict(source = OwnedSymbol))]
 pub enum AmqpError {
     /// An internal error occurred. Operator intervention may be required to resume normal
     /// operation.
@@ -137,10 +248,72 @@ pub enum AmqpError {
     FrameSizeTooSmall,
 }
 
-impl Provide<ErrorCondition> for AmqpError {
+impl Provide<ErrorCondition> for AmqpError {}
+
+/// Symbols used to indicate connection error conditions.
+#[derive(Debug, Clone, PartialEq, Eq, Type)]
+#[amqp(restrict(source = OwnedSymbol))]
+pub enum ConnectionError {
+    /// An operator intervened to close the Connection for some reason. The client may retry
+    /// at some later date.
+    #[amqp(choice = sym("amqp:connection:forced"))]
+    ConnectionForced,
+    /// A valid frame header cannot be formed from the incoming byte stream.
+    #[amqp(choice = sym("amqp:connection:framing-error"))]
+    FramingError,
+    /// The container is no longer available on the current connection. The peer should attempt
+    /// reconnection to the container using the details provided in the info map.
+    #[amqp(choice = sym("amqp:connection:redirect"))]
+    Redirect,
+}
+
+impl Provide<ErrorCondition> for ConnectionError {}
 
+/// Symbols used to indicate session error conditions.
+#[derive(Debug, Clone, PartialEq, Eq, Type)]
+#[amqp(restrict(source = OwnedSymbol))]
+pub enum SessionError {
+    /// The peer violated incoming window for the session.
+    #[amqp(choice = sym("amqp:session:window-violation"))]
+    WindowViolation,
+    /// Input was received for a link that was detached with an error.
+    #[amqp(choice = sym("amqp:session:errant-link"))]
+    ErrantLink,
+    /// An attach was received using a handle that is already in use for an attached Link.
+    #[amqp(choice = sym("amqp:session:handle-in-use"))]
+    HandleInUse,
+    /// A frame (other than attach) was received referencing a handle which is not currently in
+    /// use of an attached Link.
+    #[amqp(choice = sym("amqp:session:unattached-handle"))]
+    UnattachedHandle,
 }
 
+impl Provide<ErrorCondition> for SessionError {}
+
+/// Symbols used to indicate link error conditions.
+#[derive(Debug, Clone, PartialEq, Eq, Type)]
+#[amqp(restrict(source = OwnedSymbol))]
+pub enum LinkError {
+    /// An operator intervened to detach for some reason.
+    #[amqp(choice = sym("amqp:link:detach-forced"))]
+    DetachForced,
+    /// The peer sent more Message transfers than currently allowed on the link.
+    #[amqp(choice = sym("amqp:link:transfer-limit-exceeded"))]
+    TransferLimitExceeded,
+    /// The peer sent a larger message than is supported on the link.
+    #[amqp(choice = sym("amqp:link:message-size-exceeded"))]
+    MessageSizeExceeded,
+    /// The address provided cannot be resolved to a terminus at the current container.
+    #[amqp(choice = sym("amqp:link:redirect"))]
+    Redirect,
+    /// The link has been attached elsewhere, causing the existing attachment to be forcibly
+    /// closed.
+    #[amqp(choice = sym("amqp:link:stolen"))]
+    Stolen,
+}
+
+impl Provide<ErrorCondition> for LinkError {}
+
 /* ==========================================================================
                              CONST VALUES
 ==========================================================================*/
@@ -183,16 +356,3 @@ pub const REVISION: u8 = 0;
 /// that they can send frames of up to this size until they have
 /// agreed a definitive maximum frame size for that Connection.
 pub const MIN_MAX_FRAME_SIZE: u32 = 512;
-
-#[test]
-fn test() {
-    let x = Handle(1);
-    let value = x.as_value();
-    let y = Handle::from_value(value).unwrap();
-    assert_eq!(x, y);
-    let role = Role::Receiver;
-    let value = role.as_value();
-    dbg!(&value);
-    let role2 = Role::from_value(value).unwrap();
-    dbg!(role2);
-}
@@ -0,0 +1,172 @@
+//! Deliveries and their settlement, section 2.6.12 of the specification, with the delivery states
+//! of section 3.4.
+//!
+//! A delivery is unsettled at an endpoint until the endpoint forgets it. The states it goes through
+//! are sent in the transfers and the dispositions of the link, as values of the `delivery-state`
+//! archetype, and kept as a [`State`] for the unsettled deliveries of a
+//! [`Link`](crate::links::Link).
+use std::io;
+
+use amqp_types::{provides::Requires, Type};
+use bytes::Bytes;
+
+use crate::definitions::{
+    DeliveryNumber, DeliveryState, Error, Fields, Outcome, ReceiverSettleMode,
+};
+
+pub const UNKNOWN_STATE: &str = "delivery state from an extension";
+
+/// The state of a delivery which is being received.
+#[derive(Debug, Clone, Type)]
+#[amqp(descriptor(name = "amqp:received:list", code = 0x0000_0000_0000_0023))]
+#[amqp(provides = "delivery-state")]
+pub struct Received {
+    /// the section of the message the data received so far ends in
+    #[amqp(mandatory)]
+    pub section_number: u32,
+    /// the offset of the first byte of the section not received
+    #[amqp(mandatory)]
+    pub section_offset: u64,
+}
+
+/// The outcome of a message that has been processed.
+#[derive(Debug, Clone, Type)]
+#[amqp(descriptor(name = "amqp:accepted:list", code = 0x0000_0000_0000_0024))]
+#[amqp(provides = "delivery-state, outcome")]
+pub struct Accepted {}
+
+/// The outcome of a message that is invalid and cannot be processed.
+#[derive(Debug, Clone, Type)]
+#[amqp(descriptor(name = "amqp:rejected:list", code = 0x0000_0000_0000_0025))]
+#[amqp(provides = "delivery-state, outcome")]
+pub struct Rejected<'amqp> {
+    /// the error that caused the message to be rejected
+    pub error: Option<Error<'amqp>>,
+}
+
+/// The outcome of a message that has not been, and will not be, processed.
+#[derive(Debug, Clone, Type)]
+#[amqp(descriptor(name = "amqp:released:list", code = 0x0000_0000_0000_0026))]
+#[amqp(provides = "delivery-state, outcome")]
+pub struct Released {}
+
+/// The outcome of a message that was released with changes.
+#[derive(Debug, Clone, Type)]
+#[amqp(descriptor(name = "amqp:modified:list", code = 0x0000_0000_0000_0027))]
+#[amqp(provides = "delivery-state, outcome")]
+pub struct Modified {
+    /// count the transfer as an unsuccessful delivery attempt
+    pub delivery_failed: Option<bool>,
+    /// prevent redelivery to the same link
+    pub undeliverable_here: Option<bool>,
+    /// message attributes to combine with the existing message-annotations
+    pub message_annotations: Option<Fields>,
+}
+
+/// A delivery state, as kept for an unsettled delivery.
+///
+/// The error of a rejected delivery and the annotations of a modified one aren't kept, they are
+/// read from the frame which carried them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum State {
+    Received {
+        section_number: u32,
+        section_offset: u64,
+    },
+    Accepted,
+    Rejected,
+    Released,
+    Modified {
+        delivery_failed: bool,
+        undeliverable_here: bool,
+    },
+    /// a state defined elsewhere, which can't be sent back
+    Other,
+}
+
+impl State {
+    /// Read a state the peer sent.
+    pub fn read(state: &Requires<'_, DeliveryState>) -> io::Result<Self> {
+        Ok(if state.is::<Received>() {
+            let received = state.get::<Received>()?;
+            State::Received {
+                section_number: received.section_number,
+                section_offset: received.section_offset,
+            }
+        } else if state.is::<Accepted>() {
+            State::Accepted
+        } else if state.is::<Rejected>() {
+            State::Rejected
+        } else if state.is::<Released>() {
+            State::Released
+        } else if state.is::<Modified>() {
+            let modified = state.get::<Modified>()?;
+            State::Modified {
+                delivery_failed: modified.delivery_failed.unwrap_or(false),
+                undeliverable_here: modified.undeliverable_here.unwrap_or(false),
+            }
+        } else {
+            State::Other
+        })
+    }
+    /// Write the state, to send it.
+    pub fn write(self) -> io::Result<Requires<'static, DeliveryState>> {
+        match self {
+            State::Received {
+                section_number,
+                section_offset,
+            } => Requires::write(Received {
+                section_number,
+                section_offset,
+            }),
+            State::Accepted => Requires::write(Accepted {}),
+            State::Rejected => Requires::write(Rejected { error: None }),
+            State::Released => Requires::write(Released {}),
+            State::Modified {
+                delivery_failed,
+                undeliverable_here,
+            } => Requires::write(Modified {
+                delivery_failed: Some(delivery_failed),
+                undeliverable_here: Some(undeliverable_here),
+                message_annotations: None,
+            }),
+            State::Other => Err(io::Error::new(io::ErrorKind::InvalidInput, UNKNOWN_STATE)),
+        }
+    }
+    /// Whether the state is an outcome, the last state of a delivery.
+    pub fn is_outcome(&self) -> bool {
+        !matches!(self, State::Received { .. } | State::Other)
+    }
+}
+
+/// An unsettled delivery of a link.
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    pub(crate) id: DeliveryNumber,
+    pub(crate) tag: Bytes,
+    pub(crate) state: Option<State>,
+    pub(crate) remote_state: Option<State>,
+    pub(crate) rcv_settle_mode: ReceiverSettleMode,
+}
+
+impl Delivery {
+    pub fn id(&self) -> DeliveryNumber {
+        self.id
+    }
+    pub fn tag(&self) -> &[u8] {
+        &self.tag
+    }
+    /// Our state of the delivery.
+    pub fn state(&self) -> Option<State> {
+        self.state
+    }
+    /// The state the peer sent.
+    pub fn remote_state(&self) -> Option<State> {
+        self.remote_state
+    }
+    /// The settlement policy of the receiver for the delivery, the one of the link if the first
+    /// transfer didn't set it.
+    pub fn rcv_settle_mode(&self) -> ReceiverSettleMode {
+        self.rcv_settle_mode
+    }
+}
@@ -1,23 +1,235 @@
-use bytes::{BufMut, Bytes, BytesMut};
-use std::io::{self, Read, Write};
+//! Frames, section 2.3 of the specification.
+//!
+//! A frame is an 8 bytes header, an extended header the header gives the size of, and a body. The
+//! body of an AMQP frame is a performative followed, for a transfer, by a payload. A frame without
+//! a body is a heartbeat.
+//!
+//! Everything here reads from and writes to plain buffers.
+use std::io::{self, Write};
+
+use amqp_types::{
+    codec::{BufWriter, Decode, Encode, EncodeMode, GrowableBuf, Sink},
+    types::Type,
+    Value,
+};
+
+use crate::performative::Performative;
+
+pub const FRAME_TOO_SMALL: &str = "frame size is smaller than its header";
+pub const INVALID_DOFF: &str = "data offset is smaller than the frame header";
+pub const FRAME_TOO_LARGE: &str = "frame size exceeds the max frame size";
+pub const UNEXPECTED_FRAME_TYPE: &str = "unexpected frame type";
+pub const UNEXPECTED_PAYLOAD: &str = "only transfer frames carry a payload";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct FrameHeader {
+    /// size of the whole frame, header included
     pub size: u32,
+    /// data offset, the size of the header and extended header in 4 bytes words
     pub doff: u8,
     pub frame_type: u8,
+    /// type specific, the channel of AMQP frames
     pub ext: u16,
 }
 
 impl FrameHeader {
+    pub const SIZE: usize = 8;
+    /// The smallest data offset, for a header without extended header.
+    pub const MIN_DOFF: u8 = 2;
+    pub const AMQP: u8 = 0x00;
+    pub const SASL: u8 = 0x01;
+
+    /// The header of a frame without extended header, its body being `body_size` bytes.
+    pub fn new(frame_type: u8, ext: u16, body_size: usize) -> io::Result<Self> {
+        let size = u32::try_from(Self::SIZE + body_size)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, FRAME_TOO_LARGE))?;
+        Ok(Self {
+            size,
+            doff: Self::MIN_DOFF,
+            frame_type,
+            ext,
+        })
+    }
     pub fn exthdr_size(&self) -> Option<usize> {
         (self.doff as usize).checked_mul(4)?.checked_sub(8)
     }
     pub fn body_size(&self) -> Option<usize> {
         (self.size as usize).checked_sub((self.doff as usize).checked_mul(4)?)
     }
+    /// The channel of an AMQP frame.
+    pub fn channel(&self) -> u16 {
+        self.ext
+    }
+
+    pub fn try_parse(data: [u8; 8]) -> io::Result<Self> {
+        let [s0, s1, s2, s3, doff, frame_type, e0, e1] = data;
+        let header = FrameHeader {
+            size: u32::from_be_bytes([s0, s1, s2, s3]),
+            doff,
+            frame_type,
+            ext: u16::from_be_bytes([e0, e1]),
+        };
+        if header.doff < Self::MIN_DOFF {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, INVALID_DOFF));
+        }
+        if header.body_size().is_none() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, FRAME_TOO_SMALL));
+        }
+        Ok(header)
+    }
+
+    /// Check the frame fits in `max_frame_size`.
+    pub fn check_size(&self, max_frame_size: u32) -> io::Result<()> {
+        match self.size <= max_frame_size {
+            true => Ok(()),
+            false => Err(io::Error::new(io::ErrorKind::InvalidData, FRAME_TOO_LARGE)),
+        }
+    }
+
+    pub fn write<W: Write>(self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.as_bytes())
+    }
+
+    pub fn as_bytes(&self) -> [u8; 8] {
+        let [s0, s1, s2, s3] = self.size.to_be_bytes();
+        let [e0, e1] = self.ext.to_be_bytes();
+        [s0, s1, s2, s3, self.doff, self.frame_type, e0, e1]
+    }
+}
+
+/// A frame borrowed from the buffer it was read from.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame<'f> {
+    pub header: FrameHeader,
+    pub extended_header: &'f [u8],
+    pub body: &'f [u8],
+}
+
+/// The body of an AMQP frame which isn't a heartbeat.
+#[derive(Debug, Clone)]
+pub struct AmqpBody<'f> {
+    pub performative: Performative<'f>,
+    /// the bytes following a transfer, empty for other performatives
+    pub payload: &'f [u8],
+}
+
+impl<'f> Frame<'f> {
+    /// Split the first frame off `buffer`.
+    ///
+    /// Returns `None`, leaving `buffer` as it is, until it holds the whole frame. Fails if the
+    /// header is invalid or the frame is larger than `max_frame_size`.
+    pub fn decode(buffer: &mut &'f [u8], max_frame_size: u32) -> io::Result<Option<Self>> {
+        let Some(header) = buffer.get(..FrameHeader::SIZE) else {
+            return Ok(None);
+        };
+        let header = FrameHeader::try_parse(header.try_into().expect("8 bytes"))?;
+        header.check_size(max_frame_size)?;
+        let Some(frame) = buffer.get(..header.size as usize) else {
+            return Ok(None);
+        };
+        let (extended_header, body) =
+            frame[FrameHeader::SIZE..].split_at(header.exthdr_size().expect("checked when parsed"));
+        *buffer = &buffer[header.size as usize..];
+        Ok(Some(Frame {
+            header,
+            extended_header,
+            body,
+        }))
+    }
+    pub fn channel(&self) -> u16 {
+        self.header.channel()
+    }
+    pub fn is_heartbeat(&self) -> bool {
+        self.header.frame_type == FrameHeader::AMQP && self.body.is_empty()
+    }
+    /// The performative and payload of an AMQP frame, `None` for a heartbeat.
+    pub fn amqp_body(&self) -> io::Result<Option<AmqpBody<'f>>> {
+        if self.header.frame_type != FrameHeader::AMQP {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                UNEXPECTED_FRAME_TYPE,
+            ));
+        }
+        if self.body.is_empty() {
+            return Ok(None);
+        }
+        let mut payload = self.body;
+        let performative = Performative::try_from_value(Value::decode(&mut payload)?)?;
+        if !payload.is_empty() && !matches!(performative, Performative::Transfer(_)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                UNEXPECTED_PAYLOAD,
+            ));
+        }
+        Ok(Some(AmqpBody {
+            performative,
+            payload,
+        }))
+    }
+    /// The frame body of a SASL frame, a single value.
+    pub fn sasl_body(&self) -> io::Result<Value<'f>> {
+        if self.header.frame_type != FrameHeader::SASL {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                UNEXPECTED_FRAME_TYPE,
+            ));
+        }
+        let mut body = self.body;
+        let value = Value::decode(&mut body)?;
+        match body.is_empty() {
+            true => Ok(value),
+            false => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                UNEXPECTED_PAYLOAD,
+            )),
+        }
+    }
+}
+
+/// Write an AMQP frame on `channel` carrying `performative`, followed by `payload` for a
+/// transfer.
+///
+/// Fails, writing nothing, if the frame would be larger than `max_frame_size`: transfers must be
+/// split by the caller.
+pub fn write_amqp_frame<B: GrowableBuf, T: Encode>(
+    buffer: &mut B,
+    channel: u16,
+    performative: T,
+    payload: &[u8],
+    max_frame_size: u32,
+) -> io::Result<()> {
+    let body_size = performative.encoded_size(EncodeMode::Compact) + payload.len();
+    let header = FrameHeader::new(FrameHeader::AMQP, channel, body_size)?;
+    write_frame(buffer, header, performative, payload, max_frame_size)
+}
+
+/// Write a SASL frame carrying `body`.
+pub fn write_sasl_frame<B: GrowableBuf, T: Encode>(
+    buffer: &mut B,
+    body: T,
+    max_frame_size: u32,
+) -> io::Result<()> {
+    let header = FrameHeader::new(FrameHeader::SASL, 0, body.encoded_size(EncodeMode::Compact))?;
+    write_frame(buffer, header, body, &[], max_frame_size)
+}
+
+/// Write an empty AMQP frame, which keeps an idle connection open.
+pub fn write_heartbeat<B: GrowableBuf>(buffer: &mut B) {
+    let header = FrameHeader::new(FrameHeader::AMQP, 0, 0).expect("an empty frame fits");
+    buffer.put_slice(&header.as_bytes());
 }
 
-pub struct Frame<'f, Ext, Body> {
-    header: &'f FrameHeader,
-    extended_header: &'f Ext,
-    body: Body,
+fn write_frame<B: GrowableBuf, T: Encode>(
+    buffer: &mut B,
+    header: FrameHeader,
+    body: T,
+    payload: &[u8],
+    max_frame_size: u32,
+) -> io::Result<()> {
+    header.check_size(max_frame_size)?;
+    buffer.put_slice(&header.as_bytes());
+    let mut writer = BufWriter::new(&mut *buffer).with_mode(EncodeMode::Compact);
+    writer.write_amqp_value(body)?;
+    buffer.put_slice(payload);
+    Ok(())
 }
@@ -1,12 +1,10 @@
 pub mod version;
 pub mod framing;
+pub mod codec;
 pub mod performative;
 pub mod definitions;
 pub mod connections;
 pub mod sessions;
 pub mod links;
-
-
-pub struct Connection {
-    
-}
\ No newline at end of file
+pub mod delivery;
+pub mod sm;
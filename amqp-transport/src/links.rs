@@ -1,17 +1,1017 @@
 //!
-//! 
 //! A Link provides a unidirectional transport for Messages between a Source and a Target. The primary
 //! responsibility of a Source or Target (a Terminus) is to maintain a record of the status of each active
 //! delivery attempt until such a time as it is safe to forget. These are referred to as unsettled deliveries.
 //! When a Terminus forgets the state associated with a delivery-tag, it is considered settled. Each delivery
 //! attempt is assigned a unique delivery-tag at the Source. The status of an active delivery attempt is
 //! known as the Delivery State of the delivery.
-//! 
-//! 
+//!
+//! The link endpoints of a session, section 2.6 of the specification. A link is known by the
+//! handle we send its frames with, mapped to the handle of the peer once its attach is received.
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+};
 
+use amqp_types::{codec::Encode, provides::Provide};
+use bytes::Bytes;
 
-pub struct Links {
-    source: String,
-    target: String,
+use crate::{
+    connections::OutgoingChannelEndpoint,
+    definitions::{
+        AmqpError, DeliveryNumber, Error, ErrorCondition, Handle, LinkError, ReceiverSettleMode,
+        Role, SenderSettleMode, SequenceNo, SessionError,
+    },
+    delivery::{Delivery, State, UNKNOWN_STATE},
+    framing::AmqpBody,
+    performative::{Attach, Detach, Disposition, Flow, Performative, Transfer},
+    sessions::Sessions,
+    sm::connection::{Connection, ILLEGAL_STATE},
+};
+
+pub const NO_FREE_HANDLE: &str = "every handle below the handle max of the peer is in use";
+pub const HANDLE_IN_USE: &str = "attach with a handle already in use";
+pub const UNATTACHED_HANDLE: &str = "frame for a handle which isn't attached";
+pub const NO_LINK_CREDIT: &str = "the link has no credit";
+pub const TRANSFER_LIMIT_EXCEEDED: &str = "transfer beyond the link credit";
+pub const NAME_MISMATCH: &str = "attach answering a link of another name";
+pub const MISSING_DELIVERY: &str = "first transfer of a delivery without its id or tag";
+pub const SETTLE_MODE_VIOLATION: &str = "settlement contrary to the settle modes of the link";
+pub const UNKNOWN_DELIVERY: &str = "no unsettled delivery with this tag";
+pub const INVALID_DELIVERY_STATE: &str = "delivery state which can't be decoded";
+pub const BACKWARD_DISPOSITION: &str = "disposition whose last delivery is before its first";
+
+/// The states of a link endpoint, a link is detached once both detaches are exchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LinkState {
+    Detached,
+    /// our attach sent, the attach of the peer not received
+    AttachSent,
+    /// the attach of the peer received, ours not sent
+    AttachRcvd,
+    /// attaches exchanged
+    Attached,
+    /// our detach sent, the detach of the peer not received
+    DetachSent,
+    /// the detach of the peer received, ours not sent
+    DetachRcvd,
+}
+
+/// A link endpoint, with the flow state of section 2.6.7.
+#[derive(Debug, Clone)]
+pub struct Link {
     name: String,
-}
\ No newline at end of file
+    role: Role,
+    state: LinkState,
+    handle: Handle,
+    remote_handle: Option<Handle>,
+    snd_settle_mode: SenderSettleMode,
+    rcv_settle_mode: ReceiverSettleMode,
+    initial_delivery_count: SequenceNo,
+    delivery_count: SequenceNo,
+    link_credit: u32,
+    available: u32,
+    drain: bool,
+    /// the tag of a delivery started with `more` and not finished yet
+    incomplete: Option<Bytes>,
+    /// the deliveries not settled here, by tag
+    unsettled: HashMap<Bytes, Delivery>,
+}
+
+impl Link {
+    fn new(name: &str, role: Role, handle: Handle, state: LinkState) -> Self {
+        Self {
+            name: name.to_owned(),
+            role,
+            state,
+            handle,
+            remote_handle: None,
+            snd_settle_mode: SenderSettleMode::Mixed,
+            rcv_settle_mode: ReceiverSettleMode::First,
+            initial_delivery_count: SequenceNo(0),
+            delivery_count: SequenceNo(0),
+            link_credit: 0,
+            available: 0,
+            drain: false,
+            incomplete: None,
+            unsettled: HashMap::new(),
+        }
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Our role on the link.
+    pub fn role(&self) -> Role {
+        self.role
+    }
+    pub fn state(&self) -> LinkState {
+        self.state
+    }
+    pub fn handle(&self) -> Handle {
+        self.handle
+    }
+    /// The handle of the peer, once its attach is received.
+    pub fn remote_handle(&self) -> Option<Handle> {
+        self.remote_handle
+    }
+    /// The settle mode of the sender, the one of its attach.
+    pub fn snd_settle_mode(&self) -> SenderSettleMode {
+        self.snd_settle_mode
+    }
+    /// The settle mode of the receiver, the one of its attach.
+    pub fn rcv_settle_mode(&self) -> ReceiverSettleMode {
+        self.rcv_settle_mode
+    }
+    pub fn delivery_count(&self) -> SequenceNo {
+        self.delivery_count
+    }
+    /// The deliveries the sender may still send.
+    pub fn link_credit(&self) -> u32 {
+        self.link_credit
+    }
+    /// The deliveries the sender has waiting, as it last told.
+    pub fn available(&self) -> u32 {
+        self.available
+    }
+    pub fn drain(&self) -> bool {
+        self.drain
+    }
+    pub fn delivery(&self, tag: &[u8]) -> Option<&Delivery> {
+        self.unsettled.get(tag)
+    }
+    pub fn unsettled(&self) -> impl Iterator<Item = &Delivery> {
+        self.unsettled.values()
+    }
+
+    /// Our flow for the link, on top of the `session` flow.
+    fn flow(&self, session: Flow) -> Flow {
+        Flow {
+            handle: Some(self.handle),
+            delivery_count: Some(self.delivery_count),
+            link_credit: Some(self.link_credit),
+            available: Some(self.available),
+            drain: self.drain,
+            ..session
+        }
+    }
+    /// Apply the attach of the peer.
+    fn attached(&mut self, attach: &Attach<'_>) {
+        self.remote_handle = Some(attach.handle);
+        match self.role {
+            Role::Sender => self.rcv_settle_mode = attach.rcv_settle_mode,
+            Role::Receiver => {
+                self.snd_settle_mode = attach.snd_settle_mode;
+                let count = attach.initial_delivery_count.unwrap_or_default();
+                self.initial_delivery_count = count;
+                self.delivery_count = count;
+            }
+        }
+    }
+    /// Apply a flow of the peer.
+    fn flowed(&mut self, flow: &Flow) {
+        match self.role {
+            // the credit the receiver gave, less the deliveries sent since
+            Role::Sender => {
+                let delivery_count = flow.delivery_count.unwrap_or(self.initial_delivery_count);
+                if let Some(link_credit) = flow.link_credit {
+                    self.link_credit = (delivery_count + link_credit) - self.delivery_count;
+                }
+                self.drain = flow.drain;
+            }
+            // the sender may have advanced its delivery count, consuming the credit when draining
+            Role::Receiver => {
+                if let Some(delivery_count) = flow.delivery_count {
+                    let advanced = delivery_count - self.delivery_count;
+                    self.link_credit = self.link_credit.saturating_sub(advanced);
+                    self.delivery_count = delivery_count;
+                }
+                self.available = flow.available.unwrap_or(self.available);
+            }
+        }
+    }
+    /// The tag of the delivery `transfer` is part of, `None` if it starts one without a tag.
+    fn delivery_tag(&self, transfer: &Transfer<'_>) -> Option<Bytes> {
+        match &self.incomplete {
+            Some(tag) => Some(tag.clone()),
+            None => transfer
+                .delivery_tag
+                .as_ref()
+                .map(|tag| Bytes::copy_from_slice(tag.0.as_bytes())),
+        }
+    }
+    /// Count a transfer of the delivery `tag`, fails if it starts a delivery beyond the credit.
+    fn transferred(&mut self, transfer: &Transfer<'_>, tag: Bytes) -> bool {
+        if self.incomplete.is_none() {
+            if self.link_credit == 0 {
+                return false;
+            }
+            self.delivery_count += 1;
+            self.link_credit -= 1;
+        }
+        self.incomplete = (transfer.more && !transfer.aborted).then_some(tag);
+        true
+    }
+}
+
+/// A frame of the peer read by its link.
+// borrowed from the frame and short lived, as the performatives it holds
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+pub enum LinkReceived<'f> {
+    /// a frame of a link which is detaching
+    Nothing,
+    /// the attach of the peer, answering ours or to be answered with [`Links::accept`]
+    Attach { handle: Handle, attach: Attach<'f> },
+    /// the detach of the peer, to be answered with [`Links::detach`] if ours wasn't sent
+    Detach { handle: Handle, detach: Detach<'f> },
+    /// a flow of the peer, its credit already applied
+    Flow { handle: Handle, flow: Flow },
+    /// a transfer for a receiving link, counted against its credit, its delivery kept unsettled
+    /// unless the sender settled it
+    Transfer {
+        handle: Handle,
+        transfer: Transfer<'f>,
+        payload: &'f [u8],
+    },
+    /// a disposition for the deliveries of any link of the session, already applied to them
+    Disposition(Disposition<'f>),
+}
+
+/// The links of a session, by handle.
+///
+/// Frames are written through the [`Sessions`], and the frames of the peer they hand over as
+/// [`SessionReceived::Link`](crate::sessions::SessionReceived::Link) are given to
+/// [`Links::handle`]. A transfer beyond the credit detaches the link with an error, a frame for a
+/// handle which isn't attached ends the session, and the error is returned.
+///
+/// Delivery ids are given by the session, so the deliveries of all its links are known here by
+/// id, for the dispositions which refer to them by range.
+#[derive(Debug)]
+pub struct Links {
+    channel: OutgoingChannelEndpoint,
+    links: BTreeMap<Handle, Link>,
+    remote: BTreeMap<Handle, Handle>,
+    next_delivery_id: DeliveryNumber,
+    deliveries: Deliveries,
+    /// the dispositions to send
+    pending: Vec<Pending>,
+}
+
+/// The unsettled deliveries of the links of a session, by our role and their id.
+type Deliveries = HashMap<(Role, DeliveryNumber), (Handle, Bytes)>;
+
+/// A disposition of a single delivery, waiting to be sent with those of its neighbours.
+#[derive(Debug, Clone, Copy)]
+struct Pending {
+    role: Role,
+    id: DeliveryNumber,
+    settled: bool,
+    state: Option<State>,
+}
+
+impl Links {
+    /// The links of the session on `channel`.
+    pub fn new(channel: OutgoingChannelEndpoint) -> Self {
+        Self {
+            channel,
+            links: BTreeMap::new(),
+            remote: BTreeMap::new(),
+            next_delivery_id: SequenceNo(0),
+            deliveries: HashMap::new(),
+            pending: Vec::new(),
+        }
+    }
+    pub fn channel(&self) -> OutgoingChannelEndpoint {
+        self.channel
+    }
+    pub fn get(&self, handle: Handle) -> Option<&Link> {
+        self.links.get(&handle)
+    }
+    /// The link named `name`, names are unique within a session.
+    pub fn find(&self, name: &str) -> Option<&Link> {
+        self.links.values().find(|link| link.name == name)
+    }
+    pub fn state(&self, handle: Handle) -> LinkState {
+        self.get(handle).map_or(LinkState::Detached, Link::state)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Link> {
+        self.links.values()
+    }
+
+    /// Attach a link with the lowest free handle, the one of `attach` being ignored.
+    ///
+    /// A sender starts counting deliveries from `initial_delivery_count`, 0 if unset.
+    pub fn attach(
+        &mut self,
+        connection: &mut Connection,
+        sessions: &mut Sessions,
+        mut attach: Attach<'_>,
+    ) -> io::Result<Handle> {
+        let session = sessions.get(self.channel).ok_or_else(illegal_state)?;
+        let handle = self.free_handle(session.remote_handle_max())?;
+        let mut link = Link::new(attach.name, attach.role, handle, LinkState::AttachSent);
+        link.snd_settle_mode = attach.snd_settle_mode;
+        link.rcv_settle_mode = attach.rcv_settle_mode;
+        attach.handle = handle;
+        match attach.role {
+            Role::Sender => {
+                let count = *attach.initial_delivery_count.get_or_insert(SequenceNo(0));
+                link.initial_delivery_count = count;
+                link.delivery_count = count;
+            }
+            Role::Receiver => attach.initial_delivery_count = None,
+        }
+        sessions.send(connection, self.channel, attach)?;
+        self.links.insert(handle, link);
+        Ok(handle)
+    }
+
+    /// Answer the attach of the peer, with the role opposite to its own.
+    pub fn accept(
+        &mut self,
+        connection: &mut Connection,
+        sessions: &mut Sessions,
+        handle: Handle,
+        mut attach: Attach<'_>,
+    ) -> io::Result<()> {
+        let link = link_in(&mut self.links, handle, &[LinkState::AttachRcvd])?;
+        if attach.name != link.name {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, NAME_MISMATCH));
+        }
+        attach.handle = handle;
+        attach.role = link.role;
+        match link.role {
+            Role::Sender => {
+                link.snd_settle_mode = attach.snd_settle_mode;
+                let count = *attach.initial_delivery_count.get_or_insert(SequenceNo(0));
+                link.initial_delivery_count = count;
+                link.delivery_count = count;
+            }
+            Role::Receiver => {
+                link.rcv_settle_mode = attach.rcv_settle_mode;
+                attach.initial_delivery_count = None;
+            }
+        }
+        sessions.send(connection, self.channel, attach)?;
+        link.state = LinkState::Attached;
+        Ok(())
+    }
+
+    /// Send our detach, `closed` to close the link rather than only detach it.
+    pub fn detach(
+        &mut self,
+        connection: &mut Connection,
+        sessions: &mut Sessions,
+        handle: Handle,
+        closed: bool,
+        error: Option<Error<'_>>,
+    ) -> io::Result<()> {
+        let link = link_in(
+            &mut self.links,
+            handle,
+            &[
+                LinkState::AttachSent,
+                LinkState::Attached,
+                LinkState::DetachRcvd,
+            ],
+        )?;
+        let next = match link.state {
+            LinkState::DetachRcvd => LinkState::Detached,
+            _ => LinkState::DetachSent,
+        };
+        let detach = Detach {
+            handle,
+            closed,
+            error,
+        };
+        sessions.send(connection, self.channel, detach)?;
+        link.state = next;
+        if next == LinkState::Detached {
+            self.remove(handle);
+        }
+        Ok(())
+    }
+
+    /// Send a transfer on a sending link, taking a credit when it starts a delivery.
+    ///
+    /// A delivery starts with its tag and is given the next delivery id of the session. It's kept
+    /// unsettled unless sent settled, as the sender settle mode of the link requires or, when
+    /// mixed, as `transfer` says.
+    pub fn transfer(
+        &mut self,
+        connection: &mut Connection,
+        sessions: &mut Sessions,
+        handle: Handle,
+        mut transfer: Transfer<'_>,
+        payload: &[u8],
+    ) -> io::Result<()> {
+        let link = link_in(&mut self.links, handle, &[LinkState::Attached])?;
+        if link.role != Role::Sender {
+            return Err(illegal_state());
+        }
+        let starts = link.incomplete.is_none();
+        if starts && link.link_credit == 0 {
+            return Err(io::Error::new(io::ErrorKind::WouldBlock, NO_LINK_CREDIT));
+        }
+        let tag = link
+            .delivery_tag(&transfer)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, MISSING_DELIVERY))?;
+        match (link.snd_settle_mode, transfer.settled) {
+            (SenderSettleMode::Unsettled, Some(true))
+            | (SenderSettleMode::Settled, Some(false)) => return Err(settle_mode_violation()),
+            (SenderSettleMode::Settled, None) if starts => transfer.settled = Some(true),
+            _ => {}
+        }
+        if transfer.rcv_settle_mode == Some(ReceiverSettleMode::Second)
+            && link.rcv_settle_mode == ReceiverSettleMode::First
+        {
+            return Err(settle_mode_violation());
+        }
+        let id = self.next_delivery_id;
+        transfer.handle = handle;
+        if starts {
+            transfer.delivery_id = Some(id);
+        }
+        let settled = transfer.settled == Some(true) || transfer.aborted;
+        let more = transfer.more && !transfer.aborted;
+        let rcv_settle_mode = transfer.rcv_settle_mode.unwrap_or(link.rcv_settle_mode);
+        sessions.transfer(connection, self.channel, transfer, payload)?;
+        if starts {
+            link.delivery_count += 1;
+            link.link_credit -= 1;
+            self.next_delivery_id += 1;
+        }
+        link.incomplete = more.then(|| tag.clone());
+        if starts && !settled {
+            let delivery = Delivery {
+                id,
+                tag,
+                state: None,
+                remote_state: None,
+                rcv_settle_mode,
+            };
+            track(&mut self.deliveries, link, delivery);
+        } else if settled {
+            forget(&mut self.deliveries, link, &tag);
+        }
+        Ok(())
+    }
+
+    /// Give the sender `link_credit`, `drain` asking it to use it all or give it back.
+    pub fn flow(
+        &mut self,
+        connection: &mut Connection,
+        sessions: &mut Sessions,
+        handle: Handle,
+        link_credit: u32,
+        drain: bool,
+    ) -> io::Result<()> {
+        let link = link_in(&mut self.links, handle, &[LinkState::Attached])?;
+        if link.role != Role::Receiver {
+            return Err(illegal_state());
+        }
+        link.link_credit = link_credit;
+        link.drain = drain;
+        send_flow(connection, sessions, self.channel, link)
+    }
+
+    /// Tell the receiver how many deliveries the sender has waiting.
+    pub fn set_available(
+        &mut self,
+        connection: &mut Connection,
+        sessions: &mut Sessions,
+        handle: Handle,
+        available: u32,
+    ) -> io::Result<()> {
+        let link = link_in(&mut self.links, handle, &[LinkState::Attached])?;
+        if link.role != Role::Sender {
+            return Err(illegal_state());
+        }
+        link.available = available;
+        send_flow(connection, sessions, self.channel, link)
+    }
+
+    /// The sender has nothing more to send: when draining, the credit left is used up by
+    /// advancing the delivery count, and the receiver told.
+    pub fn drained(
+        &mut self,
+        connection: &mut Connection,
+        sessions: &mut Sessions,
+        handle: Handle,
+    ) -> io::Result<()> {
+        let link = link_in(&mut self.links, handle, &[LinkState::Attached])?;
+        if link.role != Role::Sender {
+            return Err(illegal_state());
+        }
+        if !link.drain {
+            return Ok(());
+        }
+        link.delivery_count += link.link_credit;
+        link.link_credit = 0;
+        link.available = 0;
+        send_flow(connection, sessions, self.channel, link)
+    }
+
+    /// Set our `state` of the unsettled delivery `tag` of the link, settling it if `settled`.
+    ///
+    /// The disposition is sent with those of the neighbouring deliveries by
+    /// [`Links::send_dispositions`]. With the receiver settle mode second, the receiver doesn't
+    /// settle: the delivery is settled once the disposition of the sender settling it is received.
+    pub fn dispose(
+        &mut self,
+        handle: Handle,
+        tag: &[u8],
+        state: Option<State>,
+        settled: bool,
+    ) -> io::Result<()> {
+        if state == Some(State::Other) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, UNKNOWN_STATE));
+        }
+        let link = link_in(
+            &mut self.links,
+            handle,
+            &[LinkState::Attached, LinkState::DetachRcvd],
+        )?;
+        let delivery = link
+            .unsettled
+            .get_mut(tag)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, UNKNOWN_DELIVERY))?;
+        if settled
+            && link.role == Role::Receiver
+            && delivery.rcv_settle_mode == ReceiverSettleMode::Second
+        {
+            return Err(settle_mode_violation());
+        }
+        delivery.state = state.or(delivery.state);
+        let pending = Pending {
+            role: link.role,
+            id: delivery.id,
+            settled,
+            state: delivery.state,
+        };
+        if settled {
+            forget(&mut self.deliveries, link, tag);
+        }
+        self.pending
+            .retain(|other| (other.role, other.id) != (pending.role, pending.id));
+        self.pending.push(pending);
+        Ok(())
+    }
+
+    /// Send the dispositions left by [`Links::dispose`], one for each range of deliveries with
+    /// contiguous ids and the same state.
+    ///
+    /// On an error, the dispositions which weren't written are kept to be sent again.
+    pub fn send_dispositions(
+        &mut self,
+        connection: &mut Connection,
+        sessions: &mut Sessions,
+    ) -> io::Result<()> {
+        // in order from the earliest, across the wrap
+        let Some(base) = self
+            .pending
+            .iter()
+            .map(|pending| pending.id)
+            .reduce(|base, id| if id < base { id } else { base })
+        else {
+            return Ok(());
+        };
+        self.pending
+            .sort_by_key(|pending| (pending.role == Role::Receiver, pending.id - base));
+        // the first of each range, its last id and how many it spans
+        let mut ranges: Vec<(Pending, DeliveryNumber, usize)> = Vec::new();
+        for &next in &self.pending {
+            match ranges.last_mut() {
+                Some((first, last, len))
+                    if (first.role, first.settled, first.state)
+                        == (next.role, next.settled, next.state)
+                        && *last + 1 == next.id =>
+                {
+                    *last = next.id;
+                    *len += 1;
+                }
+                _ => ranges.push((next, next.id, 1)),
+            }
+        }
+        let mut sent = 0;
+        let mut result = Ok(());
+        for (first, last, len) in ranges {
+            result = first.state.map(State::write).transpose().and_then(|state| {
+                let disposition = Disposition {
+                    role: first.role,
+                    first: first.id,
+                    last: (last != first.id).then_some(last),
+                    settled: first.settled,
+                    state,
+                    batchable: false,
+                };
+                sessions.send(connection, self.channel, disposition)
+            });
+            if result.is_err() {
+                break;
+            }
+            sent += len;
+        }
+        self.pending.drain(..sent);
+        result
+    }
+
+    /// Read a frame of the peer for the links of the session.
+    pub fn handle<'f>(
+        &mut self,
+        connection: &mut Connection,
+        sessions: &mut Sessions,
+        body: AmqpBody<'f>,
+    ) -> io::Result<LinkReceived<'f>> {
+        let performative = match body.performative {
+            Performative::Attach(attach) => {
+                return self.handle_attach(connection, sessions, attach)
+            }
+            Performative::Disposition(disposition) => {
+                let Ok(state) = disposition.state.as_ref().map(State::read).transpose() else {
+                    return Err(self.end_session(
+                        connection,
+                        sessions,
+                        AmqpError::DecodeError,
+                        INVALID_DELIVERY_STATE,
+                    ));
+                };
+                if !disposition
+                    .last
+                    .is_none_or(|last| disposition.first <= last)
+                {
+                    return Err(self.end_session(
+                        connection,
+                        sessions,
+                        AmqpError::InvalidField,
+                        BACKWARD_DISPOSITION,
+                    ));
+                }
+                self.disposed(&disposition, state);
+                return Ok(LinkReceived::Disposition(disposition));
+            }
+            performative => performative,
+        };
+        let remote_handle = match &performative {
+            Performative::Detach(detach) => detach.handle,
+            Performative::Transfer(transfer) => transfer.handle,
+            Performative::Flow(Flow {
+                handle: Some(handle),
+                ..
+            }) => *handle,
+            _ => return Err(illegal_state()),
+        };
+        let Some(&handle) = self.remote.get(&remote_handle) else {
+            return Err(self.end_session(
+                connection,
+                sessions,
+                SessionError::UnattachedHandle,
+                UNATTACHED_HANDLE,
+            ));
+        };
+        let link = self.links.get_mut(&handle).expect("mapped");
+        match performative {
+            Performative::Detach(detach) => {
+                match link.state {
+                    LinkState::DetachSent => self.remove(handle),
+                    _ => link.state = LinkState::DetachRcvd,
+                }
+                Ok(LinkReceived::Detach { handle, detach })
+            }
+            _ if link.state == LinkState::DetachSent => Ok(LinkReceived::Nothing),
+            Performative::Flow(flow) => {
+                link.flowed(&flow);
+                if flow.echo {
+                    send_flow(connection, sessions, self.channel, link)?;
+                }
+                Ok(LinkReceived::Flow { handle, flow })
+            }
+            Performative::Transfer(transfer) => {
+                if link.role != Role::Receiver {
+                    return Err(self.end_session(
+                        connection,
+                        sessions,
+                        SessionError::ErrantLink,
+                        ILLEGAL_STATE,
+                    ));
+                }
+                let starts = link.incomplete.is_none();
+                let tag = link
+                    .delivery_tag(&transfer)
+                    .filter(|_| !starts || transfer.delivery_id.is_some());
+                let Some(tag) = tag else {
+                    return Err(self.detach_with(
+                        connection,
+                        sessions,
+                        handle,
+                        AmqpError::InvalidField,
+                        MISSING_DELIVERY,
+                    ));
+                };
+                if transfer.rcv_settle_mode == Some(ReceiverSettleMode::Second)
+                    && link.rcv_settle_mode == ReceiverSettleMode::First
+                {
+                    return Err(self.detach_with(
+                        connection,
+                        sessions,
+                        handle,
+                        AmqpError::NotAllowed,
+                        SETTLE_MODE_VIOLATION,
+                    ));
+                }
+                let Ok(remote_state) = transfer.state.as_ref().map(State::read).transpose() else {
+                    return Err(self.detach_with(
+                        connection,
+                        sessions,
+                        handle,
+                        AmqpError::DecodeError,
+                        INVALID_DELIVERY_STATE,
+                    ));
+                };
+                if !link.transferred(&transfer, tag.clone()) {
+                    return Err(self.detach_with(
+                        connection,
+                        sessions,
+                        handle,
+                        LinkError::TransferLimitExceeded,
+                        TRANSFER_LIMIT_EXCEEDED,
+                    ));
+                }
+                let settled = transfer.settled == Some(true) || transfer.aborted;
+                match transfer.delivery_id {
+                    Some(id) if starts && !settled => {
+                        let delivery = Delivery {
+                            id,
+                            tag,
+                            state: None,
+                            remote_state,
+                            rcv_settle_mode: transfer
+                                .rcv_settle_mode
+                                .unwrap_or(link.rcv_settle_mode),
+                        };
+                        track(&mut self.deliveries, link, delivery);
+                    }
+                    _ if settled => {
+                        forget(&mut self.deliveries, link, &tag);
+                    }
+                    _ => {
+                        if let Some(delivery) = link.unsettled.get_mut(&tag) {
+                            delivery.remote_state = remote_state.or(delivery.remote_state);
+                        }
+                    }
+                }
+                Ok(LinkReceived::Transfer {
+                    handle,
+                    transfer,
+                    payload: body.payload,
+                })
+            }
+            _ => unreachable!("routed above"),
+        }
+    }
+
+    fn handle_attach<'f>(
+        &mut self,
+        connection: &mut Connection,
+        sessions: &mut Sessions,
+        attach: Attach<'f>,
+    ) -> io::Result<LinkReceived<'f>> {
+        if self.remote.contains_key(&attach.handle) {
+            return Err(self.end_session(
+                connection,
+                sessions,
+                SessionError::HandleInUse,
+                HANDLE_IN_USE,
+            ));
+        }
+        let answering = self
+            .links
+            .values()
+            .find(|link| link.name == attach.name && link.state == LinkState::AttachSent)
+            .map(|link| link.handle);
+        let handle = match answering {
+            Some(handle) => {
+                self.links.get_mut(&handle).expect("found").state = LinkState::Attached;
+                handle
+            }
+            None => {
+                let remote_handle_max = sessions
+                    .get(self.channel)
+                    .ok_or_else(illegal_state)?
+                    .remote_handle_max();
+                let handle = self.free_handle(remote_handle_max)?;
+                let role = match attach.role {
+                    Role::Sender => Role::Receiver,
+                    Role::Receiver => Role::Sender,
+                };
+                let link = Link::new(attach.name, role, handle, LinkState::AttachRcvd);
+                self.links.insert(handle, link);
+                handle
+            }
+        };
+        self.links
+            .get_mut(&handle)
+            .expect("inserted")
+            .attached(&attach);
+        self.remote.insert(attach.handle, handle);
+        Ok(LinkReceived::Attach { handle, attach })
+    }
+
+    /// Apply a disposition of the peer, with its `state`, to our deliveries from its first to its
+    /// last, those it settles being settled here as well. The last isn't before the first.
+    fn disposed(&mut self, disposition: &Disposition<'_>, state: Option<State>) {
+        let role = match disposition.role {
+            Role::Sender => Role::Receiver,
+            Role::Receiver => Role::Sender,
+        };
+        let first = disposition.first;
+        let len = disposition.last.unwrap_or(first) - first;
+        // the range may be wide, the deliveries are looked up the other way around
+        let disposed: Vec<_> = self
+            .deliveries
+            .iter()
+            .filter(|((other, id), _)| *other == role && *id - first <= len)
+            .map(|(_, (handle, tag))| (*handle, tag.clone()))
+            .collect();
+        for (handle, tag) in disposed {
+            let link = self.links.get_mut(&handle).expect("tracked");
+            if disposition.settled {
+                forget(&mut self.deliveries, link, &tag);
+            } else if let Some(delivery) = link.unsettled.get_mut(&tag) {
+                delivery.remote_state = state.or(delivery.remote_state);
+            }
+        }
+    }
+
+    /// The lowest handle not used by a link.
+    fn free_handle(&self, handle_max: Handle) -> io::Result<Handle> {
+        (0..=handle_max.0)
+            .map(Handle)
+            .find(|handle| !self.links.contains_key(handle))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::WouldBlock, NO_FREE_HANDLE))
+    }
+
+    fn remove(&mut self, handle: Handle) {
+        if let Some(link) = self.links.remove(&handle) {
+            if let Some(remote_handle) = link.remote_handle {
+                self.remote.remove(&remote_handle);
+            }
+            self.deliveries.retain(|_, (other, _)| *other != handle);
+        }
+    }
+
+    /// Detach the link on `handle`, closed with an error of `condition`.
+    fn detach_with<C: Provide<ErrorCondition> + Encode>(
+        &mut self,
+        connection: &mut Connection,
+        sessions: &mut Sessions,
+        handle: Handle,
+        condition: C,
+        description: &'static str,
+    ) -> io::Error {
+        let detached = Error::new(condition, description)
+            .and_then(|error| self.detach(connection, sessions, handle, true, Some(error)));
+        match detached {
+            Ok(()) => io::Error::new(io::ErrorKind::InvalidData, description),
+            Err(error) => error,
+        }
+    }
+
+    /// End the session on `condition`, the handles being broken.
+    fn end_session<C: Provide<ErrorCondition> + Encode>(
+        &mut self,
+        connection: &mut Connection,
+        sessions: &mut Sessions,
+        condition: C,
+        description: &'static str,
+    ) -> io::Error {
+        // the error is returned whether the end could be sent or not
+        let _ = Error::new(condition, description)
+            .and_then(|error| sessions.end(connection, self.channel, Some(error)));
+        self.links.clear();
+        self.remote.clear();
+        self.deliveries.clear();
+        self.pending.clear();
+        io::Error::new(io::ErrorKind::InvalidData, description)
+    }
+}
+
+/// The link on `handle`, in one of `states`.
+fn link_in<'l>(
+    links: &'l mut BTreeMap<Handle, Link>,
+    handle: Handle,
+    states: &[LinkState],
+) -> io::Result<&'l mut Link> {
+    match links.get_mut(&handle) {
+        Some(link) if states.contains(&link.state) => Ok(link),
+        _ => Err(illegal_state()),
+    }
+}
+
+/// Keep `delivery` of `link` unsettled.
+fn track(deliveries: &mut Deliveries, link: &mut Link, delivery: Delivery) {
+    deliveries.insert(
+        (link.role, delivery.id),
+        (link.handle, delivery.tag.clone()),
+    );
+    link.unsettled.insert(delivery.tag.clone(), delivery);
+}
+
+/// Forget the delivery `tag` of `link`, settled.
+fn forget(deliveries: &mut Deliveries, link: &mut Link, tag: &[u8]) {
+    if let Some(delivery) = link.unsettled.remove(tag) {
+        deliveries.remove(&(link.role, delivery.id));
+    }
+}
+
+fn send_flow(
+    connection: &mut Connection,
+    sessions: &mut Sessions,
+    channel: OutgoingChannelEndpoint,
+    link: &Link,
+) -> io::Result<()> {
+    let session = sessions.get(channel).ok_or_else(illegal_state)?;
+    let flow = link.flow(session.flow());
+    sessions.send(connection, channel, flow)
+}
+
+fn illegal_state() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, ILLEGAL_STATE)
+}
+
+fn settle_mode_violation() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, SETTLE_MODE_VIOLATION)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+
+    use super::*;
+    use crate::{
+        definitions::MIN_MAX_FRAME_SIZE, framing::write_amqp_frame, sessions::SessionConfig,
+        sm::connection::ConnectionConfig,
+    };
+
+    /// The frames of settled dispositions of the receiver, from first to last with their state.
+    fn dispositions(ranges: &[(u32, u32, State)]) -> Bytes {
+        let mut buffer = BytesMut::new();
+        for &(first, last, state) in ranges {
+            let disposition = Disposition {
+                role: Role::Receiver,
+                first: SequenceNo(first),
+                last: (last != first).then_some(SequenceNo(last)),
+                settled: true,
+                state: Some(state.write().unwrap()),
+                batchable: false,
+            };
+            write_amqp_frame(&mut buffer, 0, disposition, &[], MIN_MAX_FRAME_SIZE).unwrap();
+        }
+        buffer.freeze()
+    }
+
+    #[test]
+    fn send_dispositions_again() {
+        let mut connection = Connection::new(ConnectionConfig::new("c1"));
+        connection.open().unwrap();
+        let mut sessions = Sessions::new();
+        let channel = sessions
+            .begin(&mut connection, SessionConfig::default())
+            .unwrap();
+        connection.take_outgoing();
+        let mut links = Links::new(channel);
+        let pending = |id, state| Pending {
+            role: Role::Receiver,
+            id: SequenceNo(id),
+            settled: true,
+            state: Some(state),
+        };
+        links.pending = vec![
+            pending(3, State::Released),
+            pending(0, State::Accepted),
+            pending(2, State::Other),
+            pending(1, State::Accepted),
+        ];
+
+        // the ranges before the one which can't be written are sent
+        let error = links
+            .send_dispositions(&mut connection, &mut sessions)
+            .unwrap_err();
+        assert_eq!(error.to_string(), UNKNOWN_STATE);
+        assert_eq!(
+            connection.take_outgoing(),
+            dispositions(&[(0, 1, State::Accepted)])
+        );
+        assert_eq!(links.pending.len(), 2);
+
+        links.pending[0].state = Some(State::Rejected);
+        links
+            .send_dispositions(&mut connection, &mut sessions)
+            .unwrap();
+        assert_eq!(
+            connection.take_outgoing(),
+            dispositions(&[(2, 2, State::Rejected), (3, 3, State::Released)])
+        );
+        assert!(links.pending.is_empty());
+    }
+}
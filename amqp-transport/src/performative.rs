@@ -1,3 +1,39 @@
-pub mod open;
+//! The frame bodies of the transport, section 2.7 of the specification.
+pub mod attach;
 pub mod begin;
-pub mod attach;
\ No newline at end of file
+pub mod close;
+pub mod detach;
+pub mod disposition;
+pub mod end;
+pub mod flow;
+pub mod open;
+pub mod transfer;
+
+use amqp_types::Type;
+
+pub use attach::Attach;
+pub use begin::Begin;
+pub use close::Close;
+pub use detach::Detach;
+pub use disposition::Disposition;
+pub use end::End;
+pub use flow::Flow;
+pub use open::Open;
+pub use transfer::Transfer;
+
+/// Any performative, decoded by its descriptor.
+// performatives are decoded once per frame and short lived, they aren't worth a box
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Type)]
+#[amqp(described)]
+pub enum Performative<'amqp> {
+    Open(Open<'amqp>),
+    Begin(Begin<'amqp>),
+    Attach(Attach<'amqp>),
+    Flow(Flow),
+    Transfer(Transfer<'amqp>),
+    Disposition(Disposition<'amqp>),
+    Detach(Detach<'amqp>),
+    End(End<'amqp>),
+    Close(Close<'amqp>),
+}
@@ -1,39 +1,70 @@
-// use crate::amqp_type;
+// descriptor name="amqp:attach:list" code="0x00000000:0x00000012"
 
-// pub struct Attach {
+use std::collections::HashMap;
 
-// }
+use amqp_types::{
+    primitive::Symbol,
+    provides::{archetype, Require, Requires},
+    Type,
+};
 
-// derive_descriptor! {Attach = 0x00000000:0x00000012}
+use crate::definitions::{
+    DeliveryState, DeliveryTag, Fields, Handle, ReceiverSettleMode, Role, SenderSettleMode,
+    SequenceNo,
+};
 
-use std::collections::HashMap;
+/// The `source` archetype, the terminus messages are taken from.
+pub struct Source;
 
-use amqp_types::{Symbol, Type, Value, Primitive, provides::Require};
+impl Require for Source {
+    const ARCHETYPE: &'static str = archetype::SOURCE;
+}
 
-use crate::definitions::*;
+/// The `target` archetype, the terminus messages are sent to.
+pub struct Target;
+
+impl Require for Target {
+    const ARCHETYPE: &'static str = archetype::TARGET;
+}
 
+/// Attach a link to a session.
 #[derive(Debug, Clone, Type)]
-pub struct Source(Value);
-
-impl Require for Source {}
-
-pub struct Target(Value);
-
-
-pub struct Attach
-{
-    pub(crate) name: String,
-    pub(crate) handle: u32,
-    pub(crate) role: Role,
-    pub(crate) snd_settle_mode: SenderSettleMode,
-    pub(crate) rcv_settle_mode: ReceiverSettleMode,
-    pub(crate) source: Option<Source>,
-    pub(crate) target: Option<Target>,
-    pub(crate) unsettled: Option<HashMap<Value, Value>>,
-    pub(crate) incomplete_unsettled: Option<bool>,
-    pub(crate) initial_delivery_count: Option<u32>,
-    pub(crate) max_message_size: Option<u64>,
-    pub(crate) offered_capabilities: Option<Vec<Symbol>>,
-    pub(crate) desired_capabilities: Option<Vec<Symbol>>,
-    pub(crate) properties: Option<Fields>,
+#[amqp(descriptor(name = "amqp:attach:list", code = 0x0000_0000_0000_0012))]
+pub struct Attach<'amqp> {
+    /// the name of the link
+    #[amqp(mandatory)]
+    pub name: &'amqp str,
+    /// the handle for the link while attached
+    #[amqp(mandatory)]
+    pub handle: Handle,
+    /// role of the link endpoint
+    #[amqp(mandatory)]
+    pub role: Role,
+    /// settlement policy for the sender
+    #[amqp(default = SenderSettleMode::Mixed)]
+    pub snd_settle_mode: SenderSettleMode,
+    /// the settlement policy of the receiver
+    #[amqp(default = ReceiverSettleMode::First)]
+    pub rcv_settle_mode: ReceiverSettleMode,
+    /// the source for messages
+    pub source: Option<Requires<'amqp, Source>>,
+    /// the target for messages
+    pub target: Option<Requires<'amqp, Target>>,
+    /// unsettled delivery state
+    pub unsettled: Option<HashMap<DeliveryTag<'amqp>, Requires<'amqp, DeliveryState>>>,
+    /// the unsettled map is incomplete
+    #[amqp(default = false)]
+    pub incomplete_unsettled: bool,
+    /// the sender's initial value for delivery-count, set when the role is sender
+    pub initial_delivery_count: Option<SequenceNo>,
+    /// the maximum message size supported by the link endpoint
+    pub max_message_size: Option<u64>,
+    /// the extension capabilities the sender supports
+    #[amqp(multiple)]
+    pub offered_capabilities: Vec<Symbol<'amqp>>,
+    /// the extension capabilities the sender can use if the receiver supports them
+    #[amqp(multiple)]
+    pub desired_capabilities: Vec<Symbol<'amqp>>,
+    /// link properties
+    pub properties: Option<Fields>,
 }
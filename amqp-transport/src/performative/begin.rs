@@ -1,22 +1,33 @@
-
-
 // descriptor name="amqp:begin:list" code="0x00000000:0x00000011"
 
-use amqp_types::{Symbol, Type};
+use amqp_types::{primitive::Symbol, Type};
 
-use crate::definitions::{TransferNumber, Handle, Fields};
+use crate::definitions::{Fields, Handle, TransferNumber};
 
-#[derive(Debug, Type)]
-#[amqp(descriptor = 0x00000000:0x00000011)]
-pub struct Begin {
-    remote_channel: Option<u16>,
-    next_outgoing_i: TransferNumber,
-    incoming_window: u32,
-    outgoing_window: u32,
+/// Begin a session on a channel.
+#[derive(Debug, Clone, Type)]
+#[amqp(descriptor(name = "amqp:begin:list", code = 0x0000_0000_0000_0011))]
+pub struct Begin<'amqp> {
+    /// the remote channel for this session, set when the begin answers one
+    pub remote_channel: Option<u16>,
+    /// the transfer-id of the first transfer id the sender will send
+    #[amqp(mandatory)]
+    pub next_outgoing_id: TransferNumber,
+    /// the initial incoming-window of the sender
+    #[amqp(mandatory)]
+    pub incoming_window: u32,
+    /// the initial outgoing-window of the sender
+    #[amqp(mandatory)]
+    pub outgoing_window: u32,
+    /// the maximum handle value that can be used on the session
     #[amqp(default = Handle(u32::MAX))]
-    handle_max: Handle,
-    offered_capabilities: Vec<Symbol>,
-    desired_capabilities: Vec<Symbol>,
-    properties: Option<Fields>,
+    pub handle_max: Handle,
+    /// the extension capabilities the sender supports
+    #[amqp(multiple)]
+    pub offered_capabilities: Vec<Symbol<'amqp>>,
+    /// the extension capabilities the sender can use if the receiver supports them
+    #[amqp(multiple)]
+    pub desired_capabilities: Vec<Symbol<'amqp>>,
+    /// session properties
+    pub properties: Option<Fields>,
 }
-
@@ -0,0 +1,13 @@
+// descriptor name="amqp:close:list" code="0x00000000:0x00000018"
+
+use amqp_types::Type;
+
+use crate::definitions::Error;
+
+/// Signal a connection close.
+#[derive(Debug, Clone, Type)]
+#[amqp(descriptor(name = "amqp:close:list", code = 0x0000_0000_0000_0018))]
+pub struct Close<'amqp> {
+    /// error causing the close
+    pub error: Option<Error<'amqp>>,
+}
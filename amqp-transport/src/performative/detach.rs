@@ -0,0 +1,19 @@
+// descriptor name="amqp:detach:list" code="0x00000000:0x00000016"
+
+use amqp_types::Type;
+
+use crate::definitions::{Error, Handle};
+
+/// Detach the link endpoint from the session.
+#[derive(Debug, Clone, Type)]
+#[amqp(descriptor(name = "amqp:detach:list", code = 0x0000_0000_0000_0016))]
+pub struct Detach<'amqp> {
+    /// the link the detach is for
+    #[amqp(mandatory)]
+    pub handle: Handle,
+    /// if true then the sender has closed the link
+    #[amqp(default = false)]
+    pub closed: bool,
+    /// error causing the detach
+    pub error: Option<Error<'amqp>>,
+}
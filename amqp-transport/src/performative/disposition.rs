@@ -0,0 +1,27 @@
+// descriptor name="amqp:disposition:list" code="0x00000000:0x00000015"
+
+use amqp_types::{provides::Requires, Type};
+
+use crate::definitions::{DeliveryNumber, DeliveryState, Role};
+
+/// Inform remote peer of delivery state changes.
+#[derive(Debug, Clone, Type)]
+#[amqp(descriptor(name = "amqp:disposition:list", code = 0x0000_0000_0000_0015))]
+pub struct Disposition<'amqp> {
+    /// directionality of disposition
+    #[amqp(mandatory)]
+    pub role: Role,
+    /// lower bound of deliveries
+    #[amqp(mandatory)]
+    pub first: DeliveryNumber,
+    /// upper bound of deliveries, `first` if unset
+    pub last: Option<DeliveryNumber>,
+    /// indicates deliveries are settled
+    #[amqp(default = false)]
+    pub settled: bool,
+    /// indicates state of deliveries
+    pub state: Option<Requires<'amqp, DeliveryState>>,
+    /// batchable hint
+    #[amqp(default = false)]
+    pub batchable: bool,
+}
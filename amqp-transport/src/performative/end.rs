@@ -0,0 +1,13 @@
+// descriptor name="amqp:end:list" code="0x00000000:0x00000017"
+
+use amqp_types::Type;
+
+use crate::definitions::Error;
+
+/// End the session.
+#[derive(Debug, Clone, Type)]
+#[amqp(descriptor(name = "amqp:end:list", code = 0x0000_0000_0000_0017))]
+pub struct End<'amqp> {
+    /// error causing the end
+    pub error: Option<Error<'amqp>>,
+}
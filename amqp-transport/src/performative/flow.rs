@@ -0,0 +1,41 @@
+// descriptor name="amqp:flow:list" code="0x00000000:0x00000013"
+
+use amqp_types::Type;
+
+use crate::definitions::{Fields, Handle, SequenceNo, TransferNumber};
+
+/// Update link state.
+///
+/// The link fields are only set when the flow is for a link, `handle` being set.
+#[derive(Debug, Clone, Type)]
+#[amqp(descriptor(name = "amqp:flow:list", code = 0x0000_0000_0000_0013))]
+pub struct Flow {
+    /// the expected transfer-id of the next incoming transfer frame, unset until the begin of
+    /// the peer is received
+    pub next_incoming_id: Option<TransferNumber>,
+    /// the incoming-window of the sender
+    #[amqp(mandatory)]
+    pub incoming_window: u32,
+    /// the transfer-id that will be assigned to the next outgoing transfer frame
+    #[amqp(mandatory)]
+    pub next_outgoing_id: TransferNumber,
+    /// the outgoing-window of the sender
+    #[amqp(mandatory)]
+    pub outgoing_window: u32,
+    /// the handle of the link the flow state is for
+    pub handle: Option<Handle>,
+    /// the delivery-count of the link endpoint of the sender
+    pub delivery_count: Option<SequenceNo>,
+    /// the current maximum number of messages that can be received
+    pub link_credit: Option<u32>,
+    /// the number of available messages
+    pub available: Option<u32>,
+    /// indicates drain mode
+    #[amqp(default = false)]
+    pub drain: bool,
+    /// request state from partner
+    #[amqp(default = false)]
+    pub echo: bool,
+    /// link state properties
+    pub properties: Option<Fields>,
+}
@@ -1,37 +1,51 @@
-use std::collections::HashMap;
+// descriptor name="amqp:open:list" code="0x00000000:0x00000010"
 
-use amqp_types::{Symbol, Type};
+use amqp_types::{primitive::Symbol, Type};
 
-use crate::definitions::{Fields, IetfLanguageTag};
+use crate::definitions::{Fields, IetfLanguageTag, Milliseconds};
 
-#[derive(Debug, Type)]
-#[amqp(descriptor = 0x00000000:0x00000011)]
-pub struct Open {
+/// Negotiate connection parameters.
+#[derive(Debug, Clone, Type)]
+#[amqp(descriptor(name = "amqp:open:list", code = 0x0000_0000_0000_0010))]
+pub struct Open<'amqp> {
     /// the id of the source container
-    pub(crate) container_id: String,
+    #[amqp(mandatory)]
+    pub container_id: &'amqp str,
     /// the name of the target host
-    pub(crate) hostname: Option<String>,
+    pub hostname: Option<&'amqp str>,
     /// proposed maximum frame size
     #[amqp(default = u32::MAX)]
-    pub(crate) max_frame_size: u32,
+    pub max_frame_size: u32,
+    /// the maximum channel number that can be used on the connection
     #[amqp(default = u16::MAX)]
-    pub(crate) channel_max: u16,
-    pub(crate) idle_timeout: Option<u32>,
-    pub(crate) outgoing_locales: Vec<IetfLanguageTag>,
-    pub(crate) incoming_locales: Vec<IetfLanguageTag>,
-    pub(crate) offered_capabilities: Vec<Symbol>,
-    pub(crate) desired_capabilities: Vec<Symbol>,
-    pub(crate) properties: Option<Fields>,
+    pub channel_max: u16,
+    /// idle time-out
+    pub idle_time_out: Option<Milliseconds>,
+    /// locales available for outgoing text
+    #[amqp(multiple)]
+    pub outgoing_locales: Vec<IetfLanguageTag<'amqp>>,
+    /// desired locales for incoming text in decreasing level of preference
+    #[amqp(multiple)]
+    pub incoming_locales: Vec<IetfLanguageTag<'amqp>>,
+    /// the extension capabilities the sender supports
+    #[amqp(multiple)]
+    pub offered_capabilities: Vec<Symbol<'amqp>>,
+    /// the extension capabilities the sender can use if the receiver supports them
+    #[amqp(multiple)]
+    pub desired_capabilities: Vec<Symbol<'amqp>>,
+    /// connection properties
+    pub properties: Option<Fields>,
 }
 
-impl Default for Open {
-    fn default() -> Self {
+impl<'amqp> Open<'amqp> {
+    /// An open with `container_id` and every other field left to its default.
+    pub fn new(container_id: &'amqp str) -> Self {
         Self {
-            container_id: String::new(),
+            container_id,
             hostname: None,
             max_frame_size: u32::MAX,
             channel_max: u16::MAX,
-            idle_timeout: None,
+            idle_time_out: None,
             outgoing_locales: Vec::new(),
             incoming_locales: Vec::new(),
             offered_capabilities: Vec::new(),
@@ -0,0 +1,40 @@
+// descriptor name="amqp:transfer:list" code="0x00000000:0x00000014"
+
+use amqp_types::{provides::Requires, Type};
+
+use crate::definitions::{DeliveryNumber, DeliveryState, DeliveryTag, Handle, ReceiverSettleMode};
+
+/// Transfer a message.
+///
+/// The payload follows the performative in the frame body, it isn't part of it.
+#[derive(Debug, Clone, Type)]
+#[amqp(descriptor(name = "amqp:transfer:list", code = 0x0000_0000_0000_0014))]
+pub struct Transfer<'amqp> {
+    /// the link the transfer is on
+    #[amqp(mandatory)]
+    pub handle: Handle,
+    /// alias for delivery-tag, set on the first transfer of a delivery
+    pub delivery_id: Option<DeliveryNumber>,
+    /// the delivery-tag, set on the first transfer of a delivery
+    pub delivery_tag: Option<DeliveryTag<'amqp>>,
+    /// indicates the message format, set on the first transfer of a delivery
+    pub message_format: Option<u32>,
+    /// the settlement status of the delivery at the sender
+    pub settled: Option<bool>,
+    /// indicates that the message has more content
+    #[amqp(default = false)]
+    pub more: bool,
+    /// the settlement policy of the receiver for this delivery
+    pub rcv_settle_mode: Option<ReceiverSettleMode>,
+    /// the state of the delivery at the sender
+    pub state: Option<Requires<'amqp, DeliveryState>>,
+    /// indicates a resumed delivery
+    #[amqp(default = false)]
+    pub resume: bool,
+    /// indicates that the message is aborted
+    #[amqp(default = false)]
+    pub aborted: bool,
+    /// batchable hint
+    #[amqp(default = false)]
+    pub batchable: bool,
+}
@@ -3,11 +3,565 @@
 //! for related links. Sessions serve as the context for link communication. Any number of links of any
 //! directionality can be attached to a given Session. However, a link may be attached to at most one
 //! Session at a time.
-//! 
-//! 
-//! 
+//!
+//! The session endpoints of a connection, section 2.5 of the specification. A session is known by
+//! the channel we send its frames on, mapped to the channel the peer sends its frames on once its
+//! begin is received.
+use std::{collections::BTreeMap, io};
+
+use amqp_types::{codec::Encode, provides::Provide};
+
+use crate::{
+    connections::{IncomingChannelEndpoint, OutgoingChannelEndpoint},
+    definitions::{
+        AmqpError, ConnectionError, Error, ErrorCondition, Handle, SequenceNo, SessionError,
+        TransferNumber,
+    },
+    framing::AmqpBody,
+    performative::{Begin, End, Flow, Performative, Transfer},
+    sm::connection::{Connection, ILLEGAL_STATE},
+};
+
+pub const NO_FREE_CHANNEL: &str = "every channel below the channel max of the peer is in use";
+pub const UNKNOWN_SESSION: &str = "no session on the channel";
+pub const CHANNEL_IN_USE: &str = "begin on a channel already mapped to a session";
+pub const CHANNEL_MAX_EXCEEDED: &str = "channel above the channel max";
+pub const HANDLE_MAX_EXCEEDED: &str = "handle above the handle max of the session";
+pub const WINDOW_CLOSED: &str = "the session window is closed";
+pub const WINDOW_VIOLATION: &str = "transfer beyond the incoming window";
+
+/// The states of section 2.5.5, a session is unmapped once ended or before it begins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SessionState {
+    Unmapped,
+    /// our begin sent, the begin of the peer not received
+    BeginSent,
+    /// the begin of the peer received, ours not sent
+    BeginRcvd,
+    /// begins exchanged
+    Mapped,
+    /// our end sent, the end of the peer not received
+    EndSent,
+    /// the end of the peer received, ours not sent
+    EndRcvd,
+    /// our end sent on an error, incoming frames are dropped until the end of the peer
+    Discarding,
+}
+
+/// What our begin announces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionConfig {
+    /// the transfer-id of our first transfer
+    pub next_outgoing_id: TransferNumber,
+    /// the transfers we accept before a flow opens the window again
+    pub incoming_window: u32,
+    /// the transfers we may send before a flow opens the window again
+    pub outgoing_window: u32,
+    /// the largest handle the peer may attach a link with
+    pub handle_max: Handle,
+}
 
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            next_outgoing_id: SequenceNo(0),
+            incoming_window: 2048,
+            outgoing_window: u32::MAX,
+            handle_max: Handle(u32::MAX),
+        }
+    }
+}
 
+/// A session endpoint, with the state of section 2.5.6.
+#[derive(Debug, Clone)]
+pub struct Session {
+    state: SessionState,
+    outgoing: OutgoingChannelEndpoint,
+    incoming: Option<IncomingChannelEndpoint>,
+    handle_max: Handle,
+    remote_handle_max: Handle,
+    initial_outgoing_id: TransferNumber,
+    next_outgoing_id: TransferNumber,
+    outgoing_window: u32,
+    next_incoming_id: TransferNumber,
+    incoming_window: u32,
+    remote_incoming_window: u32,
+    remote_outgoing_window: u32,
+}
+
+impl Session {
+    fn new(outgoing: OutgoingChannelEndpoint, state: SessionState) -> Self {
+        Self {
+            state,
+            outgoing,
+            incoming: None,
+            handle_max: Handle(u32::MAX),
+            remote_handle_max: Handle(u32::MAX),
+            initial_outgoing_id: SequenceNo(0),
+            next_outgoing_id: SequenceNo(0),
+            outgoing_window: 0,
+            next_incoming_id: SequenceNo(0),
+            incoming_window: 0,
+            remote_incoming_window: 0,
+            remote_outgoing_window: 0,
+        }
+    }
+    pub fn state(&self) -> SessionState {
+        self.state
+    }
+    pub fn outgoing_channel(&self) -> OutgoingChannelEndpoint {
+        self.outgoing
+    }
+    /// The channel of the peer, once its begin is received.
+    pub fn incoming_channel(&self) -> Option<IncomingChannelEndpoint> {
+        self.incoming
+    }
+    pub fn handle_max(&self) -> Handle {
+        self.handle_max
+    }
+    /// The largest handle we may attach a link with.
+    pub fn remote_handle_max(&self) -> Handle {
+        self.remote_handle_max
+    }
+    pub fn next_outgoing_id(&self) -> TransferNumber {
+        self.next_outgoing_id
+    }
+    pub fn outgoing_window(&self) -> u32 {
+        self.outgoing_window
+    }
+    pub fn next_incoming_id(&self) -> TransferNumber {
+        self.next_incoming_id
+    }
+    pub fn incoming_window(&self) -> u32 {
+        self.incoming_window
+    }
+    /// The transfers we may send before the incoming window of the peer is exhausted.
+    pub fn remote_incoming_window(&self) -> u32 {
+        self.remote_incoming_window
+    }
+    /// The transfers the peer may send before its outgoing window is exhausted.
+    pub fn remote_outgoing_window(&self) -> u32 {
+        self.remote_outgoing_window
+    }
+    /// A flow carrying the state of the session, for the link fields to be filled.
+    pub fn flow(&self) -> Flow {
+        Flow {
+            next_incoming_id: self.incoming.map(|_| self.next_incoming_id),
+            incoming_window: self.incoming_window,
+            next_outgoing_id: self.next_outgoing_id,
+            outgoing_window: self.outgoing_window,
+            handle: None,
+            delivery_count: None,
+            link_credit: None,
+            available: None,
+            drain: false,
+            echo: false,
+            properties: None,
+        }
+    }
+
+    fn configure(&mut self, config: SessionConfig) {
+        self.handle_max = config.handle_max;
+        self.initial_outgoing_id = config.next_outgoing_id;
+        self.next_outgoing_id = config.next_outgoing_id;
+        self.outgoing_window = config.outgoing_window;
+        self.incoming_window = config.incoming_window;
+    }
+    fn begin(&self, remote_channel: Option<u16>) -> Begin<'static> {
+        Begin {
+            remote_channel,
+            next_outgoing_id: self.next_outgoing_id,
+            incoming_window: self.incoming_window,
+            outgoing_window: self.outgoing_window,
+            handle_max: self.handle_max,
+            offered_capabilities: Vec::new(),
+            desired_capabilities: Vec::new(),
+            properties: None,
+        }
+    }
+    fn begun(&mut self, incoming: IncomingChannelEndpoint, begin: &Begin<'_>) {
+        self.incoming = Some(incoming);
+        self.remote_handle_max = begin.handle_max;
+        self.next_incoming_id = begin.next_outgoing_id;
+        self.remote_incoming_window = begin.incoming_window;
+        self.remote_outgoing_window = begin.outgoing_window;
+    }
+    /// Apply a flow of the peer, returns whether it asks for ours.
+    fn flowed(&mut self, flow: &Flow) -> bool {
+        let next_incoming_id = flow.next_incoming_id.unwrap_or(self.initial_outgoing_id);
+        self.remote_incoming_window =
+            (next_incoming_id + flow.incoming_window) - self.next_outgoing_id;
+        self.next_incoming_id = flow.next_outgoing_id;
+        self.remote_outgoing_window = flow.outgoing_window;
+        flow.echo && flow.handle.is_none()
+    }
+    /// Count a transfer of the peer, fails if it's beyond our incoming window.
+    fn received_transfer(&mut self) -> bool {
+        if self.incoming_window == 0 {
+            return false;
+        }
+        self.next_incoming_id += 1;
+        self.incoming_window -= 1;
+        self.remote_outgoing_window = self.remote_outgoing_window.saturating_sub(1);
+        true
+    }
+}
+
+/// A frame of the peer read by its session.
+// borrowed from the frame and short lived, as the performatives it holds
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+pub enum SessionReceived<'f> {
+    /// a frame dropped while [`SessionState::Discarding`]
+    Nothing,
+    /// the begin of the peer, answering ours or to be answered with [`Sessions::accept`] or
+    /// [`Sessions::end`]
+    Begin {
+        channel: OutgoingChannelEndpoint,
+        begin: Begin<'f>,
+    },
+    /// the end of the peer, to be answered with [`Sessions::end`] if ours wasn't sent
+    End {
+        channel: OutgoingChannelEndpoint,
+        end: End<'f>,
+    },
+    /// a flow of the peer for the session, its windows already applied
+    Flow {
+        channel: OutgoingChannelEndpoint,
+        flow: Flow,
+    },
+    /// a frame for the links of the session, flows with a handle included
+    Link {
+        channel: OutgoingChannelEndpoint,
+        body: AmqpBody<'f>,
+    },
+}
+
+/// The sessions of a connection, by channel.
+///
+/// Frames are written through the [`Connection`], and the frames of the peer it hands over as
+/// [`Received::Session`](crate::sm::connection::Received::Session) are given to
+/// [`Sessions::handle`]. A peer breaking the session protocol ends the session with an error,
+/// breaking the channel mapping closes the connection, and the error is returned.
+#[derive(Debug, Default)]
 pub struct Sessions {
-    
-}
\ No newline at end of file
+    sessions: BTreeMap<OutgoingChannelEndpoint, Session>,
+    incoming: BTreeMap<IncomingChannelEndpoint, OutgoingChannelEndpoint>,
+}
+
+impl Sessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn get(&self, channel: OutgoingChannelEndpoint) -> Option<&Session> {
+        self.sessions.get(&channel)
+    }
+    pub fn state(&self, channel: OutgoingChannelEndpoint) -> SessionState {
+        self.get(channel)
+            .map_or(SessionState::Unmapped, Session::state)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Session> {
+        self.sessions.values()
+    }
+
+    /// Begin a session on the lowest free channel.
+    pub fn begin(
+        &mut self,
+        connection: &mut Connection,
+        config: SessionConfig,
+    ) -> io::Result<OutgoingChannelEndpoint> {
+        let channel = self.free_channel(connection.remote_channel_max())?;
+        let mut session = Session::new(channel, SessionState::BeginSent);
+        session.configure(config);
+        connection.send(channel.number, session.begin(None), &[])?;
+        self.sessions.insert(channel, session);
+        Ok(channel)
+    }
+
+    /// Answer the begin of the peer.
+    pub fn accept(
+        &mut self,
+        connection: &mut Connection,
+        channel: OutgoingChannelEndpoint,
+        config: SessionConfig,
+    ) -> io::Result<()> {
+        let session = self.session_in(channel, &[SessionState::BeginRcvd])?;
+        session.configure(config);
+        let remote_channel = session.incoming.map(|incoming| incoming.number);
+        connection.send(channel.number, session.begin(remote_channel), &[])?;
+        session.state = SessionState::Mapped;
+        Ok(())
+    }
+
+    /// Send our end, after answering the begin of the peer with the default config if it wasn't.
+    ///
+    /// Ending on an error drops the frames of the peer until its end. A session ended before the
+    /// peer answered its begin still reads the answer, then waits for the end of the peer.
+    pub fn end(
+        &mut self,
+        connection: &mut Connection,
+        channel: OutgoingChannelEndpoint,
+        error: Option<Error<'_>>,
+    ) -> io::Result<()> {
+        if self.state(channel) == SessionState::BeginRcvd {
+            self.accept(connection, channel, SessionConfig::default())?;
+        }
+        let session = self.session_in(
+            channel,
+            &[
+                SessionState::BeginSent,
+                SessionState::Mapped,
+                SessionState::EndRcvd,
+            ],
+        )?;
+        let next = match session.state {
+            SessionState::EndRcvd => SessionState::Unmapped,
+            _ if error.is_some() => SessionState::Discarding,
+            _ => SessionState::EndSent,
+        };
+        connection.send(channel.number, End { error }, &[])?;
+        session.state = next;
+        if next == SessionState::Unmapped {
+            self.unmap(channel);
+        }
+        Ok(())
+    }
+
+    /// Send a transfer, taking a transfer-id within the windows.
+    pub fn transfer(
+        &mut self,
+        connection: &mut Connection,
+        channel: OutgoingChannelEndpoint,
+        transfer: Transfer<'_>,
+        payload: &[u8],
+    ) -> io::Result<()> {
+        let session = self.session_in(channel, &[SessionState::Mapped])?;
+        if session.remote_incoming_window == 0 || session.outgoing_window == 0 {
+            return Err(io::Error::new(io::ErrorKind::WouldBlock, WINDOW_CLOSED));
+        }
+        connection.send(channel.number, transfer, payload)?;
+        session.next_outgoing_id += 1;
+        session.remote_incoming_window -= 1;
+        Ok(())
+    }
+
+    /// Send another frame of a link of the session.
+    pub fn send<T: Encode>(
+        &mut self,
+        connection: &mut Connection,
+        channel: OutgoingChannelEndpoint,
+        performative: T,
+    ) -> io::Result<()> {
+        self.session_in(
+            channel,
+            &[
+                SessionState::BeginSent,
+                SessionState::Mapped,
+                SessionState::EndRcvd,
+            ],
+        )?;
+        connection.send(channel.number, performative, &[])
+    }
+
+    /// Set our incoming window and tell the peer.
+    pub fn set_incoming_window(
+        &mut self,
+        connection: &mut Connection,
+        channel: OutgoingChannelEndpoint,
+        incoming_window: u32,
+    ) -> io::Result<()> {
+        let session = self.session_in(channel, &[SessionState::Mapped])?;
+        session.incoming_window = incoming_window;
+        connection.send(channel.number, session.flow(), &[])
+    }
+
+    /// Read a frame of the peer on `channel`.
+    pub fn handle<'f>(
+        &mut self,
+        connection: &mut Connection,
+        channel: u16,
+        body: AmqpBody<'f>,
+    ) -> io::Result<SessionReceived<'f>> {
+        let incoming = IncomingChannelEndpoint { number: channel };
+        if channel > connection.config().channel_max {
+            return Err(fail(
+                connection,
+                ConnectionError::FramingError,
+                CHANNEL_MAX_EXCEEDED,
+            ));
+        }
+        let performative = match body.performative {
+            Performative::Begin(begin) => return self.handle_begin(connection, incoming, begin),
+            performative => performative,
+        };
+        let Some(&outgoing) = self.incoming.get(&incoming) else {
+            return Err(fail(connection, AmqpError::IllegalState, UNKNOWN_SESSION));
+        };
+        let session = self.sessions.get_mut(&outgoing).expect("mapped");
+        match session.state {
+            SessionState::Discarding => {
+                if let Performative::End(end) = performative {
+                    self.unmap(outgoing);
+                    return Ok(SessionReceived::End {
+                        channel: outgoing,
+                        end,
+                    });
+                }
+                return Ok(SessionReceived::Nothing);
+            }
+            SessionState::Mapped | SessionState::EndSent => (),
+            _ => return Err(fail(connection, AmqpError::IllegalState, ILLEGAL_STATE)),
+        }
+        match performative {
+            Performative::End(end) => {
+                match session.state {
+                    SessionState::EndSent => self.unmap(outgoing),
+                    _ => session.state = SessionState::EndRcvd,
+                }
+                Ok(SessionReceived::End {
+                    channel: outgoing,
+                    end,
+                })
+            }
+            Performative::Flow(flow) if flow.handle.is_none() => {
+                if session.flowed(&flow) {
+                    connection.send(outgoing.number, session.flow(), &[])?;
+                }
+                Ok(SessionReceived::Flow {
+                    channel: outgoing,
+                    flow,
+                })
+            }
+            Performative::Flow(flow) => {
+                session.flowed(&flow);
+                Ok(SessionReceived::Link {
+                    channel: outgoing,
+                    body: AmqpBody {
+                        performative: Performative::Flow(flow),
+                        payload: body.payload,
+                    },
+                })
+            }
+            Performative::Transfer(transfer) => {
+                if !session.received_transfer() {
+                    let ended = Error::new(SessionError::WindowViolation, WINDOW_VIOLATION)
+                        .and_then(|error| self.end(connection, outgoing, Some(error)));
+                    return Err(match ended {
+                        Ok(()) => io::Error::new(io::ErrorKind::InvalidData, WINDOW_VIOLATION),
+                        Err(error) => error,
+                    });
+                }
+                Ok(SessionReceived::Link {
+                    channel: outgoing,
+                    body: AmqpBody {
+                        performative: Performative::Transfer(transfer),
+                        payload: body.payload,
+                    },
+                })
+            }
+            Performative::Attach(attach) if attach.handle.0 > session.handle_max.0 => Err(fail(
+                connection,
+                ConnectionError::FramingError,
+                HANDLE_MAX_EXCEEDED,
+            )),
+            Performative::Open(_) | Performative::Close(_) | Performative::Begin(_) => {
+                Err(fail(connection, AmqpError::IllegalState, ILLEGAL_STATE))
+            }
+            performative => Ok(SessionReceived::Link {
+                channel: outgoing,
+                body: AmqpBody {
+                    performative,
+                    payload: body.payload,
+                },
+            }),
+        }
+    }
+
+    fn handle_begin<'f>(
+        &mut self,
+        connection: &mut Connection,
+        incoming: IncomingChannelEndpoint,
+        begin: Begin<'f>,
+    ) -> io::Result<SessionReceived<'f>> {
+        if self.incoming.contains_key(&incoming) {
+            return Err(fail(connection, AmqpError::IllegalState, CHANNEL_IN_USE));
+        }
+        let channel = match begin.remote_channel {
+            // the answer to our begin
+            Some(number) => {
+                let channel = OutgoingChannelEndpoint { number };
+                match self.sessions.get_mut(&channel) {
+                    Some(session) if session.state == SessionState::BeginSent => {
+                        session.state = SessionState::Mapped;
+                    }
+                    // ended before the answer came, the end of the peer is still to come
+                    Some(session)
+                        if session.incoming.is_none()
+                            && matches!(
+                                session.state,
+                                SessionState::EndSent | SessionState::Discarding
+                            ) => {}
+                    _ => return Err(fail(connection, AmqpError::IllegalState, UNKNOWN_SESSION)),
+                }
+                channel
+            }
+            None => {
+                let channel = match self.free_channel(connection.remote_channel_max()) {
+                    Ok(channel) => channel,
+                    Err(_) => {
+                        return Err(fail(
+                            connection,
+                            AmqpError::ResourceLimitExceeded,
+                            NO_FREE_CHANNEL,
+                        ))
+                    }
+                };
+                self.sessions
+                    .insert(channel, Session::new(channel, SessionState::BeginRcvd));
+                channel
+            }
+        };
+        let session = self.sessions.get_mut(&channel).expect("inserted");
+        session.begun(incoming, &begin);
+        self.incoming.insert(incoming, channel);
+        Ok(SessionReceived::Begin { channel, begin })
+    }
+
+    fn session_in(
+        &mut self,
+        channel: OutgoingChannelEndpoint,
+        states: &[SessionState],
+    ) -> io::Result<&mut Session> {
+        match self.sessions.get_mut(&channel) {
+            Some(session) if states.contains(&session.state) => Ok(session),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, ILLEGAL_STATE)),
+        }
+    }
+
+    /// The lowest channel not mapped to a session.
+    fn free_channel(&self, channel_max: u16) -> io::Result<OutgoingChannelEndpoint> {
+        (0..=channel_max)
+            .map(|number| OutgoingChannelEndpoint { number })
+            .find(|channel| !self.sessions.contains_key(channel))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::WouldBlock, NO_FREE_CHANNEL))
+    }
+
+    fn unmap(&mut self, channel: OutgoingChannelEndpoint) {
+        if let Some(session) = self.sessions.remove(&channel) {
+            if let Some(incoming) = session.incoming {
+                self.incoming.remove(&incoming);
+            }
+        }
+    }
+}
+
+/// Close the connection on `condition`, the channel mapping being broken.
+fn fail<C: Provide<ErrorCondition> + Encode>(
+    connection: &mut Connection,
+    condition: C,
+    description: &'static str,
+) -> io::Error {
+    // the error is returned whether the close could be sent or not
+    let _ = Error::new(condition, description).and_then(|error| connection.close(Some(error)));
+    io::Error::new(io::ErrorKind::InvalidData, description)
+}
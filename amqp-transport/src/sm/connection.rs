@@ -0,0 +1,478 @@
+//! The connection endpoint, section 2.4 of the specification.
+//!
+//! | state      | sent           | received | then                |
+//! |------------|----------------|----------|---------------------|
+//! | START      | -              | -        |                     |
+//! | HDR_RCVD   | -              | HDR      |                     |
+//! | HDR_SENT   | HDR            | -        |                     |
+//! | HDR_EXCH   | HDR            | HDR      |                     |
+//! | OPEN_PIPE  | HDR OPEN       | -        |                     |
+//! | OC_PIPE    | HDR OPEN CLOSE | -        | TCP close for write |
+//! | OPEN_RCVD  | HDR            | HDR OPEN |                     |
+//! | OPEN_SENT  | HDR OPEN       | HDR      |                     |
+//! | CLOSE_PIPE | HDR OPEN CLOSE | HDR      | TCP close for write |
+//! | OPENED     | HDR OPEN       | HDR OPEN |                     |
+//! | CLOSE_RCVD | *              | CLOSE    | TCP close for read  |
+//! | CLOSE_SENT | CLOSE          | *        | TCP close for write |
+//! | DISCARDING | CLOSE          | *        | TCP close for write |
+//! | END        | CLOSE          | CLOSE    | TCP close           |
+use std::{collections::VecDeque, io, mem};
+
+use amqp_types::{codec::Encode, provides::Provide};
+use bytes::{Bytes, BytesMut};
+
+use crate::{
+    definitions::{
+        AmqpError, ConnectionError, Error, ErrorCondition, Milliseconds, MIN_MAX_FRAME_SIZE,
+    },
+    framing::{
+        write_amqp_frame, write_heartbeat, AmqpBody, Frame, FrameHeader, FRAME_TOO_LARGE,
+        UNEXPECTED_FRAME_TYPE,
+    },
+    performative::{Close, Open, Performative},
+    version::{negotiate, Negotiation, Version, UNSUPPORTED_HEADER},
+};
+
+pub const ILLEGAL_STATE: &str = "not allowed in the state of the connection";
+pub const UNEXPECTED_HEADER: &str = "protocol header received after the header exchange";
+pub const UNEXPECTED_FRAME: &str = "frame not allowed in the state of the connection";
+pub const MAX_FRAME_SIZE_TOO_SMALL: &str = "max frame size is smaller than 512";
+
+/// The states of section 2.4.6.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectionState {
+    /// nothing sent or received
+    Start,
+    /// the header of the peer received, ours not sent
+    HdrRcvd,
+    /// our header sent, the header of the peer not received
+    HdrSent,
+    /// headers exchanged, no open sent or received
+    HdrExch,
+    /// our header and open sent, the header of the peer not received
+    OpenPipe,
+    /// our header, open and close sent, the header of the peer not received
+    OcPipe,
+    /// the open of the peer received, ours not sent
+    OpenRcvd,
+    /// our open sent, the open of the peer not received
+    OpenSent,
+    /// our open and close sent, the open of the peer not received
+    ClosePipe,
+    /// opens exchanged
+    Opened,
+    /// the close of the peer received, ours not sent
+    CloseRcvd,
+    /// our close sent, the close of the peer not received
+    CloseSent,
+    /// our close sent on an error, incoming frames are dropped until the close of the peer
+    Discarding,
+    /// closes exchanged, the socket can be closed
+    End,
+}
+
+/// A change of [`ConnectionState`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    pub from: ConnectionState,
+    pub to: ConnectionState,
+}
+
+/// What our open announces.
+#[derive(Debug, Clone)]
+pub struct ConnectionConfig {
+    pub container_id: String,
+    pub hostname: Option<String>,
+    /// the largest frame accepted from the peer
+    pub max_frame_size: u32,
+    /// the largest channel number the peer may use
+    pub channel_max: u16,
+    pub idle_time_out: Option<Milliseconds>,
+}
+
+impl ConnectionConfig {
+    /// A config with `container_id` and the defaults of the open for the rest.
+    pub fn new(container_id: impl Into<String>) -> Self {
+        Self {
+            container_id: container_id.into(),
+            hostname: None,
+            max_frame_size: u32::MAX,
+            channel_max: u16::MAX,
+            idle_time_out: None,
+        }
+    }
+}
+
+/// A frame read in the current state.
+// borrowed from the frame and short lived, as the performatives it holds
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+pub enum Received<'f> {
+    /// a heartbeat, or a frame dropped while [`ConnectionState::Discarding`]
+    Nothing,
+    /// the open of the peer, its limits already applied
+    Open(Open<'f>),
+    /// the close of the peer, to be answered with [`Connection::close`]
+    Close(Close<'f>),
+    /// a frame for the session on `channel`
+    Session { channel: u16, body: AmqpBody<'f> },
+}
+
+/// The AMQP layer of a connection, from the protocol header to the close.
+///
+/// Headers and frames read from the socket are given to [`Connection::handle_header`] and
+/// [`Connection::handle_frame`], the bytes to write are taken with [`Connection::take_outgoing`].
+/// A peer breaking the protocol is answered with a close carrying the error, when it's still
+/// possible to send one, and the error is returned.
+#[derive(Debug)]
+pub struct Connection {
+    config: ConnectionConfig,
+    state: ConnectionState,
+    open_sent: bool,
+    /// our close carried an error
+    close_error: bool,
+    remote_max_frame_size: u32,
+    remote_channel_max: u16,
+    remote_idle_time_out: Option<Milliseconds>,
+    outgoing: BytesMut,
+    transitions: VecDeque<Transition>,
+}
+
+impl Connection {
+    pub fn new(config: ConnectionConfig) -> Self {
+        Self {
+            config,
+            state: ConnectionState::Start,
+            open_sent: false,
+            close_error: false,
+            remote_max_frame_size: MIN_MAX_FRAME_SIZE,
+            remote_channel_max: u16::MAX,
+            remote_idle_time_out: None,
+            outgoing: BytesMut::new(),
+            transitions: VecDeque::new(),
+        }
+    }
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+    pub fn config(&self) -> &ConnectionConfig {
+        &self.config
+    }
+    /// The largest frame the peer accepts, [`MIN_MAX_FRAME_SIZE`] until its open is received.
+    pub fn remote_max_frame_size(&self) -> u32 {
+        self.remote_max_frame_size
+    }
+    pub fn remote_channel_max(&self) -> u16 {
+        self.remote_channel_max
+    }
+    /// The idle time-out of the peer, a frame must be sent at least every half of it.
+    pub fn remote_idle_time_out(&self) -> Option<Milliseconds> {
+        self.remote_idle_time_out
+    }
+    /// The next state change not yet reported, oldest first.
+    pub fn poll_transition(&mut self) -> Option<Transition> {
+        self.transitions.pop_front()
+    }
+    pub fn has_outgoing(&self) -> bool {
+        !self.outgoing.is_empty()
+    }
+    /// The bytes to write to the peer, in order.
+    pub fn take_outgoing(&mut self) -> Bytes {
+        self.outgoing.split().freeze()
+    }
+
+    /// Send our protocol header.
+    pub fn send_header(&mut self) -> io::Result<()> {
+        let next = match self.state {
+            ConnectionState::Start => ConnectionState::HdrSent,
+            ConnectionState::HdrRcvd => ConnectionState::HdrExch,
+            _ => return Err(illegal_state()),
+        };
+        self.outgoing
+            .extend_from_slice(&Version::V_1_0_0.as_bytes());
+        self.transition(next);
+        Ok(())
+    }
+
+    /// Send our open, after our header if it wasn't sent.
+    pub fn open(&mut self) -> io::Result<()> {
+        if self.state == ConnectionState::Start {
+            self.send_header()?;
+        }
+        let next = match self.state {
+            ConnectionState::HdrSent => ConnectionState::OpenPipe,
+            ConnectionState::HdrExch => ConnectionState::OpenSent,
+            ConnectionState::OpenRcvd => ConnectionState::Opened,
+            _ => return Err(illegal_state()),
+        };
+        self.write_open()?;
+        self.transition(next);
+        Ok(())
+    }
+
+    /// Send our close, after our header and open if they weren't sent.
+    ///
+    /// Closing on an error drops the frames of the peer until its close.
+    pub fn close(&mut self, error: Option<Error<'_>>) -> io::Result<()> {
+        match self.state {
+            ConnectionState::Start
+            | ConnectionState::HdrSent
+            | ConnectionState::HdrExch
+            | ConnectionState::OpenRcvd => self.open()?,
+            ConnectionState::CloseRcvd if !self.open_sent => self.write_open()?,
+            _ => (),
+        }
+        let next = match self.state {
+            ConnectionState::OpenPipe => ConnectionState::OcPipe,
+            ConnectionState::OpenSent => ConnectionState::ClosePipe,
+            ConnectionState::Opened if error.is_some() => ConnectionState::Discarding,
+            ConnectionState::Opened => ConnectionState::CloseSent,
+            ConnectionState::CloseRcvd => ConnectionState::End,
+            _ => return Err(illegal_state()),
+        };
+        self.close_error = error.is_some();
+        let close = Close { error };
+        write_amqp_frame(
+            &mut self.outgoing,
+            0,
+            close,
+            &[],
+            self.remote_max_frame_size,
+        )?;
+        self.transition(next);
+        Ok(())
+    }
+
+    /// Send a frame of the session on `channel`, once our open is sent and until our close.
+    pub fn send<T: Encode>(
+        &mut self,
+        channel: u16,
+        performative: T,
+        payload: &[u8],
+    ) -> io::Result<()> {
+        let sending = matches!(
+            self.state,
+            ConnectionState::OpenPipe
+                | ConnectionState::OpenSent
+                | ConnectionState::Opened
+                | ConnectionState::CloseRcvd
+        );
+        if !sending || !self.open_sent || channel > self.remote_channel_max {
+            return Err(illegal_state());
+        }
+        write_amqp_frame(
+            &mut self.outgoing,
+            channel,
+            performative,
+            payload,
+            self.remote_max_frame_size,
+        )
+    }
+
+    /// Send an empty frame, keeping the connection open while idle.
+    ///
+    /// Fails before our header is sent, and once our close is since nothing may follow it.
+    pub fn heartbeat(&mut self) -> io::Result<()> {
+        match self.state {
+            ConnectionState::HdrSent
+            | ConnectionState::HdrExch
+            | ConnectionState::OpenPipe
+            | ConnectionState::OpenRcvd
+            | ConnectionState::OpenSent
+            | ConnectionState::Opened
+            | ConnectionState::CloseRcvd => {
+                write_heartbeat(&mut self.outgoing);
+                Ok(())
+            }
+            _ => Err(illegal_state()),
+        }
+    }
+
+    /// Read the protocol header of the peer, answering it with ours if it wasn't sent.
+    ///
+    /// An unsupported header ends the connection, after sending our header if it wasn't sent.
+    pub fn handle_header(&mut self, header: [u8; 8]) -> io::Result<()> {
+        let next = match self.state {
+            ConnectionState::Start => ConnectionState::HdrRcvd,
+            ConnectionState::HdrSent => ConnectionState::HdrExch,
+            ConnectionState::OpenPipe => ConnectionState::OpenSent,
+            ConnectionState::OcPipe => ConnectionState::ClosePipe,
+            _ => {
+                self.transition(ConnectionState::End);
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    UNEXPECTED_HEADER,
+                ));
+            }
+        };
+        if let Negotiation::Reject(version) = negotiate(header, &[Version::V_1_0_0]) {
+            if self.state == ConnectionState::Start {
+                self.outgoing.extend_from_slice(&version.as_bytes());
+            }
+            self.transition(ConnectionState::End);
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                UNSUPPORTED_HEADER,
+            ));
+        }
+        self.transition(next);
+        if next == ConnectionState::HdrRcvd {
+            self.send_header()?;
+        }
+        Ok(())
+    }
+
+    /// Read a frame of the peer.
+    pub fn handle_frame<'f>(&mut self, frame: Frame<'f>) -> io::Result<Received<'f>> {
+        match self.state {
+            ConnectionState::Start
+            | ConnectionState::HdrRcvd
+            | ConnectionState::HdrSent
+            | ConnectionState::OpenPipe
+            | ConnectionState::OcPipe
+            | ConnectionState::End => {
+                self.transition(ConnectionState::End);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, UNEXPECTED_FRAME));
+            }
+            ConnectionState::Discarding => return Ok(self.discard(frame)),
+            _ => (),
+        }
+        // the peer knows our max frame size once it reads our open
+        let max_frame_size = match self.open_sent {
+            true => self.config.max_frame_size,
+            false => MIN_MAX_FRAME_SIZE,
+        };
+        if frame.header.check_size(max_frame_size).is_err() {
+            return Err(self.fail(ConnectionError::FramingError, FRAME_TOO_LARGE));
+        }
+        if frame.header.frame_type != FrameHeader::AMQP {
+            return Err(self.fail(ConnectionError::FramingError, UNEXPECTED_FRAME_TYPE));
+        }
+        let body = match frame.amqp_body() {
+            Ok(Some(body)) => body,
+            Ok(None) => return Ok(Received::Nothing),
+            Err(error) => return Err(self.fail_with(AmqpError::DecodeError, error)),
+        };
+        match body.performative {
+            Performative::Open(open) => {
+                let next = match self.state {
+                    ConnectionState::HdrExch => ConnectionState::OpenRcvd,
+                    ConnectionState::OpenSent => ConnectionState::Opened,
+                    ConnectionState::ClosePipe if self.close_error => ConnectionState::Discarding,
+                    ConnectionState::ClosePipe => ConnectionState::CloseSent,
+                    _ => return Err(self.fail(AmqpError::IllegalState, UNEXPECTED_FRAME)),
+                };
+                if open.max_frame_size < MIN_MAX_FRAME_SIZE {
+                    return Err(self.fail(AmqpError::InvalidField, MAX_FRAME_SIZE_TOO_SMALL));
+                }
+                self.remote_max_frame_size = open.max_frame_size;
+                self.remote_channel_max = open.channel_max;
+                self.remote_idle_time_out = open.idle_time_out;
+                self.transition(next);
+                Ok(Received::Open(open))
+            }
+            Performative::Close(close) => {
+                let next = match self.state {
+                    ConnectionState::OpenRcvd | ConnectionState::Opened => {
+                        ConnectionState::CloseRcvd
+                    }
+                    ConnectionState::CloseSent => ConnectionState::End,
+                    _ => return Err(self.fail(AmqpError::IllegalState, UNEXPECTED_FRAME)),
+                };
+                self.transition(next);
+                Ok(Received::Close(close))
+            }
+            performative => match self.state {
+                ConnectionState::OpenRcvd
+                | ConnectionState::Opened
+                | ConnectionState::CloseSent => Ok(Received::Session {
+                    channel: frame.channel(),
+                    body: AmqpBody {
+                        performative,
+                        payload: body.payload,
+                    },
+                }),
+                _ => Err(self.fail(AmqpError::IllegalState, UNEXPECTED_FRAME)),
+            },
+        }
+    }
+
+    fn write_open(&mut self) -> io::Result<()> {
+        let config = &self.config;
+        let open = Open {
+            hostname: config.hostname.as_deref(),
+            max_frame_size: config.max_frame_size,
+            channel_max: config.channel_max,
+            idle_time_out: config.idle_time_out,
+            ..Open::new(&config.container_id)
+        };
+        write_amqp_frame(&mut self.outgoing, 0, open, &[], self.remote_max_frame_size)?;
+        self.open_sent = true;
+        Ok(())
+    }
+
+    /// Drop anything but the close of the peer.
+    fn discard<'f>(&mut self, frame: Frame<'f>) -> Received<'f> {
+        if frame.header.frame_type != FrameHeader::AMQP {
+            return Received::Nothing;
+        }
+        match frame.amqp_body() {
+            Ok(Some(AmqpBody {
+                performative: Performative::Close(close),
+                ..
+            })) => {
+                self.transition(ConnectionState::End);
+                Received::Close(close)
+            }
+            _ => Received::Nothing,
+        }
+    }
+
+    fn fail<C: Provide<ErrorCondition> + Encode>(
+        &mut self,
+        condition: C,
+        description: &'static str,
+    ) -> io::Error {
+        self.fail_with(
+            condition,
+            io::Error::new(io::ErrorKind::InvalidData, description),
+        )
+    }
+
+    /// Close on `condition` if a close can still be sent, else end.
+    fn fail_with<C: Provide<ErrorCondition> + Encode>(
+        &mut self,
+        condition: C,
+        error: io::Error,
+    ) -> io::Error {
+        let closing = matches!(
+            self.state,
+            ConnectionState::HdrExch
+                | ConnectionState::OpenRcvd
+                | ConnectionState::OpenSent
+                | ConnectionState::Opened
+                | ConnectionState::CloseRcvd
+        );
+        if closing {
+            let description = error.to_string();
+            let closed =
+                Error::new(condition, &description).and_then(|error| self.close(Some(error)));
+            if closed.is_ok() {
+                return error;
+            }
+        }
+        self.transition(ConnectionState::End);
+        error
+    }
+
+    fn transition(&mut self, to: ConnectionState) {
+        let from = mem::replace(&mut self.state, to);
+        if from != to {
+            self.transitions.push_back(Transition { from, to });
+        }
+    }
+}
+
+fn illegal_state() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, ILLEGAL_STATE)
+}
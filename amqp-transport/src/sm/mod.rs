@@ -0,0 +1,6 @@
+//! State machines of the endpoints, free of any IO.
+//!
+//! They are fed the headers and frames read from the peer, and write the bytes to send into a
+//! buffer the caller drains. The same machine is driven from tokio, from tests or from any other
+//! event loop.
+pub mod connection;
@@ -1,44 +1,89 @@
-use std::io::{self, Read, Write};
+//! The protocol header, section 2.2 of the specification.
+//!
+//! A connection opens with 8 bytes: `AMQP`, a protocol id naming the layer which follows, and the
+//! version. Each layer, TLS then SASL then AMQP, starts with its own header.
+use std::io::{self, Write};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
 use crate::definitions::{MAJOR, MINOR, REVISION};
-#[derive(Debug, PartialEq, Eq)]
+
+pub const INVALID_HEADER: &str = "not an AMQP protocol header";
+pub const UNSUPPORTED_HEADER: &str = "protocol header is not supported";
+
+/// The layer announced by a protocol header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProtocolId {
+    /// the AMQP frames
+    Amqp,
+    /// a TLS handshake, the next header follows inside the tunnel
+    Tls,
+    /// the SASL frames, then a new header
+    Sasl,
+    /// `0xd0`, a nonstandard id for plain AMQP, [`negotiate`] answers it with the AMQP id 0
+    Legacy,
+    /// an id the specification doesn't define
+    Other(u8),
+}
+
+impl ProtocolId {
+    pub const fn from_u8(id: u8) -> Self {
+        match id {
+            0 => ProtocolId::Amqp,
+            2 => ProtocolId::Tls,
+            3 => ProtocolId::Sasl,
+            0xd0 => ProtocolId::Legacy,
+            id => ProtocolId::Other(id),
+        }
+    }
+    pub const fn as_u8(self) -> u8 {
+        match self {
+            ProtocolId::Amqp => 0,
+            ProtocolId::Tls => 2,
+            ProtocolId::Sasl => 3,
+            ProtocolId::Legacy => 0xd0,
+            ProtocolId::Other(id) => id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Version {
+    pub protocol_id: ProtocolId,
     pub major: u8,
     pub minor: u8,
     pub revision: u8,
 }
 
 impl Version {
-    pub const V_1_0_0: Self = Version {
-        major: MAJOR,
-        minor: MINOR,
-        revision: REVISION,
-    };
+    pub const V_1_0_0: Self = Self::with_protocol_id(ProtocolId::Amqp);
+    pub const SASL_V_1_0_0: Self = Self::with_protocol_id(ProtocolId::Sasl);
+    pub const TLS_V_1_0_0: Self = Self::with_protocol_id(ProtocolId::Tls);
+
+    /// Version 1.0.0 of the layer `protocol_id`.
+    pub const fn with_protocol_id(protocol_id: ProtocolId) -> Self {
+        Version {
+            protocol_id,
+            major: MAJOR,
+            minor: MINOR,
+            revision: REVISION,
+        }
+    }
+
     pub fn write<W: Write>(self, writer: &mut W) -> io::Result<()> {
-        writer.write_all(&[
-            b'A',
-            b'M',
-            b'Q',
-            b'P',
-            0xd0,
-            self.major,
-            self.minor,
-            self.revision,
-        ])
+        writer.write_all(&self.as_bytes())
     }
 
+    /// Parse a header starting with `AMQP`, whatever its protocol id and version.
     pub fn try_parse(data: [u8; 8]) -> io::Result<Self> {
-        if &data[0..5] == b"AMQP\xd0" {
-            Ok(Version {
-                major: data[5],
-                minor: data[6],
-                revision: data[7],
-            })
-        } else {
-            Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Invalid AMQP version",
-            ))
+        match data {
+            [b'A', b'M', b'Q', b'P', id, major, minor, revision] => Ok(Version {
+                protocol_id: ProtocolId::from_u8(id),
+                major,
+                minor,
+                revision,
+            }),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, INVALID_HEADER)),
         }
     }
 
@@ -48,10 +93,72 @@ impl Version {
             b'M',
             b'Q',
             b'P',
-            0xd0,
+            self.protocol_id.as_u8(),
             self.major,
             self.minor,
             self.revision,
         ]
     }
 }
+
+/// What to do with the protocol header of the peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Negotiation {
+    /// The header is supported: the layer it names follows, answered with the same header.
+    Accept(Version),
+    /// The header is not supported: write this header, then close the connection.
+    Reject(Version),
+}
+
+/// Answer the protocol header `header` of the peer with one of the `supported` headers, the
+/// preferred first.
+///
+/// A header with the legacy protocol id is answered as AMQP. When rejecting, the answer is the
+/// supported header of the same layer if there is one, so the peer learns which version to use,
+/// else the first supported header.
+///
+/// # Panics
+///
+/// If `supported` is empty.
+pub fn negotiate(header: [u8; 8], supported: &[Version]) -> Negotiation {
+    let preferred = *supported.first().expect("a supported header");
+    let Ok(mut version) = Version::try_parse(header) else {
+        return Negotiation::Reject(preferred);
+    };
+    if version.protocol_id == ProtocolId::Legacy {
+        version.protocol_id = ProtocolId::Amqp;
+    }
+    if supported.contains(&version) {
+        return Negotiation::Accept(version);
+    }
+    let same_layer = supported
+        .iter()
+        .find(|supported| supported.protocol_id == version.protocol_id);
+    Negotiation::Reject(*same_layer.unwrap_or(&preferred))
+}
+
+/// Read the protocol header of the peer from `stream` and answer it.
+///
+/// The accepted header is written back and returned. An unsupported header is answered with a
+/// supported one, then `stream` is shut down and an [`UNSUPPORTED_HEADER`] error returned.
+pub async fn accept<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    supported: &[Version],
+) -> io::Result<Version> {
+    let mut header = [0; 8];
+    stream.read_exact(&mut header).await?;
+    match negotiate(header, supported) {
+        Negotiation::Accept(version) => {
+            stream.write_all(&version.as_bytes()).await?;
+            Ok(version)
+        }
+        Negotiation::Reject(version) => {
+            stream.write_all(&version.as_bytes()).await?;
+            stream.shutdown().await?;
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                UNSUPPORTED_HEADER,
+            ))
+        }
+    }
+}
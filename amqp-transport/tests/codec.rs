@@ -0,0 +1,120 @@
+use amqp_transport::{
+    codec::{AmqpFrame, FrameCodec, Heartbeat, Incoming, SaslFrame},
+    framing::FRAME_TOO_LARGE,
+    performative::{Close, Open, Performative},
+    version::Version,
+};
+use amqp_types::primitive::Symbol;
+use bytes::{Bytes, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+fn open_frame() -> AmqpFrame<Open<'static>> {
+    AmqpFrame {
+        channel: 0,
+        performative: Open::new("container"),
+        payload: Bytes::new(),
+    }
+}
+
+#[test]
+fn header_then_frames() {
+    let mut codec = FrameCodec::new();
+    let mut buffer = BytesMut::new();
+    codec.encode(Version::V_1_0_0, &mut buffer).unwrap();
+    codec.encode(open_frame(), &mut buffer).unwrap();
+    codec.encode(Heartbeat, &mut buffer).unwrap();
+    let close = AmqpFrame {
+        channel: 0,
+        performative: Close { error: None },
+        payload: Bytes::new(),
+    };
+    codec.encode(close, &mut buffer).unwrap();
+    let bytes = buffer.freeze();
+
+    // fed a byte at a time, as a slow peer would send them
+    let mut codec = FrameCodec::new();
+    let mut input = BytesMut::new();
+    let mut received = Vec::new();
+    for byte in bytes.iter() {
+        input.extend_from_slice(&[*byte]);
+        while let Some(incoming) = codec.decode(&mut input).unwrap() {
+            received.push(incoming);
+        }
+    }
+    assert!(input.is_empty());
+    assert_eq!(received.len(), 4);
+    assert!(matches!(received[0], Incoming::Header(Version::V_1_0_0)));
+    let Incoming::Frame(frame) = &received[1] else {
+        panic!("expected a frame")
+    };
+    let body = frame.frame().amqp_body().unwrap().unwrap();
+    let Performative::Open(open) = body.performative else {
+        panic!("expected an open")
+    };
+    assert_eq!(open.container_id, "container");
+    let Incoming::Frame(frame) = &received[2] else {
+        panic!("expected a frame")
+    };
+    assert!(frame.frame().is_heartbeat());
+    let Incoming::Frame(frame) = &received[3] else {
+        panic!("expected a frame")
+    };
+    let body = frame.frame().amqp_body().unwrap().unwrap();
+    assert!(matches!(body.performative, Performative::Close(_)));
+}
+
+#[test]
+fn max_frame_size() {
+    let large = || AmqpFrame {
+        channel: 0,
+        performative: Close { error: None },
+        payload: Bytes::from(vec![0; 1024]),
+    };
+    let mut codec = FrameCodec::new();
+    let mut buffer = BytesMut::new();
+    let error = codec.encode(large(), &mut buffer).unwrap_err();
+    assert_eq!(error.to_string(), FRAME_TOO_LARGE);
+    assert!(buffer.is_empty());
+    // once the open of the peer allows it
+    codec.set_remote_max_frame_size(4096);
+    codec.encode(Version::V_1_0_0, &mut buffer).unwrap();
+    codec.encode(large(), &mut buffer).unwrap();
+
+    let mut codec = FrameCodec::new();
+    assert!(codec.decode(&mut buffer).unwrap().is_some());
+    let error = codec.decode(&mut buffer.clone()).unwrap_err();
+    assert_eq!(error.to_string(), FRAME_TOO_LARGE);
+    codec.set_max_frame_size(4096);
+    let Some(Incoming::Frame(frame)) = codec.decode(&mut buffer).unwrap() else {
+        panic!("expected a frame")
+    };
+    assert_eq!(frame.header.size, 8 + 4 + 1024);
+}
+
+#[test]
+fn sasl_then_amqp_header() {
+    let mut codec = FrameCodec::new();
+    let mut buffer = BytesMut::new();
+    codec.encode(Version::V_1_0_0, &mut buffer).unwrap();
+    codec
+        .encode(SaslFrame(Symbol::new(b"PLAIN")), &mut buffer)
+        .unwrap();
+    codec.encode(Version::V_1_0_0, &mut buffer).unwrap();
+
+    let mut codec = FrameCodec::new();
+    assert!(matches!(
+        codec.decode(&mut buffer).unwrap(),
+        Some(Incoming::Header(_))
+    ));
+    let Some(Incoming::Frame(frame)) = codec.decode(&mut buffer).unwrap() else {
+        panic!("expected a frame")
+    };
+    assert!(frame.frame().sasl_body().is_ok());
+    // the outcome of the SASL exchange is followed by a new header
+    codec.expect_header();
+    assert!(matches!(
+        codec.decode(&mut buffer).unwrap(),
+        Some(Incoming::Header(_))
+    ));
+    assert!(buffer.is_empty());
+}
@@ -0,0 +1,203 @@
+//! Peers of the sans-IO state machines, handing the frames one wrote to the other.
+// every test uses a part of it
+#![allow(dead_code)]
+
+use std::io;
+
+use amqp_transport::{
+    codec::{FrameCodec, Incoming},
+    connections::OutgoingChannelEndpoint,
+    definitions::{Handle, SequenceNo},
+    links::{Link, LinkReceived, Links},
+    performative::Performative,
+    sessions::{SessionReceived, Sessions},
+    sm::connection::{Connection, ConnectionConfig, ConnectionState, Received},
+};
+use bytes::BytesMut;
+use tokio_util::codec::Decoder;
+
+/// How far the frames a peer reads are handed up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Connection,
+    Sessions,
+    Links,
+}
+
+/// A connection, its sessions and the links of the session on channel 0.
+pub struct Peer {
+    pub connection: Connection,
+    pub sessions: Sessions,
+    pub links: Links,
+    layer: Layer,
+    codec: FrameCodec,
+    input: BytesMut,
+}
+
+impl Peer {
+    pub fn new(container_id: &str, layer: Layer) -> Self {
+        Self {
+            connection: Connection::new(ConnectionConfig::new(container_id)),
+            sessions: Sessions::new(),
+            links: Links::new(OutgoingChannelEndpoint { number: 0 }),
+            layer,
+            codec: FrameCodec::new(),
+            input: BytesMut::new(),
+        }
+    }
+    pub fn link(&self, handle: Handle) -> &Link {
+        self.links.get(handle).unwrap()
+    }
+    /// The states the connection went through since last asked.
+    pub fn states(&mut self) -> Vec<ConnectionState> {
+        std::iter::from_fn(|| self.connection.poll_transition())
+            .map(|transition| transition.to)
+            .collect()
+    }
+}
+
+/// A performative, without its fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Open,
+    Begin,
+    Attach,
+    Flow,
+    Transfer,
+    Disposition,
+    Detach,
+    End,
+    Close,
+}
+
+impl From<&Performative<'_>> for Kind {
+    fn from(performative: &Performative<'_>) -> Self {
+        match performative {
+            Performative::Open(_) => Kind::Open,
+            Performative::Begin(_) => Kind::Begin,
+            Performative::Attach(_) => Kind::Attach,
+            Performative::Flow(_) => Kind::Flow,
+            Performative::Transfer(_) => Kind::Transfer,
+            Performative::Disposition(_) => Kind::Disposition,
+            Performative::Detach(_) => Kind::Detach,
+            Performative::End(_) => Kind::End,
+            Performative::Close(_) => Kind::Close,
+        }
+    }
+}
+
+/// What a peer read, from the layer which handled it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Read {
+    Header,
+    /// a frame dropped
+    Nothing,
+    /// an open, with its container id
+    Open(String),
+    /// a close, with the description of its error
+    Close(Option<String>),
+    /// a frame of a session, read by the connection
+    Session(u16, Kind),
+    Begin(u16),
+    End(u16),
+    Flow(u16),
+    /// a frame of a link, read by the sessions
+    Link(u16, Kind),
+    Attach(Handle),
+    Detach {
+        handle: Handle,
+        closed: bool,
+    },
+    LinkFlow(Handle),
+    Transfer(Handle),
+    /// a disposition, from its first to its last delivery
+    Disposition(SequenceNo, SequenceNo),
+}
+
+/// Hand what `from` wrote to `to`, with what `to` read of each frame.
+pub fn deliver(from: &mut Peer, to: &mut Peer) -> Vec<io::Result<Read>> {
+    to.input.extend_from_slice(&from.connection.take_outgoing());
+    let mut read = Vec::new();
+    while let Some(incoming) = to.codec.decode(&mut to.input).unwrap() {
+        read.push(match incoming {
+            Incoming::Header(version) => to
+                .connection
+                .handle_header(version.as_bytes())
+                .map(|()| Read::Header),
+            Incoming::Frame(frame) => to
+                .connection
+                .handle_frame(frame.frame())
+                .and_then(|received| read_connection(to, received)),
+        });
+    }
+    read
+}
+
+fn read_connection(to: &mut Peer, received: Received<'_>) -> io::Result<Read> {
+    Ok(match received {
+        Received::Nothing => Read::Nothing,
+        Received::Open(open) => Read::Open(open.container_id.to_owned()),
+        Received::Close(close) => Read::Close(
+            close
+                .error
+                .and_then(|error| error.description.map(str::to_owned)),
+        ),
+        Received::Session { channel, body } if to.layer == Layer::Connection => {
+            Read::Session(channel, Kind::from(&body.performative))
+        }
+        Received::Session { channel, body } => {
+            let received = to.sessions.handle(&mut to.connection, channel, body)?;
+            return read_sessions(to, received);
+        }
+    })
+}
+
+fn read_sessions(to: &mut Peer, received: SessionReceived<'_>) -> io::Result<Read> {
+    Ok(match received {
+        SessionReceived::Nothing => Read::Nothing,
+        SessionReceived::Begin { channel, .. } => Read::Begin(channel.number),
+        SessionReceived::End { channel, .. } => Read::End(channel.number),
+        SessionReceived::Flow { channel, .. } => Read::Flow(channel.number),
+        SessionReceived::Link { channel, body } if to.layer == Layer::Sessions => {
+            Read::Link(channel.number, Kind::from(&body.performative))
+        }
+        SessionReceived::Link { body, .. } => {
+            match to
+                .links
+                .handle(&mut to.connection, &mut to.sessions, body)?
+            {
+                LinkReceived::Nothing => Read::Nothing,
+                LinkReceived::Attach { handle, .. } => Read::Attach(handle),
+                LinkReceived::Detach { handle, detach } => Read::Detach {
+                    handle,
+                    closed: detach.closed,
+                },
+                LinkReceived::Flow { handle, .. } => Read::LinkFlow(handle),
+                LinkReceived::Transfer { handle, .. } => Read::Transfer(handle),
+                LinkReceived::Disposition(disposition) => Read::Disposition(
+                    disposition.first,
+                    disposition.last.unwrap_or(disposition.first),
+                ),
+            }
+        }
+    })
+}
+
+/// What was read, every frame having been read without an error.
+pub fn ok(read: Vec<io::Result<Read>>) -> Vec<Read> {
+    read.into_iter().map(Result::unwrap).collect()
+}
+
+/// Two peers with their connection opened, handing frames up to `layer`.
+pub fn opened(layer: Layer) -> (Peer, Peer) {
+    let (mut client, mut server) = (Peer::new("client", layer), Peer::new("server", layer));
+    client.connection.open().unwrap();
+    server.connection.open().unwrap();
+    deliver(&mut client, &mut server);
+    deliver(&mut server, &mut client);
+    assert_eq!(client.connection.state(), ConnectionState::Opened);
+    assert_eq!(server.connection.state(), ConnectionState::Opened);
+    client.states();
+    server.states();
+    (client, server)
+}
@@ -0,0 +1,218 @@
+mod common;
+
+use amqp_transport::{
+    codec::{FrameCodec, Incoming},
+    definitions::{AmqpError, ConnectionError, Error, Handle},
+    performative::{Begin, Open, Performative},
+    sm::connection::{
+        Connection, ConnectionConfig, ConnectionState, ILLEGAL_STATE, UNEXPECTED_FRAME,
+    },
+    version::{Version, UNSUPPORTED_HEADER},
+};
+use bytes::BytesMut;
+use common::{deliver, ok, Kind, Layer, Peer, Read};
+use tokio_util::codec::Decoder;
+
+use ConnectionState::*;
+
+fn begin() -> Begin<'static> {
+    Begin {
+        remote_channel: None,
+        next_outgoing_id: Default::default(),
+        incoming_window: 10,
+        outgoing_window: 10,
+        handle_max: Handle(u32::MAX),
+        offered_capabilities: Vec::new(),
+        desired_capabilities: Vec::new(),
+        properties: None,
+    }
+}
+
+#[test]
+fn open_and_close() {
+    let (mut client, mut server) = (
+        Peer::new("client", Layer::Connection),
+        Peer::new("server", Layer::Connection),
+    );
+    client.connection.open().unwrap();
+    assert_eq!(client.states(), [HdrSent, OpenPipe]);
+
+    assert_eq!(
+        ok(deliver(&mut client, &mut server)),
+        [Read::Header, Read::Open("client".to_owned())]
+    );
+    assert_eq!(server.states(), [HdrRcvd, HdrExch, OpenRcvd]);
+    server.connection.open().unwrap();
+    assert_eq!(server.states(), [Opened]);
+
+    assert_eq!(
+        ok(deliver(&mut server, &mut client)),
+        [Read::Header, Read::Open("server".to_owned())]
+    );
+    assert_eq!(client.states(), [OpenSent, Opened]);
+    assert_eq!(client.connection.remote_max_frame_size(), u32::MAX);
+
+    client.connection.send(1, begin(), &[]).unwrap();
+    client.connection.close(None).unwrap();
+    assert_eq!(client.states(), [CloseSent]);
+    assert_eq!(
+        ok(deliver(&mut client, &mut server)),
+        [Read::Session(1, Kind::Begin), Read::Close(None)]
+    );
+    assert_eq!(server.states(), [CloseRcvd]);
+    server.connection.close(None).unwrap();
+    assert_eq!(server.states(), [End]);
+    assert_eq!(ok(deliver(&mut server, &mut client)), [Read::Close(None)]);
+    assert_eq!(client.states(), [End]);
+}
+
+#[test]
+fn pipelined_open_and_close() {
+    let (mut client, mut server) = (
+        Peer::new("client", Layer::Connection),
+        Peer::new("server", Layer::Connection),
+    );
+    client.connection.close(None).unwrap();
+    assert_eq!(client.states(), [HdrSent, OpenPipe, OcPipe]);
+
+    assert_eq!(
+        ok(deliver(&mut client, &mut server)),
+        [
+            Read::Header,
+            Read::Open("client".to_owned()),
+            Read::Close(None)
+        ]
+    );
+    assert_eq!(server.states(), [HdrRcvd, HdrExch, OpenRcvd, CloseRcvd]);
+    // the open is sent before the close answering the peer
+    server.connection.close(None).unwrap();
+    assert_eq!(server.states(), [End]);
+
+    assert_eq!(
+        ok(deliver(&mut server, &mut client)),
+        [
+            Read::Header,
+            Read::Open("server".to_owned()),
+            Read::Close(None)
+        ]
+    );
+    assert_eq!(client.states(), [ClosePipe, CloseSent, End]);
+}
+
+#[test]
+fn no_heartbeat_after_close() {
+    let (mut client, mut server) = (
+        Peer::new("client", Layer::Connection),
+        Peer::new("server", Layer::Connection),
+    );
+    client.connection.close(None).unwrap();
+    assert_eq!(client.states(), [HdrSent, OpenPipe, OcPipe]);
+    assert!(client.connection.heartbeat().is_err());
+
+    server.connection.send_header().unwrap();
+    deliver(&mut server, &mut client);
+    assert_eq!(client.states(), [ClosePipe]);
+    assert!(client.connection.heartbeat().is_err());
+
+    server.connection.open().unwrap();
+    deliver(&mut server, &mut client);
+    assert_eq!(client.states(), [CloseSent]);
+    assert!(client.connection.heartbeat().is_err());
+}
+
+#[test]
+fn discarding() {
+    let (mut client, mut server) = (
+        Peer::new("client", Layer::Connection),
+        Peer::new("server", Layer::Connection),
+    );
+    client.connection.open().unwrap();
+    deliver(&mut client, &mut server);
+    server.connection.open().unwrap();
+    deliver(&mut server, &mut client);
+    client.states();
+
+    let error = Error::new(ConnectionError::ConnectionForced, "maintenance").unwrap();
+    client.connection.close(Some(error)).unwrap();
+    assert_eq!(client.states(), [Discarding]);
+    assert!(client.connection.send(1, begin(), &[]).is_err());
+    assert!(client.connection.heartbeat().is_err());
+
+    server.connection.send(1, begin(), &[]).unwrap();
+    server.connection.heartbeat().unwrap();
+    server.connection.close(None).unwrap();
+    assert_eq!(
+        ok(deliver(&mut server, &mut client)),
+        [Read::Nothing, Read::Nothing, Read::Close(None)]
+    );
+    assert_eq!(client.states(), [End]);
+    assert_eq!(
+        ok(deliver(&mut client, &mut server)),
+        [Read::Close(Some("maintenance".to_owned()))]
+    );
+}
+
+#[test]
+fn unsupported_header() {
+    let mut server = Connection::new(ConnectionConfig::new("server"));
+    let error = server
+        .handle_header(Version::SASL_V_1_0_0.as_bytes())
+        .unwrap_err();
+    assert_eq!(error.to_string(), UNSUPPORTED_HEADER);
+    assert_eq!(server.state(), End);
+    // answered with the header we support
+    assert_eq!(&server.take_outgoing()[..], Version::V_1_0_0.as_bytes());
+}
+
+#[test]
+fn unexpected_frame() {
+    let (mut client, mut server) = (
+        Peer::new("client", Layer::Connection),
+        Peer::new("server", Layer::Connection),
+    );
+    client.connection.open().unwrap();
+    deliver(&mut client, &mut server);
+    server.connection.open().unwrap();
+    server.states();
+
+    // a second open
+    client.connection.send(0, Open::new("client"), &[]).unwrap();
+    let read = deliver(&mut client, &mut server);
+    assert_eq!(read[0].as_ref().unwrap_err().to_string(), UNEXPECTED_FRAME);
+    assert_eq!(server.states(), [Discarding]);
+
+    // the close carries the error
+    let mut input = BytesMut::from(&server.connection.take_outgoing()[..]);
+    let mut codec = FrameCodec::new();
+    let Some(Incoming::Header(_)) = codec.decode(&mut input).unwrap() else {
+        panic!("expected the header")
+    };
+    let Some(Incoming::Frame(_open)) = codec.decode(&mut input).unwrap() else {
+        panic!("expected the open")
+    };
+    let Some(Incoming::Frame(close)) = codec.decode(&mut input).unwrap() else {
+        panic!("expected the close")
+    };
+    let frame = close.frame();
+    let Performative::Close(close) = frame.amqp_body().unwrap().unwrap().performative else {
+        panic!("expected a close")
+    };
+    let error = close.error.unwrap();
+    assert_eq!(
+        error.condition.get::<AmqpError>().unwrap(),
+        AmqpError::IllegalState
+    );
+}
+
+#[test]
+fn illegal_actions() {
+    let mut connection = Connection::new(ConnectionConfig::new("container"));
+    assert_eq!(
+        connection.send(1, begin(), &[]).unwrap_err().to_string(),
+        ILLEGAL_STATE
+    );
+    assert!(connection.heartbeat().is_err());
+    connection.open().unwrap();
+    assert!(connection.open().is_err());
+    assert!(connection.send_header().is_err());
+}
@@ -0,0 +1,179 @@
+use amqp_transport::{
+    definitions::{DeliveryTag, Handle, SequenceNo, MIN_MAX_FRAME_SIZE},
+    framing::*,
+    performative::{Begin, Performative, Transfer},
+};
+use amqp_types::primitive::{Binary, Symbol};
+
+fn transfer() -> Transfer<'static> {
+    Transfer {
+        handle: Handle(1),
+        delivery_id: Some(SequenceNo(0)),
+        delivery_tag: Some(DeliveryTag(Binary::new(b"t"))),
+        message_format: Some(0),
+        settled: None,
+        more: false,
+        rcv_settle_mode: None,
+        state: None,
+        resume: false,
+        aborted: false,
+        batchable: false,
+    }
+}
+
+#[test]
+fn header() {
+    let bytes = [0x00, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x07];
+    let header = FrameHeader::try_parse(bytes).unwrap();
+    assert_eq!(header.size, 256);
+    assert_eq!(header.doff, 2);
+    assert_eq!(header.frame_type, FrameHeader::AMQP);
+    assert_eq!(header.channel(), 7);
+    assert_eq!(header.exthdr_size(), Some(0));
+    assert_eq!(header.body_size(), Some(248));
+    assert_eq!(header.as_bytes(), bytes);
+
+    let error = FrameHeader::try_parse([0, 0, 0, 8, 1, 0, 0, 0]).unwrap_err();
+    assert_eq!(error.to_string(), INVALID_DOFF);
+    let error = FrameHeader::try_parse([0, 0, 0, 8, 3, 0, 0, 0]).unwrap_err();
+    assert_eq!(error.to_string(), FRAME_TOO_SMALL);
+}
+
+#[test]
+fn heartbeat() {
+    let mut buffer = Vec::new();
+    write_heartbeat(&mut buffer);
+    assert_eq!(buffer, [0x00, 0x00, 0x00, 0x08, 0x02, 0x00, 0x00, 0x00]);
+    let mut input = buffer.as_slice();
+    let frame = Frame::decode(&mut input, MIN_MAX_FRAME_SIZE)
+        .unwrap()
+        .unwrap();
+    assert!(input.is_empty());
+    assert!(frame.is_heartbeat());
+    assert!(frame.amqp_body().unwrap().is_none());
+}
+
+#[test]
+fn partial_frames() {
+    let mut buffer = Vec::new();
+    let begin = Begin {
+        remote_channel: None,
+        next_outgoing_id: SequenceNo(1),
+        incoming_window: 2048,
+        outgoing_window: 2048,
+        handle_max: Handle(u32::MAX),
+        offered_capabilities: Vec::new(),
+        desired_capabilities: Vec::new(),
+        properties: None,
+    };
+    write_amqp_frame(&mut buffer, 3, begin, &[], MIN_MAX_FRAME_SIZE).unwrap();
+    write_heartbeat(&mut buffer);
+    let begin_size = buffer.len() - FrameHeader::SIZE;
+    assert_eq!(
+        buffer[..8],
+        [0x00, 0x00, 0x00, 0x1b, 0x02, 0x00, 0x00, 0x03]
+    );
+
+    for len in 0..begin_size {
+        let mut input = &buffer[..len];
+        assert!(Frame::decode(&mut input, MIN_MAX_FRAME_SIZE)
+            .unwrap()
+            .is_none());
+        assert_eq!(input.len(), len);
+    }
+    let mut input = buffer.as_slice();
+    let frame = Frame::decode(&mut input, MIN_MAX_FRAME_SIZE)
+        .unwrap()
+        .unwrap();
+    assert_eq!(frame.channel(), 3);
+    let body = frame.amqp_body().unwrap().unwrap();
+    let Performative::Begin(begin) = body.performative else {
+        panic!("expected a begin")
+    };
+    assert_eq!(begin.incoming_window, 2048);
+    assert!(body.payload.is_empty());
+    let frame = Frame::decode(&mut input, MIN_MAX_FRAME_SIZE)
+        .unwrap()
+        .unwrap();
+    assert!(frame.is_heartbeat());
+    assert!(input.is_empty());
+}
+
+#[test]
+fn transfer_payload() {
+    let mut buffer = Vec::new();
+    write_amqp_frame(&mut buffer, 0, transfer(), b"message", MIN_MAX_FRAME_SIZE).unwrap();
+    let mut input = buffer.as_slice();
+    let frame = Frame::decode(&mut input, MIN_MAX_FRAME_SIZE)
+        .unwrap()
+        .unwrap();
+    let body = frame.amqp_body().unwrap().unwrap();
+    assert!(matches!(body.performative, Performative::Transfer(_)));
+    assert_eq!(body.payload, b"message");
+
+    // only transfers are followed by a payload
+    let mut buffer = vec![0x00, 0x00, 0x00, 0x0e, 0x02, 0x00, 0x00, 0x00];
+    buffer.extend_from_slice(&[0x00, 0x53, 0x17, 0x45, 0xde, 0xad]);
+    let frame = Frame::decode(&mut buffer.as_slice(), MIN_MAX_FRAME_SIZE)
+        .unwrap()
+        .unwrap();
+    let error = frame.amqp_body().unwrap_err();
+    assert_eq!(error.to_string(), UNEXPECTED_PAYLOAD);
+}
+
+#[test]
+fn extended_header() {
+    // doff 3, a 4 bytes extended header before the end performative
+    let buffer = [
+        0x00, 0x00, 0x00, 0x10, 0x03, 0x00, 0x00, 0x01, 0xca, 0xfe, 0xca, 0xfe, 0x00, 0x53, 0x17,
+        0x45,
+    ];
+    let frame = Frame::decode(&mut &buffer[..], MIN_MAX_FRAME_SIZE)
+        .unwrap()
+        .unwrap();
+    assert_eq!(frame.extended_header, [0xca, 0xfe, 0xca, 0xfe]);
+    assert_eq!(frame.body, [0x00, 0x53, 0x17, 0x45]);
+    let body = frame.amqp_body().unwrap().unwrap();
+    assert!(matches!(body.performative, Performative::End(_)));
+}
+
+#[test]
+fn max_frame_size() {
+    let payload = [0; 512];
+    let mut buffer = Vec::new();
+    let error =
+        write_amqp_frame(&mut buffer, 0, transfer(), &payload, MIN_MAX_FRAME_SIZE).unwrap_err();
+    assert_eq!(error.to_string(), FRAME_TOO_LARGE);
+    assert!(buffer.is_empty());
+
+    write_amqp_frame(&mut buffer, 0, transfer(), &payload, 1024).unwrap();
+    let error = Frame::decode(&mut buffer.as_slice(), MIN_MAX_FRAME_SIZE).unwrap_err();
+    assert_eq!(error.to_string(), FRAME_TOO_LARGE);
+    assert!(Frame::decode(&mut buffer.as_slice(), 1024)
+        .unwrap()
+        .is_some());
+}
+
+#[test]
+fn sasl() {
+    let mut buffer = Vec::new();
+    write_sasl_frame(&mut buffer, Symbol::new(b"PLAIN"), MIN_MAX_FRAME_SIZE).unwrap();
+    assert_eq!(
+        buffer,
+        [
+            0x00, 0x00, 0x00, 0x0f, 0x02, 0x01, 0x00, 0x00, 0xa3, 0x05, b'P', b'L', b'A', b'I',
+            b'N'
+        ]
+    );
+    let frame = Frame::decode(&mut buffer.as_slice(), MIN_MAX_FRAME_SIZE)
+        .unwrap()
+        .unwrap();
+    assert!(!frame.is_heartbeat());
+    let value = frame.sasl_body().unwrap();
+    assert_eq!(
+        value.construct().unwrap().as_symbol().unwrap().as_bytes(),
+        b"PLAIN"
+    );
+    let error = frame.amqp_body().unwrap_err();
+    assert_eq!(error.to_string(), UNEXPECTED_FRAME_TYPE);
+}
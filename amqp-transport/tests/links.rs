@@ -0,0 +1,749 @@
+mod common;
+
+use amqp_transport::{
+    definitions::{DeliveryTag, Handle, ReceiverSettleMode, Role, SenderSettleMode, SequenceNo},
+    delivery::State,
+    links::{
+        LinkState, BACKWARD_DISPOSITION, INVALID_DELIVERY_STATE, MISSING_DELIVERY, NO_LINK_CREDIT,
+        SETTLE_MODE_VIOLATION, TRANSFER_LIMIT_EXCEEDED,
+    },
+    performative::{Attach, Disposition, Transfer},
+    sessions::{SessionConfig, SessionState},
+    sm::connection::ILLEGAL_STATE,
+};
+use amqp_types::{codec::Decode, primitive::Binary, provides::Requires, Value};
+use common::{deliver, ok, opened, Layer, Peer, Read};
+
+/// Two peers with a session begun on channel 0 of both.
+fn begun() -> (Peer, Peer) {
+    let (mut client, mut server) = opened(Layer::Links);
+    let channel = client
+        .sessions
+        .begin(&mut client.connection, SessionConfig::default())
+        .unwrap();
+    deliver(&mut client, &mut server);
+    server
+        .sessions
+        .accept(&mut server.connection, channel, SessionConfig::default())
+        .unwrap();
+    deliver(&mut server, &mut client);
+    assert_eq!(client.sessions.state(channel), SessionState::Mapped);
+    (client, server)
+}
+
+fn attach(name: &str, role: Role) -> Attach<'_> {
+    Attach {
+        name,
+        handle: Handle(0),
+        role,
+        snd_settle_mode: SenderSettleMode::Unsettled,
+        rcv_settle_mode: ReceiverSettleMode::Second,
+        source: None,
+        target: None,
+        unsettled: None,
+        incomplete_unsettled: false,
+        initial_delivery_count: None,
+        max_message_size: None,
+        offered_capabilities: Vec::new(),
+        desired_capabilities: Vec::new(),
+        properties: None,
+    }
+}
+
+fn transfer(tag: &[u8], more: bool) -> Transfer<'_> {
+    Transfer {
+        handle: Handle(0),
+        delivery_id: Some(SequenceNo(0)),
+        delivery_tag: Some(DeliveryTag(Binary::new(tag))),
+        message_format: None,
+        settled: None,
+        more,
+        rcv_settle_mode: None,
+        state: None,
+        resume: false,
+        aborted: false,
+        batchable: false,
+    }
+}
+
+/// A sending link from the client attached with `initial_delivery_count` and the
+/// `rcv_settle_mode` of the server, its handles on both peers.
+fn attached(
+    client: &mut Peer,
+    server: &mut Peer,
+    initial_delivery_count: u32,
+    rcv_settle_mode: ReceiverSettleMode,
+) -> (Handle, Handle) {
+    let mut sender = attach("link", Role::Sender);
+    sender.initial_delivery_count = Some(SequenceNo(initial_delivery_count));
+    let handle = client
+        .links
+        .attach(&mut client.connection, &mut client.sessions, sender)
+        .unwrap();
+    let [Read::Attach(remote)] = ok(deliver(client, server))[..] else {
+        panic!("expected an attach")
+    };
+    assert_eq!(server.link(remote).state(), LinkState::AttachRcvd);
+    let mut receiver = attach("link", Role::Receiver);
+    receiver.rcv_settle_mode = rcv_settle_mode;
+    server
+        .links
+        .accept(
+            &mut server.connection,
+            &mut server.sessions,
+            remote,
+            receiver,
+        )
+        .unwrap();
+    assert_eq!(ok(deliver(server, client)), [Read::Attach(handle)]);
+    (handle, remote)
+}
+
+#[test]
+fn attach_and_detach() {
+    let (mut client, mut server) = begun();
+    let (handle, remote) = attached(&mut client, &mut server, 5, ReceiverSettleMode::Second);
+
+    let sender = client.link(handle);
+    assert_eq!(sender.state(), LinkState::Attached);
+    assert_eq!(sender.role(), Role::Sender);
+    assert_eq!(sender.remote_handle(), Some(remote));
+    assert_eq!(sender.delivery_count(), SequenceNo(5));
+    assert_eq!(sender.rcv_settle_mode(), ReceiverSettleMode::Second);
+    let receiver = server.link(remote);
+    assert_eq!(receiver.state(), LinkState::Attached);
+    assert_eq!(receiver.role(), Role::Receiver);
+    assert_eq!(receiver.name(), "link");
+    assert_eq!(receiver.delivery_count(), SequenceNo(5));
+    assert_eq!(receiver.snd_settle_mode(), SenderSettleMode::Unsettled);
+
+    client
+        .links
+        .detach(
+            &mut client.connection,
+            &mut client.sessions,
+            handle,
+            true,
+            None,
+        )
+        .unwrap();
+    assert_eq!(client.links.state(handle), LinkState::DetachSent);
+    assert_eq!(
+        ok(deliver(&mut client, &mut server)),
+        [Read::Detach {
+            handle: remote,
+            closed: true,
+        }]
+    );
+    assert_eq!(server.links.state(remote), LinkState::DetachRcvd);
+    // answered without closing, the link is only detached
+    server
+        .links
+        .detach(
+            &mut server.connection,
+            &mut server.sessions,
+            remote,
+            false,
+            None,
+        )
+        .unwrap();
+    assert_eq!(server.links.state(remote), LinkState::Detached);
+    assert_eq!(
+        ok(deliver(&mut server, &mut client)),
+        [Read::Detach {
+            handle,
+            closed: false,
+        }]
+    );
+    assert_eq!(client.links.state(handle), LinkState::Detached);
+    assert!(client.links.find("link").is_none());
+}
+
+#[test]
+fn credit() {
+    let (mut client, mut server) = begun();
+    let (handle, remote) = attached(&mut client, &mut server, 0, ReceiverSettleMode::Second);
+    let error = client
+        .links
+        .transfer(
+            &mut client.connection,
+            &mut client.sessions,
+            handle,
+            transfer(b"0", false),
+            b"data",
+        )
+        .unwrap_err();
+    assert_eq!(error.to_string(), NO_LINK_CREDIT);
+
+    server
+        .links
+        .flow(
+            &mut server.connection,
+            &mut server.sessions,
+            remote,
+            2,
+            false,
+        )
+        .unwrap();
+    assert_eq!(
+        ok(deliver(&mut server, &mut client)),
+        [Read::LinkFlow(handle)]
+    );
+    assert_eq!(client.link(handle).link_credit(), 2);
+
+    // a delivery split in two frames takes a single credit
+    for (tag, more) in [(b"0", true), (b"0", false), (b"1", false)] {
+        client
+            .links
+            .transfer(
+                &mut client.connection,
+                &mut client.sessions,
+                handle,
+                transfer(tag, more),
+                b"data",
+            )
+            .unwrap();
+    }
+    let sender = client.link(handle);
+    assert_eq!(sender.link_credit(), 0);
+    assert_eq!(sender.delivery_count(), SequenceNo(2));
+    assert!(client
+        .links
+        .transfer(
+            &mut client.connection,
+            &mut client.sessions,
+            handle,
+            transfer(b"0", false),
+            b"data",
+        )
+        .is_err());
+    assert_eq!(deliver(&mut client, &mut server).len(), 3);
+    let receiver = server.link(remote);
+    assert_eq!(receiver.link_credit(), 0);
+    assert_eq!(receiver.delivery_count(), SequenceNo(2));
+}
+
+#[test]
+fn credit_across_the_wrap() {
+    let (mut client, mut server) = begun();
+    let (handle, remote) = attached(
+        &mut client,
+        &mut server,
+        u32::MAX - 1,
+        ReceiverSettleMode::Second,
+    );
+    server
+        .links
+        .flow(
+            &mut server.connection,
+            &mut server.sessions,
+            remote,
+            3,
+            false,
+        )
+        .unwrap();
+    deliver(&mut server, &mut client);
+    for tag in [b"0", b"1"] {
+        client
+            .links
+            .transfer(
+                &mut client.connection,
+                &mut client.sessions,
+                handle,
+                transfer(tag, false),
+                b"data",
+            )
+            .unwrap();
+    }
+    deliver(&mut client, &mut server);
+    assert_eq!(server.link(remote).delivery_count(), SequenceNo(0));
+    assert_eq!(server.link(remote).link_credit(), 1);
+
+    // the receiver grants more while the count has wrapped
+    server
+        .links
+        .flow(
+            &mut server.connection,
+            &mut server.sessions,
+            remote,
+            4,
+            false,
+        )
+        .unwrap();
+    deliver(&mut server, &mut client);
+    assert_eq!(client.link(handle).delivery_count(), SequenceNo(0));
+    assert_eq!(client.link(handle).link_credit(), 4);
+}
+
+#[test]
+fn drain() {
+    let (mut client, mut server) = begun();
+    let (handle, remote) = attached(&mut client, &mut server, 0, ReceiverSettleMode::Second);
+    server
+        .links
+        .flow(
+            &mut server.connection,
+            &mut server.sessions,
+            remote,
+            5,
+            true,
+        )
+        .unwrap();
+    deliver(&mut server, &mut client);
+    assert!(client.link(handle).drain());
+    client
+        .links
+        .transfer(
+            &mut client.connection,
+            &mut client.sessions,
+            handle,
+            transfer(b"0", false),
+            b"data",
+        )
+        .unwrap();
+    // nothing more to send, the credit left is used up
+    client
+        .links
+        .drained(&mut client.connection, &mut client.sessions, handle)
+        .unwrap();
+    let sender = client.link(handle);
+    assert_eq!(sender.delivery_count(), SequenceNo(5));
+    assert_eq!(sender.link_credit(), 0);
+
+    assert_eq!(
+        ok(deliver(&mut client, &mut server)),
+        [Read::Transfer(remote), Read::LinkFlow(remote)]
+    );
+    let receiver = server.link(remote);
+    assert_eq!(receiver.delivery_count(), SequenceNo(5));
+    assert_eq!(receiver.link_credit(), 0);
+}
+
+#[test]
+fn echo() {
+    let (mut client, mut server) = begun();
+    let (handle, remote) = attached(&mut client, &mut server, 0, ReceiverSettleMode::Second);
+    client
+        .links
+        .set_available(&mut client.connection, &mut client.sessions, handle, 7)
+        .unwrap();
+    deliver(&mut client, &mut server);
+    assert_eq!(server.link(remote).available(), 7);
+
+    // the receiver asks for the state of the sender
+    let channel = server.links.channel();
+    let mut flow = server.sessions.get(channel).unwrap().flow();
+    flow.handle = Some(remote);
+    flow.delivery_count = Some(SequenceNo(0));
+    flow.link_credit = Some(1);
+    flow.echo = true;
+    server
+        .sessions
+        .send(&mut server.connection, channel, flow)
+        .unwrap();
+    assert_eq!(
+        ok(deliver(&mut server, &mut client)),
+        [Read::LinkFlow(handle)]
+    );
+    assert_eq!(
+        ok(deliver(&mut client, &mut server)),
+        [Read::LinkFlow(remote)]
+    );
+}
+
+#[test]
+fn transfer_limit_exceeded() {
+    let (mut client, mut server) = begun();
+    let (handle, remote) = attached(&mut client, &mut server, 0, ReceiverSettleMode::Second);
+    // past the link, which would refuse it without credit
+    let mut unwanted = transfer(b"0", false);
+    unwanted.handle = handle;
+    client
+        .sessions
+        .transfer(
+            &mut client.connection,
+            client.links.channel(),
+            unwanted,
+            b"data",
+        )
+        .unwrap();
+    let read = deliver(&mut client, &mut server);
+    assert_eq!(
+        read[0].as_ref().unwrap_err().to_string(),
+        TRANSFER_LIMIT_EXCEEDED
+    );
+    assert_eq!(server.links.state(remote), LinkState::DetachSent);
+    assert_eq!(
+        ok(deliver(&mut server, &mut client)),
+        [Read::Detach {
+            handle,
+            closed: true,
+        }]
+    );
+}
+
+#[test]
+fn unattached_handle() {
+    let (mut client, mut server) = begun();
+    let channel = client.links.channel();
+    let mut stray = transfer(b"0", false);
+    stray.handle = Handle(9);
+    client
+        .sessions
+        .transfer(&mut client.connection, channel, stray, b"data")
+        .unwrap();
+    let read = deliver(&mut client, &mut server);
+    assert!(read[0].is_err());
+    assert_eq!(server.sessions.state(channel), SessionState::Discarding);
+    assert_eq!(ok(deliver(&mut server, &mut client)), [Read::End(0)]);
+    // the session is gone on the client once its end is answered
+    client
+        .sessions
+        .end(&mut client.connection, channel, None)
+        .unwrap();
+    let error = client
+        .links
+        .attach(
+            &mut client.connection,
+            &mut client.sessions,
+            attach("link", Role::Sender),
+        )
+        .unwrap_err();
+    assert_eq!(error.to_string(), ILLEGAL_STATE);
+}
+
+/// Send a delivery of `tag` for each of `tags` on the sending link, with the credit for them.
+fn sent(client: &mut Peer, server: &mut Peer, handle: Handle, remote: Handle, tags: &[&[u8]]) {
+    server
+        .links
+        .flow(
+            &mut server.connection,
+            &mut server.sessions,
+            remote,
+            tags.len() as u32,
+            false,
+        )
+        .unwrap();
+    deliver(server, client);
+    for tag in tags {
+        client
+            .links
+            .transfer(
+                &mut client.connection,
+                &mut client.sessions,
+                handle,
+                transfer(tag, false),
+                b"data",
+            )
+            .unwrap();
+    }
+    assert_eq!(deliver(client, server).len(), tags.len());
+}
+
+#[test]
+fn settle_first() {
+    let (mut client, mut server) = begun();
+    let (handle, remote) = attached(&mut client, &mut server, 0, ReceiverSettleMode::First);
+    sent(
+        &mut client,
+        &mut server,
+        handle,
+        remote,
+        &[b"a", b"b", b"c"],
+    );
+    let delivery = server.link(remote).delivery(b"b").unwrap();
+    assert_eq!(delivery.id(), SequenceNo(1));
+    assert_eq!(delivery.rcv_settle_mode(), ReceiverSettleMode::First);
+    assert_eq!(client.link(handle).unsettled().count(), 3);
+
+    for (tag, state) in [
+        (b"c", State::Released),
+        (b"a", State::Accepted),
+        (b"b", State::Accepted),
+    ] {
+        server
+            .links
+            .dispose(remote, tag, Some(state), true)
+            .unwrap();
+    }
+    assert_eq!(server.link(remote).unsettled().count(), 0);
+    server
+        .links
+        .send_dispositions(&mut server.connection, &mut server.sessions)
+        .unwrap();
+    // the contiguous deliveries of the same state in a single range
+    assert_eq!(
+        ok(deliver(&mut server, &mut client)),
+        [
+            Read::Disposition(SequenceNo(0), SequenceNo(1)),
+            Read::Disposition(SequenceNo(2), SequenceNo(2))
+        ]
+    );
+    assert_eq!(client.link(handle).unsettled().count(), 0);
+}
+
+#[test]
+fn backward_disposition() {
+    let (mut client, mut server) = begun();
+    let (handle, remote) = attached(&mut client, &mut server, 0, ReceiverSettleMode::First);
+    sent(
+        &mut client,
+        &mut server,
+        handle,
+        remote,
+        &[b"a", b"b", b"c"],
+    );
+    // from the last delivery back to the first, which would span nearly every id
+    let disposition = Disposition {
+        role: Role::Receiver,
+        first: SequenceNo(2),
+        last: Some(SequenceNo(0)),
+        settled: true,
+        state: None,
+        batchable: false,
+    };
+    let channel = server.links.channel();
+    server
+        .sessions
+        .send(&mut server.connection, channel, disposition)
+        .unwrap();
+    let read = deliver(&mut server, &mut client);
+    assert_eq!(
+        read[0].as_ref().unwrap_err().to_string(),
+        BACKWARD_DISPOSITION
+    );
+    assert_eq!(
+        client.sessions.state(client.links.channel()),
+        SessionState::Discarding
+    );
+    assert_eq!(ok(deliver(&mut client, &mut server)), [Read::End(0)]);
+}
+
+#[test]
+fn settle_second() {
+    let (mut client, mut server) = begun();
+    let (handle, remote) = attached(&mut client, &mut server, 0, ReceiverSettleMode::Second);
+    sent(&mut client, &mut server, handle, remote, &[b"a"]);
+
+    // the receiver settles after the sender
+    let modified = State::Modified {
+        delivery_failed: true,
+        undeliverable_here: false,
+    };
+    let error = server
+        .links
+        .dispose(remote, b"a", Some(modified), true)
+        .unwrap_err();
+    assert_eq!(error.to_string(), SETTLE_MODE_VIOLATION);
+    server
+        .links
+        .dispose(remote, b"a", Some(modified), false)
+        .unwrap();
+    server
+        .links
+        .send_dispositions(&mut server.connection, &mut server.sessions)
+        .unwrap();
+    assert_eq!(
+        ok(deliver(&mut server, &mut client)),
+        [Read::Disposition(SequenceNo(0), SequenceNo(0))]
+    );
+    let delivery = client.link(handle).delivery(b"a").unwrap();
+    assert_eq!(delivery.remote_state(), Some(modified));
+
+    client.links.dispose(handle, b"a", None, true).unwrap();
+    assert!(client.link(handle).delivery(b"a").is_none());
+    client
+        .links
+        .send_dispositions(&mut client.connection, &mut client.sessions)
+        .unwrap();
+    assert_eq!(
+        ok(deliver(&mut client, &mut server)),
+        [Read::Disposition(SequenceNo(0), SequenceNo(0))]
+    );
+    assert!(server.link(remote).delivery(b"a").is_none());
+}
+
+#[test]
+fn settle_mode_violations() {
+    let (mut client, mut server) = begun();
+    let (handle, remote) = attached(&mut client, &mut server, 0, ReceiverSettleMode::First);
+    server
+        .links
+        .flow(
+            &mut server.connection,
+            &mut server.sessions,
+            remote,
+            1,
+            false,
+        )
+        .unwrap();
+    deliver(&mut server, &mut client);
+    let mut transfers = [
+        transfer(b"a", false),
+        transfer(b"a", false),
+        transfer(b"a", false),
+    ];
+    // settled on a link whose sender settle mode is unsettled
+    transfers[0].settled = Some(true);
+    // settling second on a link whose receiver settles first
+    transfers[1].rcv_settle_mode = Some(ReceiverSettleMode::Second);
+    transfers[2].delivery_tag = None;
+    let errors: Vec<_> = transfers
+        .into_iter()
+        .map(|transfer| {
+            client
+                .links
+                .transfer(
+                    &mut client.connection,
+                    &mut client.sessions,
+                    handle,
+                    transfer,
+                    b"data",
+                )
+                .unwrap_err()
+                .to_string()
+        })
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            SETTLE_MODE_VIOLATION,
+            SETTLE_MODE_VIOLATION,
+            MISSING_DELIVERY
+        ]
+    );
+
+    // the receiver detaches on the violations of the sender
+    let mut unwanted = transfer(b"a", false);
+    unwanted.handle = handle;
+    unwanted.rcv_settle_mode = Some(ReceiverSettleMode::Second);
+    client
+        .sessions
+        .transfer(
+            &mut client.connection,
+            client.links.channel(),
+            unwanted,
+            b"data",
+        )
+        .unwrap();
+    let read = deliver(&mut client, &mut server);
+    assert_eq!(
+        read[0].as_ref().unwrap_err().to_string(),
+        SETTLE_MODE_VIOLATION
+    );
+    assert_eq!(server.links.state(remote), LinkState::DetachSent);
+}
+
+#[test]
+fn unbounded_credit_and_window() {
+    let (mut client, mut server) = begun();
+    let (handle, remote) = attached(&mut client, &mut server, 0, ReceiverSettleMode::First);
+    let channel = server.links.channel();
+    server
+        .sessions
+        .set_incoming_window(&mut server.connection, channel, u32::MAX)
+        .unwrap();
+    server
+        .links
+        .flow(
+            &mut server.connection,
+            &mut server.sessions,
+            remote,
+            u32::MAX,
+            false,
+        )
+        .unwrap();
+    deliver(&mut server, &mut client);
+    let session = client.sessions.get(channel).unwrap();
+    assert_eq!(session.remote_incoming_window(), u32::MAX);
+    assert_eq!(client.link(handle).link_credit(), u32::MAX);
+
+    client
+        .links
+        .transfer(
+            &mut client.connection,
+            &mut client.sessions,
+            handle,
+            transfer(b"a", false),
+            b"data",
+        )
+        .unwrap();
+    // the sender tells its state, the receiver keeps the credit left
+    client
+        .links
+        .set_available(&mut client.connection, &mut client.sessions, handle, 1)
+        .unwrap();
+    deliver(&mut client, &mut server);
+    assert_eq!(server.link(remote).link_credit(), u32::MAX - 1);
+    assert_eq!(
+        client
+            .sessions
+            .get(channel)
+            .unwrap()
+            .remote_incoming_window(),
+        u32::MAX - 1
+    );
+}
+
+/// A `received` without its section-offset.
+const MALFORMED_STATE: &[u8] = &[0x00, 0x53, 0x23, 0xc0, 0x03, 0x01, 0x52, 0x01];
+
+#[test]
+fn malformed_state() {
+    let (mut client, mut server) = begun();
+    let (handle, remote) = attached(&mut client, &mut server, 0, ReceiverSettleMode::First);
+    server
+        .links
+        .flow(
+            &mut server.connection,
+            &mut server.sessions,
+            remote,
+            1,
+            false,
+        )
+        .unwrap();
+    deliver(&mut server, &mut client);
+    let mut malformed = transfer(b"a", false);
+    malformed.state = Some(Requires::new(
+        Value::decode(&mut &MALFORMED_STATE[..]).unwrap(),
+    ));
+    client
+        .links
+        .transfer(
+            &mut client.connection,
+            &mut client.sessions,
+            handle,
+            malformed,
+            b"data",
+        )
+        .unwrap();
+    let read = deliver(&mut client, &mut server);
+    assert_eq!(
+        read[0].as_ref().unwrap_err().to_string(),
+        INVALID_DELIVERY_STATE
+    );
+    assert_eq!(server.links.state(remote), LinkState::DetachSent);
+
+    // a disposition isn't of a link, the session ends
+    let disposition = Disposition {
+        role: Role::Sender,
+        first: SequenceNo(0),
+        last: None,
+        settled: false,
+        state: Some(Requires::new(
+            Value::decode(&mut &MALFORMED_STATE[..]).unwrap(),
+        )),
+        batchable: false,
+    };
+    let channel = client.links.channel();
+    client
+        .sessions
+        .send(&mut client.connection, channel, disposition)
+        .unwrap();
+    let read = deliver(&mut client, &mut server);
+    assert_eq!(
+        read[0].as_ref().unwrap_err().to_string(),
+        INVALID_DELIVERY_STATE
+    );
+    assert_eq!(server.sessions.state(channel), SessionState::Discarding);
+}
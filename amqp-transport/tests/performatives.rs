@@ -0,0 +1,274 @@
+use amqp_transport::{
+    definitions::{
+        register_all, AmqpError, ConnectionError, Handle, ReceiverSettleMode, Role, SequenceNo,
+    },
+    performative::*,
+};
+use amqp_types::{
+    codec::{BufWriter, Decode, Encode, EncodeMode, Sink},
+    provides::archetype,
+    types::Type,
+    Descriptor, Value,
+};
+
+/// Decode `bytes` as any performative, then write it back and check it's written as it came.
+fn round_trip(bytes: &[u8]) -> Performative<'_> {
+    let value = Value::decode(&mut &bytes[..]).unwrap();
+    let performative = Performative::try_from_value(value).unwrap();
+    assert_eq!(performative.encoded_size(EncodeMode::Compact), bytes.len());
+    let mut writer = BufWriter::new(Vec::new()).with_mode(EncodeMode::Compact);
+    writer.write_amqp_value(performative.clone()).unwrap();
+    assert_eq!(writer.into_inner(), bytes);
+    performative
+}
+
+#[test]
+fn open() {
+    let bytes = [
+        0x00, 0x53, 0x10, // amqp:open:list
+        0xc0, 0x1d, 0x05, // list8, 5 fields
+        0xa1, 0x02, b'c', b'1', // container-id
+        0xa1, 0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't', // hostname
+        0x70, 0x00, 0x01, 0x00, 0x00, // max-frame-size
+        0x60, 0x00, 0xff, // channel-max
+        0x70, 0x00, 0x00, 0x75, 0x30, // idle-time-out
+    ];
+    let Performative::Open(open) = round_trip(&bytes) else {
+        panic!("expected an open")
+    };
+    assert_eq!(open.container_id, "c1");
+    assert_eq!(open.hostname, Some("localhost"));
+    assert_eq!(open.max_frame_size, 65536);
+    assert_eq!(open.channel_max, 255);
+    assert_eq!(open.idle_time_out.map(|t| t.0), Some(30000));
+    assert!(open.outgoing_locales.is_empty());
+    assert!(open.properties.is_none());
+
+    // only the container id is mandatory
+    let bytes = [0x00, 0x53, 0x10, 0xc0, 0x04, 0x01, 0xa1, 0x01, b'c'];
+    let Performative::Open(open) = round_trip(&bytes) else {
+        panic!("expected an open")
+    };
+    assert_eq!(open.max_frame_size, u32::MAX);
+    assert_eq!(open.channel_max, u16::MAX);
+    let bytes = [0x00, 0x53, 0x10, 0x45];
+    let value = Value::decode(&mut &bytes[..]).unwrap();
+    assert!(Open::try_from_value(value).is_err());
+}
+
+#[test]
+fn open_written_compact() {
+    let mut open = Open::new("c1");
+    open.offered_capabilities = vec![amqp_types::primitive::Symbol::new(b"ANONYMOUS-RELAY")];
+    let mut writer = BufWriter::new(Vec::new()).with_mode(EncodeMode::Compact);
+    writer.write_amqp_value(open).unwrap();
+    let mut expected = vec![
+        0x00, 0x53, 0x10, 0xc0, 0x22, 0x08, // list8, 8 fields
+        0xa1, 0x02, b'c', b'1', // container-id
+        0x40, // hostname
+        0x70, 0xff, 0xff, 0xff, 0xff, // max-frame-size
+        0x60, 0xff, 0xff, // channel-max
+        0x40, 0x40, 0x40, // idle-time-out and locales
+        0xa3, 0x0f, // offered-capabilities, a single symbol
+    ];
+    expected.extend_from_slice(b"ANONYMOUS-RELAY");
+    assert_eq!(writer.into_inner(), expected);
+}
+
+#[test]
+fn begin() {
+    let bytes = [
+        0x00, 0x53, 0x11, // amqp:begin:list
+        0xc0, 0x0e, 0x04, // list8, 4 fields
+        0x40, // remote-channel
+        0x52, 0x01, // next-outgoing-id
+        0x70, 0x00, 0x00, 0x08, 0x00, // incoming-window
+        0x70, 0x00, 0x00, 0x08, 0x00, // outgoing-window
+    ];
+    let Performative::Begin(begin) = round_trip(&bytes) else {
+        panic!("expected a begin")
+    };
+    assert_eq!(begin.remote_channel, None);
+    assert_eq!(begin.next_outgoing_id, SequenceNo(1));
+    assert_eq!(begin.incoming_window, 2048);
+    assert_eq!(begin.outgoing_window, 2048);
+    assert_eq!(begin.handle_max, Handle(u32::MAX));
+}
+
+#[test]
+fn attach() {
+    let bytes = [
+        0x00, 0x53, 0x12, // amqp:attach:list
+        0xc0, 0x17, 0x06, // list8, 6 fields
+        0xa1, 0x04, b'l', b'i', b'n', b'k', // name
+        0x43, // handle
+        0x41, // role, receiver
+        0x50, 0x02, // snd-settle-mode, mixed
+        0x50, 0x00, // rcv-settle-mode, first
+        0x00, 0x53, 0x28, 0xc0, 0x05, 0x01, 0xa1, 0x02, b'q', b'1', // source, its address
+    ];
+    let Performative::Attach(attach) = round_trip(&bytes) else {
+        panic!("expected an attach")
+    };
+    assert_eq!(attach.name, "link");
+    assert_eq!(attach.handle, Handle(0));
+    assert_eq!(attach.role, Role::Receiver);
+    assert_eq!(attach.rcv_settle_mode, ReceiverSettleMode::First);
+    let source = attach.source.unwrap();
+    assert!(matches!(
+        source.descriptor(),
+        Some(Descriptor::Numeric(0x28))
+    ));
+    assert!(attach.target.is_none());
+    assert!(!attach.incomplete_unsettled);
+}
+
+#[test]
+fn flow() {
+    let bytes = [
+        0x00, 0x53, 0x13, // amqp:flow:list
+        0xc0, 0x13, 0x07, // list8, 7 fields
+        0x52, 0x01, // next-incoming-id
+        0x70, 0x00, 0x00, 0x08, 0x00, // incoming-window
+        0x52, 0x01, // next-outgoing-id
+        0x70, 0x00, 0x00, 0x08, 0x00, // outgoing-window
+        0x43, // handle
+        0x43, // delivery-count
+        0x52, 0x64, // link-credit
+    ];
+    let Performative::Flow(flow) = round_trip(&bytes) else {
+        panic!("expected a flow")
+    };
+    assert_eq!(flow.next_incoming_id, Some(SequenceNo(1)));
+    assert_eq!(flow.handle, Some(Handle(0)));
+    assert_eq!(flow.delivery_count, Some(SequenceNo(0)));
+    assert_eq!(flow.link_credit, Some(100));
+    assert_eq!(flow.available, None);
+    assert!(!flow.drain && !flow.echo);
+}
+
+#[test]
+fn transfer() {
+    let bytes = [
+        0x00, 0x53, 0x14, // amqp:transfer:list
+        0xc0, 0x08, 0x05, // list8, 5 fields
+        0x43, // handle
+        0x43, // delivery-id
+        0xa0, 0x01, 0x00, // delivery-tag
+        0x43, // message-format
+        0x41, // settled
+    ];
+    let Performative::Transfer(transfer) = round_trip(&bytes) else {
+        panic!("expected a transfer")
+    };
+    assert_eq!(transfer.delivery_id, Some(SequenceNo(0)));
+    assert_eq!(transfer.delivery_tag.unwrap().0.as_bytes(), [0x00]);
+    assert_eq!(transfer.settled, Some(true));
+    assert!(!transfer.more);
+
+    // delivery tags are at most 32 bytes
+    let mut bytes = vec![0x00, 0x53, 0x14, 0xc0, 0x26, 0x03, 0x43, 0x43, 0xa0, 0x21];
+    bytes.extend_from_slice(&[0; 33]);
+    let value = Value::decode(&mut bytes.as_slice()).unwrap();
+    assert!(Transfer::try_from_value(value).is_err());
+}
+
+#[test]
+fn disposition() {
+    let bytes = [
+        0x00, 0x53, 0x15, // amqp:disposition:list
+        0xc0, 0x09, 0x05, // list8, 5 fields
+        0x41, // role, receiver
+        0x43, // first
+        0x40, // last
+        0x41, // settled
+        0x00, 0x53, 0x24, 0x45, // state, accepted
+    ];
+    let Performative::Disposition(disposition) = round_trip(&bytes) else {
+        panic!("expected a disposition")
+    };
+    assert_eq!(disposition.role, Role::Receiver);
+    assert_eq!(disposition.first, SequenceNo(0));
+    assert_eq!(disposition.last, None);
+    assert!(disposition.settled);
+    let state = disposition.state.unwrap();
+    assert!(matches!(
+        state.descriptor(),
+        Some(Descriptor::Numeric(0x24))
+    ));
+    register_all();
+    let provider = state.provider().unwrap();
+    assert!(provider.type_name.ends_with("Accepted"));
+    assert_eq!(provider.archetype, archetype::DELIVERY_STATE);
+}
+
+#[test]
+fn detach_and_end() {
+    let bytes = [0x00, 0x53, 0x16, 0xc0, 0x04, 0x02, 0x52, 0x01, 0x41];
+    let Performative::Detach(detach) = round_trip(&bytes) else {
+        panic!("expected a detach")
+    };
+    assert_eq!(detach.handle, Handle(1));
+    assert!(detach.closed);
+    assert!(detach.error.is_none());
+
+    let bytes = [0x00, 0x53, 0x17, 0x45];
+    let Performative::End(end) = round_trip(&bytes) else {
+        panic!("expected an end")
+    };
+    assert!(end.error.is_none());
+}
+
+#[test]
+fn close() {
+    let mut bytes = vec![
+        0x00, 0x53, 0x18, // amqp:close:list
+        0xc0, 0x24, 0x01, // list8, 1 field
+        0x00, 0x53, 0x1d, // amqp:error:list
+        0xc0, 0x1e, 0x02, // list8, 2 fields
+        0xa3, 0x16, // condition
+    ];
+    bytes.extend_from_slice(b"amqp:connection:forced");
+    bytes.extend_from_slice(&[0xa1, 0x03, b'b', b'y', b'e']); // description
+    let Performative::Close(close) = round_trip(&bytes) else {
+        panic!("expected a close")
+    };
+    let error = close.error.unwrap();
+    assert_eq!(
+        error.condition.get::<ConnectionError>().unwrap(),
+        ConnectionError::ConnectionForced
+    );
+    assert!(error.condition.get::<AmqpError>().is_err());
+    assert_eq!(error.description, Some("bye"));
+}
+
+#[test]
+fn vendor_error_condition() {
+    let mut bytes = vec![0x00, 0x53, 0x18, 0xc0, 0x22, 0x01, 0x00, 0x53, 0x1d];
+    bytes.extend_from_slice(&[0xc0, 0x1c, 0x01, 0xa3, 0x19]);
+    bytes.extend_from_slice(b"com.microsoft:server-busy");
+    let Performative::Close(close) = round_trip(&bytes) else {
+        panic!("expected a close")
+    };
+    let condition = close.error.unwrap().condition;
+    assert!(condition.get::<AmqpError>().is_err());
+    assert_eq!(
+        condition
+            .value()
+            .clone()
+            .construct()
+            .unwrap()
+            .as_symbol()
+            .unwrap()
+            .as_bytes(),
+        b"com.microsoft:server-busy"
+    );
+}
+
+#[test]
+fn unknown_performative() {
+    let bytes = [0x00, 0x53, 0x30, 0x45];
+    let value = Value::decode(&mut &bytes[..]).unwrap();
+    let error = Performative::try_from_value(value).unwrap_err();
+    assert_eq!(error.to_string(), amqp_types::error::UNKNOWN_DESCRIPTOR);
+}
@@ -0,0 +1,84 @@
+use std::cmp::Ordering;
+
+use amqp_transport::definitions::SequenceNo;
+use proptest::prelude::*;
+
+const HALF: u32 = SequenceNo::HALF;
+
+/// Numbers close to the ends of the space and to its middle, where the wraps are.
+fn boundary() -> impl Strategy<Value = u32> {
+    prop_oneof![
+        0..1024u32,
+        HALF - 1024..HALF + 1024,
+        u32::MAX - 1024..=u32::MAX,
+        any::<u32>(),
+    ]
+}
+
+#[test]
+fn wraps() {
+    assert_eq!(SequenceNo(u32::MAX) + 1, SequenceNo(0));
+    assert_eq!(SequenceNo(1) - SequenceNo(u32::MAX), 2);
+    assert!(SequenceNo(u32::MAX) < SequenceNo(0));
+    assert!(SequenceNo(HALF - 1) > SequenceNo(0));
+    assert!(SequenceNo(HALF + 1) < SequenceNo(0));
+    // exactly half the space away is neither before nor after
+    assert_eq!(SequenceNo(HALF).partial_cmp(&SequenceNo(0)), None);
+    assert_eq!(
+        SequenceNo(u32::MAX - 1)
+            .range_inclusive(SequenceNo(1))
+            .collect::<Vec<_>>(),
+        [u32::MAX - 1, u32::MAX, 0, 1].map(SequenceNo)
+    );
+    // backwards, or half the space away, is no range rather than one across the whole space
+    assert_eq!(SequenceNo(1).range_inclusive(SequenceNo(0)).count(), 0);
+    assert_eq!(SequenceNo(0).range_inclusive(SequenceNo(HALF)).count(), 0);
+}
+
+proptest! {
+    #[test]
+    fn added_is_after(start in boundary(), n in 1..HALF) {
+        let start = SequenceNo(start);
+        let end = start + n;
+        prop_assert!(start < end);
+        prop_assert!(end > start);
+        prop_assert_eq!(end - start, n);
+    }
+
+    #[test]
+    fn added_past_half_is_before(start in boundary(), n in HALF + 1..=u32::MAX) {
+        let start = SequenceNo(start);
+        prop_assert!(start + n < start);
+        prop_assert!(start + HALF != start);
+        prop_assert_eq!(start.partial_cmp(&(start + HALF)), None);
+    }
+
+    #[test]
+    fn antisymmetric(a in boundary(), b in boundary()) {
+        let (a, b) = (SequenceNo(a), SequenceNo(b));
+        prop_assert_eq!(a < b, b > a);
+        prop_assert_eq!(a == b, a.partial_cmp(&b) == Some(Ordering::Equal));
+        // any two numbers not half the space apart are ordered one way only
+        if a != b && b - a != HALF {
+            prop_assert!((a < b) ^ (b < a));
+        }
+    }
+
+    #[test]
+    fn distance_is_inverse_of_addition(a in boundary(), n in any::<u32>()) {
+        let a = SequenceNo(a);
+        prop_assert_eq!((a + n) - a, n);
+    }
+
+    #[test]
+    fn range(start in boundary(), len in 0..64u32) {
+        let start = SequenceNo(start);
+        let range: Vec<_> = start.range_inclusive(start + len).collect();
+        prop_assert_eq!(range.len() as u32, len + 1);
+        prop_assert_eq!(range[0], start);
+        prop_assert!(range.windows(2).all(|pair| pair[1] - pair[0] == 1 && pair[0] < pair[1]));
+        if len > 0 {
+            prop_assert_eq!((start + len).range_inclusive(start).count(), 0);
+        }
+    }
+}
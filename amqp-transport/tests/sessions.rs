@@ -0,0 +1,323 @@
+mod common;
+
+use amqp_transport::{
+    connections::{IncomingChannelEndpoint, OutgoingChannelEndpoint},
+    definitions::{Handle, ReceiverSettleMode, Role, SenderSettleMode, SequenceNo},
+    performative::{Attach, Transfer},
+    sessions::{SessionConfig, SessionState, HANDLE_MAX_EXCEEDED, WINDOW_CLOSED, WINDOW_VIOLATION},
+    sm::connection::ConnectionState,
+};
+use common::{deliver, ok, opened, Kind, Layer, Peer, Read};
+
+/// Begin a session from `client`, accepted by `server` with `config`.
+fn begun(
+    client: &mut Peer,
+    server: &mut Peer,
+    config: SessionConfig,
+) -> (OutgoingChannelEndpoint, OutgoingChannelEndpoint) {
+    let local = client
+        .sessions
+        .begin(&mut client.connection, SessionConfig::default())
+        .unwrap();
+    let [Read::Begin(number)] = ok(deliver(client, server))[..] else {
+        panic!("expected a begin")
+    };
+    let remote = OutgoingChannelEndpoint { number };
+    server
+        .sessions
+        .accept(&mut server.connection, remote, config)
+        .unwrap();
+    assert_eq!(ok(deliver(server, client)), [Read::Begin(local.number)]);
+    (local, remote)
+}
+
+fn transfer(handle: u32) -> Transfer<'static> {
+    Transfer {
+        handle: Handle(handle),
+        delivery_id: Some(SequenceNo(0)),
+        delivery_tag: None,
+        message_format: Some(0),
+        settled: Some(true),
+        more: false,
+        rcv_settle_mode: None,
+        state: None,
+        resume: false,
+        aborted: false,
+        batchable: false,
+    }
+}
+
+fn attach(handle: u32) -> Attach<'static> {
+    Attach {
+        name: "link",
+        handle: Handle(handle),
+        role: Role::Sender,
+        snd_settle_mode: SenderSettleMode::Mixed,
+        rcv_settle_mode: ReceiverSettleMode::First,
+        source: None,
+        target: None,
+        unsettled: None,
+        incomplete_unsettled: false,
+        initial_delivery_count: Some(SequenceNo(0)),
+        max_message_size: None,
+        offered_capabilities: Vec::new(),
+        desired_capabilities: Vec::new(),
+        properties: None,
+    }
+}
+
+#[test]
+fn begin_and_end() {
+    let (mut client, mut server) = opened(Layer::Sessions);
+    let config = SessionConfig {
+        next_outgoing_id: SequenceNo(100),
+        incoming_window: 10,
+        outgoing_window: 20,
+        handle_max: Handle(7),
+    };
+    let (local, remote) = begun(&mut client, &mut server, config);
+
+    let session = client.sessions.get(local).unwrap();
+    assert_eq!(session.state(), SessionState::Mapped);
+    assert_eq!(session.incoming_channel().unwrap().number, remote.number);
+    assert_eq!(session.next_incoming_id(), SequenceNo(100));
+    assert_eq!(session.remote_incoming_window(), 10);
+    assert_eq!(session.remote_outgoing_window(), 20);
+    assert_eq!(session.remote_handle_max(), Handle(7));
+    let session = server.sessions.get(remote).unwrap();
+    assert_eq!(session.state(), SessionState::Mapped);
+    assert_eq!(
+        session.remote_incoming_window(),
+        SessionConfig::default().incoming_window
+    );
+
+    client
+        .sessions
+        .end(&mut client.connection, local, None)
+        .unwrap();
+    assert_eq!(client.sessions.state(local), SessionState::EndSent);
+    assert_eq!(ok(deliver(&mut client, &mut server)), [Read::End(0)]);
+    assert_eq!(server.sessions.state(remote), SessionState::EndRcvd);
+    server
+        .sessions
+        .end(&mut server.connection, remote, None)
+        .unwrap();
+    assert_eq!(server.sessions.state(remote), SessionState::Unmapped);
+    assert_eq!(ok(deliver(&mut server, &mut client)), [Read::End(0)]);
+    assert_eq!(client.sessions.state(local), SessionState::Unmapped);
+}
+
+#[test]
+fn end_before_answer() {
+    let (mut client, mut server) = opened(Layer::Sessions);
+    let local = client
+        .sessions
+        .begin(&mut client.connection, SessionConfig::default())
+        .unwrap();
+    assert_eq!(ok(deliver(&mut client, &mut server)), [Read::Begin(0)]);
+    let remote = OutgoingChannelEndpoint { number: 0 };
+    server
+        .sessions
+        .accept(&mut server.connection, remote, SessionConfig::default())
+        .unwrap();
+    // the end crosses the answering begin
+    client
+        .sessions
+        .end(&mut client.connection, local, None)
+        .unwrap();
+    assert_eq!(client.sessions.state(local), SessionState::EndSent);
+    assert_eq!(ok(deliver(&mut server, &mut client)), [Read::Begin(0)]);
+    assert_eq!(client.sessions.state(local), SessionState::EndSent);
+    assert_eq!(
+        client.sessions.get(local).unwrap().incoming_channel(),
+        Some(IncomingChannelEndpoint { number: 0 })
+    );
+
+    assert_eq!(ok(deliver(&mut client, &mut server)), [Read::End(0)]);
+    server
+        .sessions
+        .end(&mut server.connection, remote, None)
+        .unwrap();
+    assert_eq!(ok(deliver(&mut server, &mut client)), [Read::End(0)]);
+    assert_eq!(client.sessions.state(local), SessionState::Unmapped);
+    assert_eq!(client.connection.state(), ConnectionState::Opened);
+}
+
+#[test]
+fn channel_mapping() {
+    let (mut client, mut server) = opened(Layer::Sessions);
+    // the channel 0 of the server is taken by its own session
+    let own = server
+        .sessions
+        .begin(&mut server.connection, SessionConfig::default())
+        .unwrap();
+    assert_eq!(own.number, 0);
+    assert_eq!(ok(deliver(&mut server, &mut client)), [Read::Begin(0)]);
+
+    let local = client
+        .sessions
+        .begin(&mut client.connection, SessionConfig::default())
+        .unwrap();
+    // the channel 0 of the client answers the server, this one is the next
+    assert_eq!(local.number, 1);
+    assert_eq!(ok(deliver(&mut client, &mut server)), [Read::Begin(1)]);
+    let session = server
+        .sessions
+        .get(OutgoingChannelEndpoint { number: 1 })
+        .unwrap();
+    assert_eq!(session.incoming_channel().unwrap().number, 1);
+    assert_eq!(session.state(), SessionState::BeginRcvd);
+}
+
+#[test]
+fn windows() {
+    let (mut client, mut server) = opened(Layer::Sessions);
+    let config = SessionConfig {
+        incoming_window: 2,
+        ..SessionConfig::default()
+    };
+    let (local, remote) = begun(&mut client, &mut server, config);
+
+    for _ in 0..2 {
+        client
+            .sessions
+            .transfer(&mut client.connection, local, transfer(0), b"data")
+            .unwrap();
+    }
+    let error = client
+        .sessions
+        .transfer(&mut client.connection, local, transfer(0), b"data")
+        .unwrap_err();
+    assert_eq!(error.to_string(), WINDOW_CLOSED);
+    assert_eq!(
+        ok(deliver(&mut client, &mut server)),
+        [Read::Link(0, Kind::Transfer), Read::Link(0, Kind::Transfer)]
+    );
+    let session = server.sessions.get(remote).unwrap();
+    assert_eq!(session.next_incoming_id(), SequenceNo(2));
+    assert_eq!(session.incoming_window(), 0);
+
+    server
+        .sessions
+        .set_incoming_window(&mut server.connection, remote, 5)
+        .unwrap();
+    assert_eq!(ok(deliver(&mut server, &mut client)), [Read::Flow(0)]);
+    let session = client.sessions.get(local).unwrap();
+    assert_eq!(session.next_outgoing_id(), SequenceNo(2));
+    assert_eq!(session.remote_incoming_window(), 5);
+}
+
+#[test]
+fn echo() {
+    let (mut client, mut server) = opened(Layer::Sessions);
+    let (local, _) = begun(&mut client, &mut server, SessionConfig::default());
+    let mut flow = client.sessions.get(local).unwrap().flow();
+    flow.echo = true;
+    client
+        .sessions
+        .send(&mut client.connection, local, flow)
+        .unwrap();
+    assert_eq!(ok(deliver(&mut client, &mut server)), [Read::Flow(0)]);
+    assert_eq!(ok(deliver(&mut server, &mut client)), [Read::Flow(0)]);
+}
+
+#[test]
+fn echo_across_channels() {
+    let (mut client, mut server) = opened(Layer::Sessions);
+    // both begin on their channel 0, each maps the begin of the other to its channel 1
+    let local = client
+        .sessions
+        .begin(&mut client.connection, SessionConfig::default())
+        .unwrap();
+    server
+        .sessions
+        .begin(&mut server.connection, SessionConfig::default())
+        .unwrap();
+    assert_eq!(ok(deliver(&mut client, &mut server)), [Read::Begin(1)]);
+    let remote = OutgoingChannelEndpoint { number: 1 };
+    server
+        .sessions
+        .accept(&mut server.connection, remote, SessionConfig::default())
+        .unwrap();
+    assert_eq!(
+        ok(deliver(&mut server, &mut client)),
+        [Read::Begin(1), Read::Begin(local.number)]
+    );
+
+    let mut flow = client.sessions.get(local).unwrap().flow();
+    flow.echo = true;
+    client
+        .sessions
+        .send(&mut client.connection, local, flow)
+        .unwrap();
+    assert_eq!(ok(deliver(&mut client, &mut server)), [Read::Flow(1)]);
+    // the echo comes back to the session which asked for it
+    assert_eq!(ok(deliver(&mut server, &mut client)), [Read::Flow(0)]);
+}
+
+#[test]
+fn window_violation() {
+    let (mut client, mut server) = opened(Layer::Sessions);
+    let config = SessionConfig {
+        incoming_window: 1,
+        ..SessionConfig::default()
+    };
+    let (local, remote) = begun(&mut client, &mut server, config);
+    // past the session, which would refuse the second
+    for _ in 0..2 {
+        client
+            .connection
+            .send(local.number, transfer(0), b"data")
+            .unwrap();
+    }
+    let read = deliver(&mut client, &mut server);
+    assert_eq!(read[0].as_ref().unwrap(), &Read::Link(0, Kind::Transfer));
+    assert_eq!(read[1].as_ref().unwrap_err().to_string(), WINDOW_VIOLATION);
+    assert_eq!(server.sessions.state(remote), SessionState::Discarding);
+
+    // the frames of the peer are dropped until its end
+    client
+        .sessions
+        .send(&mut client.connection, local, attach(0))
+        .unwrap();
+    assert_eq!(ok(deliver(&mut server, &mut client)), [Read::End(0)]);
+    client
+        .sessions
+        .end(&mut client.connection, local, None)
+        .unwrap();
+    assert_eq!(client.sessions.state(local), SessionState::Unmapped);
+    assert_eq!(
+        ok(deliver(&mut client, &mut server)),
+        [Read::Nothing, Read::End(0)]
+    );
+    assert_eq!(server.sessions.state(remote), SessionState::Unmapped);
+}
+
+#[test]
+fn handle_max() {
+    let (mut client, mut server) = opened(Layer::Sessions);
+    let config = SessionConfig {
+        handle_max: Handle(3),
+        ..SessionConfig::default()
+    };
+    let (local, _) = begun(&mut client, &mut server, config);
+    assert_eq!(
+        client.sessions.get(local).unwrap().remote_handle_max(),
+        Handle(3)
+    );
+    client
+        .sessions
+        .send(&mut client.connection, local, attach(3))
+        .unwrap();
+    client
+        .sessions
+        .send(&mut client.connection, local, attach(4))
+        .unwrap();
+    let read = deliver(&mut client, &mut server);
+    assert_eq!(read[0].as_ref().unwrap(), &Read::Link(0, Kind::Attach));
+    assert_eq!(
+        read[1].as_ref().unwrap_err().to_string(),
+        HANDLE_MAX_EXCEEDED
+    );
+    assert_eq!(server.connection.state(), ConnectionState::Discarding);
+}
@@ -0,0 +1,62 @@
+use amqp_transport::version::{accept, negotiate, Negotiation, ProtocolId, Version};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[test]
+fn protocol_ids() {
+    assert_eq!(Version::V_1_0_0.as_bytes(), *b"AMQP\x00\x01\x00\x00");
+    assert_eq!(Version::TLS_V_1_0_0.as_bytes(), *b"AMQP\x02\x01\x00\x00");
+    assert_eq!(Version::SASL_V_1_0_0.as_bytes(), *b"AMQP\x03\x01\x00\x00");
+    let legacy = Version::try_parse(*b"AMQP\xd0\x01\x00\x00").unwrap();
+    assert_eq!(legacy.protocol_id, ProtocolId::Legacy);
+    let other = Version::try_parse(*b"AMQP\x01\x01\x00\x00").unwrap();
+    assert_eq!(other.protocol_id, ProtocolId::Other(1));
+    assert_eq!(other.as_bytes(), *b"AMQP\x01\x01\x00\x00");
+    assert!(Version::try_parse(*b"GET / HT").is_err());
+}
+
+#[test]
+fn negotiation() {
+    let supported = [Version::SASL_V_1_0_0, Version::V_1_0_0];
+    assert_eq!(
+        negotiate(*b"AMQP\x03\x01\x00\x00", &supported),
+        Negotiation::Accept(Version::SASL_V_1_0_0)
+    );
+    assert_eq!(
+        negotiate(*b"AMQP\xd0\x01\x00\x00", &supported),
+        Negotiation::Accept(Version::V_1_0_0)
+    );
+    // another version of a supported layer is answered with the version of that layer
+    assert_eq!(
+        negotiate(*b"AMQP\x00\x00\x09\x01", &supported),
+        Negotiation::Reject(Version::V_1_0_0)
+    );
+    assert_eq!(
+        negotiate(*b"AMQP\x02\x01\x00\x00", &supported),
+        Negotiation::Reject(Version::SASL_V_1_0_0)
+    );
+    assert_eq!(
+        negotiate(*b"GET / HT", &supported),
+        Negotiation::Reject(Version::SASL_V_1_0_0)
+    );
+}
+
+#[tokio::test]
+async fn accept_supported() {
+    let (mut client, mut server) = tokio::io::duplex(64);
+    client.write_all(b"AMQP\x00\x01\x00\x00").await.unwrap();
+    let version = accept(&mut server, &[Version::V_1_0_0]).await.unwrap();
+    assert_eq!(version, Version::V_1_0_0);
+    let mut answer = [0; 8];
+    client.read_exact(&mut answer).await.unwrap();
+    assert_eq!(answer, Version::V_1_0_0.as_bytes());
+}
+
+#[tokio::test]
+async fn reject_unsupported() {
+    let (mut client, mut server) = tokio::io::duplex(64);
+    client.write_all(b"AMQP\x02\x01\x00\x00").await.unwrap();
+    assert!(accept(&mut server, &[Version::V_1_0_0]).await.is_err());
+    let mut answer = Vec::new();
+    client.read_to_end(&mut answer).await.unwrap();
+    assert_eq!(answer, Version::V_1_0_0.as_bytes());
+}
@@ -1,6 +1,12 @@
-use darling::{ast, FromMeta};
+use darling::{
+    ast::{self, NestedMeta},
+    util::{Flag, PathList},
+    FromMeta,
+};
 use quote::{quote, ToTokens};
-use syn::{parse_quote, Expr};
+use syn::{parse_quote, Expr, ExprLit, Lit};
+
+use crate::descriptor::DescriptorOpts;
 
 #[derive(FromDeriveInput)]
 #[darling(attributes(amqp), forward_attrs(allow, doc, cfg), supports(struct_any))]
@@ -9,7 +15,50 @@ pub struct CompositeOpts {
     attrs: Vec<syn::Attribute>,
     generics: syn::Generics,
     data: ast::Data<(), CompositeFieldOpts>,
-    descriptor: Option<Expr>,
+    descriptor: Option<DescriptorOpts>,
+    /// the archetypes provided, as in the `provides` of the specification
+    provides: Option<ProvidesOpts>,
+}
+
+/// `provides = "delivery-state, outcome"`, each archetype standing for the `Require` type in scope
+/// named after it in upper camel case, or `provides(DeliveryState, Outcome)` naming the types.
+#[derive(Debug)]
+pub struct ProvidesOpts(Vec<syn::Path>);
+
+impl FromMeta for ProvidesOpts {
+    fn from_expr(expr: &Expr) -> darling::Result<Self> {
+        let Expr::Lit(ExprLit {
+            lit: Lit::Str(archetypes),
+            ..
+        }) = expr
+        else {
+            return Err(darling::Error::unexpected_expr_type(expr));
+        };
+        let value = archetypes.value();
+        let types = value.split(',').map(|archetype| {
+            let name = archetype
+                .trim()
+                .split('-')
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars
+                        .next()
+                        .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                        .unwrap_or_default()
+                })
+                .collect::<String>();
+            let mut ident = syn::parse_str::<syn::Ident>(&name).map_err(|_| {
+                darling::Error::custom(format!("`{}` isn't an archetype", archetype.trim()))
+                    .with_span(archetypes)
+            })?;
+            ident.set_span(archetypes.span());
+            Ok(syn::Path::from(ident))
+        });
+        types.collect::<darling::Result<_>>().map(Self)
+    }
+    fn from_list(items: &[NestedMeta]) -> darling::Result<Self> {
+        PathList::from_list(items).map(|types| Self(types.to_vec()))
+    }
 }
 
 #[derive(Debug, FromField)]
@@ -18,6 +67,111 @@ pub struct CompositeFieldOpts {
     ident: Option<syn::Ident>,
     ty: syn::Type,
     default: Option<syn::Expr>,
+    /// decoding fails if the field is absent or null, and it's always written
+    mandatory: Flag,
+    /// a `Vec<T>` read and written with [`amqp_types::types::Multiple`]
+    multiple: Flag,
+    /// a Rust only field, not in the list and `Default::default()` when decoded
+    skip: Flag,
+    /// a module with `decode(Value) -> io::Result<T>`, `encode(T, &mut W)` and
+    /// `encoded_size(&T, EncodeMode)`, used instead of the `Type` of the field which is then
+    /// always written
+    with: Option<syn::Path>,
+    /// the name of the field in the specification, its identifier in kebab case by default
+    rename: Option<syn::LitStr>,
+    /// the error returned when the decoded source of a `Restrict` type is rejected
+    restrict_err: Option<syn::Expr>,
+}
+
+impl CompositeFieldOpts {
+    fn ident(&self) -> &syn::Ident {
+        self.ident.as_ref().expect("should be named")
+    }
+    fn name(&self) -> String {
+        match &self.rename {
+            Some(name) => name.value(),
+            None => self.ident().to_string().replace('_', "-"),
+        }
+    }
+    /// Initializer of the field, taking its value out of `list`.
+    fn decode(&self) -> proc_macro2::TokenStream {
+        let ident = self.ident();
+        let ty = &self.ty;
+        if self.skip.is_present() {
+            return quote!(#ident: Default::default(),);
+        }
+        let value = match self.mandatory.is_present() {
+            true => quote! {
+                match list.next().transpose()? {
+                    Some(value) if !amqp_types::codec::Encode::is_null(&value) => value,
+                    _ => return Err(std::io::Error::other(amqp_types::error::MANDATORY_FIELD_MISSING)),
+                }
+            },
+            false => quote!(list.next().transpose()?.unwrap_or_default()),
+        };
+        if let Some(with) = &self.with {
+            return quote!(#ident: #with::decode(#value)?,);
+        }
+        if self.multiple.is_present() {
+            return quote!(#ident: amqp_types::types::Multiple::decode_multiple(#value)?,);
+        }
+        let decode = match &self.restrict_err {
+            Some(restrict_err) => quote! {
+                <#ty as amqp_types::types::Restrict>::restrict(
+                    <<#ty as amqp_types::types::Restrict>::Source as amqp_types::types::Type>::try_from_value(value)?
+                )
+                .map_err(|_| std::io::Error::other(#restrict_err))?
+            },
+            None => quote!(<#ty as amqp_types::types::Type>::try_from_value(value)?),
+        };
+        match &self.default {
+            Some(default_value) if !self.mandatory.is_present() => quote! {
+                #ident: match #value {
+                    value if amqp_types::codec::Encode::is_null(&value) => #default_value,
+                    value => #decode,
+                },
+            },
+            _ => quote! {
+                #ident: {
+                    let value = #value;
+                    #decode
+                },
+            },
+        }
+    }
+    fn write(&self) -> proc_macro2::TokenStream {
+        let ident = self.ident();
+        match (&self.with, self.multiple.is_present()) {
+            (Some(with), _) => quote!(#with::encode(self.#ident, writer)?),
+            (None, true) => {
+                quote!(amqp_types::types::Multiple::encode_multiple(self.#ident, writer)?)
+            }
+            (None, false) => quote!(writer.write_amqp_value(self.#ident)?),
+        }
+    }
+    fn size(&self) -> proc_macro2::TokenStream {
+        let ident = self.ident();
+        match (&self.with, self.multiple.is_present()) {
+            (Some(with), _) => quote!(#with::encoded_size(&self.#ident, mode)),
+            (None, true) => quote! {
+                amqp_types::types::Multiple::multiple_encoded_size(self.#ident.as_slice(), mode)
+            },
+            (None, false) => quote!(amqp_types::codec::Encode::encoded_size(&self.#ident, mode)),
+        }
+    }
+    /// Condition on `self` under which the field may be left out when it ends the list, `None` if
+    /// it must be written.
+    fn elided_when(&self) -> Option<proc_macro2::TokenStream> {
+        let ident = self.ident();
+        if self.mandatory.is_present() || self.with.is_some() {
+            return None;
+        }
+        Some(match &self.default {
+            _ if self.multiple.is_present() => quote!(self.#ident.is_empty()),
+            Some(default_value) => quote!(self.#ident == #default_value),
+            None => quote!(amqp_types::codec::Encode::is_null(&self.#ident)),
+        })
+    }
 }
 
 impl ToTokens for CompositeOpts {
@@ -28,48 +182,90 @@ impl ToTokens for CompositeOpts {
             generics,
             data,
             descriptor,
+            provides,
         } = self;
         let (r#impl, r#type, r#where) = generics.split_for_impl();
-        let descriptor = match descriptor {
-            Some(code) => {
+        // `Type` is implemented for any `'amqp`, declared by the struct or not
+        let mut amqp_generics = generics.clone();
+        if !generics.lifetimes().any(|def| def.lifetime.ident == "amqp") {
+            amqp_generics.params.insert(0, parse_quote!('amqp));
+        }
+        let (amqp_impl, _, _) = amqp_generics.split_for_impl();
+        let verify = match descriptor {
+            Some(_) => quote! {
+                <Self as amqp_types::types::Described>::DESCRIPTOR_ID
+                    .verify(value.constructor.descriptor.as_ref())?;
+            },
+            None => quote!(),
+        };
+        let (descriptor, described) = match descriptor {
+            Some(id) => (
                 quote! {
-                    Some(amqp_types::Descriptor::numeric(#code))
-                }
-            }
-            None => {
+                    Some(<Self as amqp_types::types::Described>::DESCRIPTOR_ID.descriptor())
+                },
                 quote! {
-                    None
+                    impl #r#impl amqp_types::types::Described for #ident #r#type #r#where {
+                        const DESCRIPTOR_ID: amqp_types::DescriptorId = #id;
+                    }
+                },
+            ),
+            None => (quote!(None), quote!()),
+        };
+        let provides = provides.as_ref().map(|provides| {
+            let archetypes = provides.0.iter();
+            let provide = provides.0.iter();
+            quote! {
+                impl #r#impl amqp_types::provides::Provides for #ident #r#type #r#where {
+                    const PROVIDES: &'static [&'static str] = &[
+                        #(<#archetypes as amqp_types::provides::Require>::ARCHETYPE),*
+                    ];
                 }
+                #(
+                    impl #r#impl amqp_types::provides::Provide<#provide> for #ident #r#type #r#where {}
+                )*
             }
-        };
+        });
         let fields = data.as_ref().take_struct().expect("should be struct");
-        let field_try_from = fields.fields.clone().into_iter().map(|opt| {
-            let CompositeFieldOpts { ident, default, ty } = opt;
-            let ident = ident.as_ref().expect("should be named");
-            match default {
-                Some(default_value) => {
-                    quote! {
-                        #ident: Option::<#ty>::try_from_value(list.next().transpose()?.unwrap_or_default())?.unwrap_or(#default_value),
-                    }
-                },
-                None => {
-                    quote! {
-                        #ident: <#ty>::try_from_value(list.next().transpose()?.unwrap_or_default())?,
-                    }
-                },
+        let field_try_from = fields.fields.iter().map(|opt| opt.decode());
+        // the fields in the list, in order
+        let listed = fields
+            .fields
+            .iter()
+            .filter(|opt| !opt.skip.is_present())
+            .collect::<Vec<_>>();
+        let field_names = listed.iter().map(|opt| opt.name());
+        // number of fields written, trailing null or default fields are left out
+        let field_count = listed
+            .iter()
+            .enumerate()
+            .fold(quote!(0usize), |prefix, (index, opt)| {
+                let count = index + 1;
+                match opt.elided_when() {
+                    Some(elided) => quote!(if #elided { #prefix } else { #count }),
+                    None => quote!(#count),
+                }
+            });
+        let field_encode = listed.iter().enumerate().map(|(index, opt)| {
+            let write = opt.write();
+            quote! {
+                if count > #index {
+                    #write;
+                }
             }
         });
-        let field_len = fields.fields.len();
-        let field_len: Expr = parse_quote!(#field_len);
-        let field_encode = fields.fields.into_iter().map(|opt| {
-            let CompositeFieldOpts { ident, .. } = opt;
-            let ident = ident.as_ref().expect("should be named");
+        let field_size = listed.iter().enumerate().map(|(index, opt)| {
+            let size = opt.size();
             quote! {
-                self.#ident.encode_default(writer)?;
+                + if count > #index {
+                    #size
+                } else {
+                    0
+                }
             }
         });
         let try_from = quote!(
             fn try_from_value(value: amqp_types::Value<'amqp>) -> Result<Self, std::io::Error> {
+                #verify
                 let Some(mut list) = value.construct()?.as_list() else {
                     return Err(std::io::Error::other(amqp_types::error::UNEXPECTED_TYPE))
                 };
@@ -89,19 +285,33 @@ impl ToTokens for CompositeOpts {
                 }
             }
             impl #r#impl amqp_types::types::Multiple for #ident #r#type #r#where {}
+            #described
+            #provides
+            impl #r#impl amqp_types::types::Composite for #ident #r#type #r#where {
+                const FIELDS: &'static [&'static str] = &[#(#field_names),*];
+            }
             impl #r#impl amqp_types::codec::Encode for #ident #r#type #r#where {
                 const DESCRIPTOR: Option<amqp_types::Descriptor<'static>> = #descriptor;
                 const ENCODE_DEFAULT_FORMAT_CODE: amqp_types::FormatCode = amqp_types::FormatCode::LIST32;
-                fn encode_data(self, format_code: amqp_types::FormatCode, writer: &mut amqp_types::codec::Writer) -> std::io::Result<()> {
-                    debug_assert_eq!(format_code, Self::ENCODE_DEFAULT_FORMAT_CODE);
-                    writer.write_items_32(move |writer: &mut amqp_types::codec::Writer| {
+                fn items_size(&self, mode: amqp_types::codec::EncodeMode) -> Option<(usize, usize)> {
+                    let count = #field_count;
+                    Some((count, 0 #(#field_size)*))
+                }
+                fn encode_data<W: amqp_types::codec::Sink>(self, format_code: amqp_types::FormatCode, writer: &mut W) -> std::io::Result<()> {
+                    let count = #field_count;
+                    let fwrite = move |writer: &mut W| {
                         #(#field_encode)*
-                        std::io::Result::Ok(#field_len)
-                    })?;
-                    Ok(())
+                        std::io::Result::Ok(count)
+                    };
+                    match format_code {
+                        amqp_types::FormatCode::LIST0 => Ok(()),
+                        amqp_types::FormatCode::LIST8 => writer.write_items_8(fwrite),
+                        amqp_types::FormatCode::LIST32 => writer.write_items_32(fwrite),
+                        code => panic!("invalid format code {code:?}"),
+                    }
                 }
             }
-            impl #r#impl amqp_types::types::Type<'amqp> for #ident #r#type #r#where {
+            impl #amqp_impl amqp_types::types::Type<'amqp> for #ident #r#type #r#where {
                 #try_from
             }
         ))
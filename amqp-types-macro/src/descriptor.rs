@@ -0,0 +1,49 @@
+use darling::{ast::NestedMeta, FromMeta};
+use quote::{quote, ToTokens};
+use syn::{Expr, ExprLit, Lit, LitStr};
+
+/// `descriptor = 0x10`, `descriptor = "amqp:open:list"` or
+/// `descriptor(name = "amqp:open:list", code = 0x10)`
+#[derive(Debug)]
+pub enum DescriptorOpts {
+    Name(LitStr),
+    Code(Expr),
+    Both { name: LitStr, code: Expr },
+}
+
+#[derive(FromMeta)]
+struct BothOpts {
+    name: LitStr,
+    code: Expr,
+}
+
+impl FromMeta for DescriptorOpts {
+    fn from_expr(expr: &Expr) -> darling::Result<Self> {
+        match expr {
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(name),
+                ..
+            }) => Ok(Self::Name(name.clone())),
+            Expr::Lit(ExprLit { lit, .. }) if !matches!(lit, Lit::Int(_)) => {
+                Err(darling::Error::unexpected_lit_type(lit))
+            }
+            code => Ok(Self::Code(code.clone())),
+        }
+    }
+    fn from_list(items: &[NestedMeta]) -> darling::Result<Self> {
+        let BothOpts { name, code } = BothOpts::from_list(items)?;
+        Ok(Self::Both { name, code })
+    }
+}
+
+impl ToTokens for DescriptorOpts {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        tokens.extend(match self {
+            DescriptorOpts::Name(name) => quote!(amqp_types::DescriptorId::name(#name)),
+            DescriptorOpts::Code(code) => quote!(amqp_types::DescriptorId::code(#code)),
+            DescriptorOpts::Both { name, code } => {
+                quote!(amqp_types::DescriptorId::new(#name, #code))
+            }
+        })
+    }
+}
@@ -0,0 +1,161 @@
+use darling::{ast, util::Flag};
+use quote::{quote, ToTokens};
+use syn::parse_quote;
+
+/// An enum whose variants each wrap a described type, decoded by dispatching on the descriptor.
+#[derive(FromDeriveInput)]
+#[darling(
+    attributes(amqp),
+    forward_attrs(allow, doc, cfg),
+    supports(enum_newtype)
+)]
+pub struct EnumDescribedOpts {
+    ident: syn::Ident,
+    generics: syn::Generics,
+    data: ast::Data<VariantOpts, ()>,
+    /// the opt-in, `#[amqp(described)]`
+    described: Flag,
+}
+
+#[derive(FromVariant)]
+#[darling(attributes(amqp), forward_attrs(allow, doc, cfg))]
+pub struct VariantOpts {
+    ident: syn::Ident,
+    fields: ast::Fields<syn::Type>,
+    /// holds the described values no other variant matches
+    other: Flag,
+}
+
+impl VariantOpts {
+    fn ty(&self) -> &syn::Type {
+        self.fields.fields.first().expect("newtype variant")
+    }
+}
+
+impl EnumDescribedOpts {
+    /// The enum opted in, and at most one variant may be the catch-all.
+    pub fn validate(&self) -> syn::Result<()> {
+        if !self.described.is_present() {
+            return Err(syn::Error::new_spanned(
+                &self.ident,
+                "expected `#[amqp(described)]`",
+            ));
+        }
+        let variants = self.data.as_ref().take_enum().expect("should be enum");
+        match variants.iter().filter(|v| v.other.is_present()).nth(1) {
+            Some(second) => Err(syn::Error::new_spanned(
+                &second.ident,
+                "only one variant may be `#[amqp(other)]`",
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ToTokens for EnumDescribedOpts {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let EnumDescribedOpts {
+            ident,
+            generics,
+            data,
+            ..
+        } = self;
+        let (r#impl, r#type, r#where) = generics.split_for_impl();
+        let mut amqp_generics = generics.clone();
+        if !generics.lifetimes().any(|def| def.lifetime.ident == "amqp") {
+            amqp_generics.params.insert(0, parse_quote!('amqp));
+        }
+        let (amqp_impl, _, _) = amqp_generics.split_for_impl();
+        let variants = data.as_ref().take_enum().expect("should be enum");
+        let idents = variants.iter().map(|v| &v.ident).collect::<Vec<_>>();
+        let (others, described): (Vec<_>, Vec<_>) =
+            variants.into_iter().partition(|v| v.other.is_present());
+        let dispatch = described.iter().map(|variant| {
+            let (variant, ty) = (&variant.ident, variant.ty());
+            quote! {
+                if <#ty as amqp_types::types::Described>::DESCRIPTOR_ID.matches(descriptor) {
+                    return Ok(Self::#variant(<#ty as amqp_types::types::Type>::try_from_value(value)?));
+                }
+            }
+        });
+        let fallback = match others.first() {
+            Some(other) => {
+                let (variant, ty) = (&other.ident, other.ty());
+                quote!(Ok(Self::#variant(<#ty as amqp_types::types::Type>::try_from_value(value)?)))
+            }
+            None => quote!(Err(std::io::Error::other(
+                amqp_types::error::UNKNOWN_DESCRIPTOR
+            ))),
+        };
+        tokens.extend(quote!(
+            impl #r#impl amqp_types::types::Restrict for #ident #r#type #r#where {
+                type Source = Self;
+                fn restrict(source: Self::Source) -> Result<Self, Self::Source> {
+                    Ok(source)
+                }
+                fn source(self) -> Self::Source {
+                    self
+                }
+            }
+            // not `Multiple`, the variants share no constructor to write an array of them with
+            impl #r#impl amqp_types::codec::Encode for #ident #r#type #r#where {
+                /// not used, each variant is written with its own constructor
+                const ENCODE_DEFAULT_FORMAT_CODE: amqp_types::FormatCode = amqp_types::FormatCode::NULL;
+                fn encode_data<W: amqp_types::codec::Sink>(self, format_code: amqp_types::FormatCode, writer: &mut W) -> std::io::Result<()> {
+                    match self {
+                        #(Self::#idents(v) => amqp_types::codec::Encode::encode_data(v, format_code, writer),)*
+                    }
+                }
+                fn items_size(&self, mode: amqp_types::codec::EncodeMode) -> Option<(usize, usize)> {
+                    match self {
+                        #(Self::#idents(v) => amqp_types::codec::Encode::items_size(v, mode),)*
+                    }
+                }
+                fn is_null(&self) -> bool {
+                    match self {
+                        #(Self::#idents(v) => amqp_types::codec::Encode::is_null(v),)*
+                    }
+                }
+                fn data_size(&self, format_code: amqp_types::FormatCode, mode: amqp_types::codec::EncodeMode) -> usize {
+                    match self {
+                        #(Self::#idents(v) => amqp_types::codec::Encode::data_size(v, format_code, mode),)*
+                    }
+                }
+                fn encoded_size(&self, mode: amqp_types::codec::EncodeMode) -> usize {
+                    match self {
+                        #(Self::#idents(v) => amqp_types::codec::Encode::encoded_size(v, mode),)*
+                    }
+                }
+                fn encode<W: amqp_types::codec::Sink>(self, constructor: amqp_types::Constructor, writer: &mut W) -> std::io::Result<()> {
+                    match self {
+                        #(Self::#idents(v) => amqp_types::codec::Encode::encode(v, constructor, writer),)*
+                    }
+                }
+                fn encode_default<W: amqp_types::codec::Sink>(self, writer: &mut W) -> std::io::Result<()> {
+                    match self {
+                        #(Self::#idents(v) => amqp_types::codec::Encode::encode_default(v, writer),)*
+                    }
+                }
+                fn compact_format_code(&self) -> amqp_types::FormatCode {
+                    match self {
+                        #(Self::#idents(v) => amqp_types::codec::Encode::compact_format_code(v),)*
+                    }
+                }
+                fn encode_compact<W: amqp_types::codec::Sink>(self, writer: &mut W) -> std::io::Result<()> {
+                    match self {
+                        #(Self::#idents(v) => amqp_types::codec::Encode::encode_compact(v, writer),)*
+                    }
+                }
+            }
+            impl #amqp_impl amqp_types::types::Type<'amqp> for #ident #r#type #r#where {
+                fn try_from_value(value: amqp_types::Value<'amqp>) -> Result<Self, std::io::Error> {
+                    let Some(descriptor) = value.constructor.descriptor.as_ref() else {
+                        return Err(std::io::Error::other(amqp_types::error::UNEXPECTED_TYPE));
+                    };
+                    #(#dispatch)*
+                    #fallback
+                }
+            }
+        ))
+    }
+}
@@ -2,11 +2,16 @@
 extern crate darling;
 extern crate proc_macro;
 mod composite;
+mod descriptor;
+mod enum_described;
 
 use darling::FromDeriveInput;
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, token::Colon, Data, DeriveInput, Expr, Field, Fields, Lit};
+use syn::{
+    parse_macro_input, parse_quote, punctuated::Punctuated, token::Colon, Data, DeriveInput, Expr,
+    Field, Fields, Lit,
+};
 mod consts;
 use consts::AMQP_DOMAIN;
 mod newtype_restricted;
@@ -50,6 +55,8 @@ struct AmqpNewTypeAttr {
 ///
 ///
 /// ```rust
+/// use amqp_types::Type;
+///
 /// #[derive(Type)]
 /// #[amqp(restrict(source = u8))]
 /// pub enum MyEnum {
@@ -63,9 +70,49 @@ struct AmqpNewTypeAttr {
 ///
 /// ```
 ///
+/// Values which are none of the choices fail to decode, unless a variant is marked
+/// `#[amqp(other)]`: it then holds them and writes them back as they came.
+///
+/// ```rust
+/// use amqp_types::{primitive::OwnedSymbol, Type};
+///
+/// #[derive(Type)]
+/// #[amqp(restrict(source = OwnedSymbol))]
+/// pub enum Condition {
+///     #[amqp(choice = OwnedSymbol::from_static(b"amqp:not-found"))]
+///     NotFound,
+///     #[amqp(other)]
+///     Other(OwnedSymbol),
+/// }
+/// ```
+///
+/// An enum marked `#[amqp(described)]` holds one of several described types, each variant wrapping
+/// one of them. The variant is picked by the descriptor of the value, those of no variant fail to
+/// decode unless a variant is marked `#[amqp(other)]` to hold them.
+///
+/// ```rust
+/// use amqp_types::{Type, Value};
+///
+/// #[derive(Type)]
+/// #[amqp(descriptor = 0x0000_0000_0000_0024)]
+/// pub struct Accepted {}
+///
+/// #[derive(Type)]
+/// #[amqp(described)]
+/// pub enum Outcome<'amqp> {
+///     Accepted(Accepted),
+///     #[amqp(other)]
+///     Other(Value<'amqp>),
+/// }
+/// ```
+///
+/// A tuple struct restricts the type of its field, values `validation` rejects fail to decode.
+///
 /// ```rust
+/// use amqp_types::Type;
+///
 /// #[derive(Type)]
-/// #[amqp(restrict(source = u64, validation = |x| x!=0))]
+/// #[amqp(restrict(validation = |x: &u64| *x != 0))]
 /// pub struct MyNewType(u64);
 /// ```
 ///
@@ -82,30 +129,40 @@ pub fn derive_types(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
         Data::Struct(s) => match s.fields {
             Fields::Named(_) => derive_types_for_struct(input_raw),
             Fields::Unnamed(_) => derive_types_for_new_type(input_raw),
-            Fields::Unit => panic!("Type can only be derived for structs with named fields"),
+            Fields::Unit => Err(syn::Error::new_spanned(
+                &input.ident,
+                "`Type` can't be derived for unit structs, use a struct with named fields",
+            )),
         },
+        Data::Enum(_) if is_described_union(&input.attrs) => {
+            derive_types_for_described_enum(input_raw)
+        }
         Data::Enum(_) => derive_types_for_enum(input_raw),
-        Data::Union(_) => panic!("Type can only be derived for structs and enums"),
+        Data::Union(u) => Err(syn::Error::new_spanned(
+            u.union_token,
+            "`Type` can only be derived for structs and enums",
+        )),
     };
 
-    proc_macro::TokenStream::from(expanded.unwrap_or_default())
+    proc_macro::TokenStream::from(expanded.unwrap_or_else(syn::Error::into_compile_error))
 }
 
 fn derive_types_for_new_type(input: DeriveInput) -> syn::Result<TokenStream> {
     let name = input.ident;
     let data = input.data;
-    let data = match data {
-        Data::Struct(data) => data,
-        _ => panic!("Type can only be derived for structs"),
+    let Data::Struct(data) = data else {
+        unreachable!("only called for structs")
     };
     let field = match data.fields {
-        Fields::Unnamed(unnamed) => {
-            if unnamed.unnamed.len() != 1 {
-                panic!("Type can only be derived for newtypes with one field");
-            }
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
             unnamed.unnamed.into_iter().next().unwrap()
         }
-        _ => panic!("Type can only be derived for newtypes with one field"),
+        fields => {
+            return Err(syn::Error::new_spanned(
+                fields,
+                "`Type` can only be derived for tuple structs with a single field",
+            ))
+        }
     };
     let field_ty = field.ty;
     let new_type_attr = input
@@ -126,9 +183,13 @@ fn derive_types_for_new_type(input: DeriveInput) -> syn::Result<TokenStream> {
                         if nested.path.is_ident("validation") {
                             let value = nested.value()?;
                             validation = Some(value.parse::<syn::Expr>()?);
+                            Ok(())
+                        } else {
+                            Err(nested.error("expected `validation`"))
                         }
-                        Ok(())
                     })?;
+                } else {
+                    return Err(nested.error("expected `descriptor` or `restrict`"));
                 }
                 Ok(())
             })
@@ -139,58 +200,32 @@ fn derive_types_for_new_type(input: DeriveInput) -> syn::Result<TokenStream> {
         })
         .transpose()?
         .unwrap_or_default();
-    let validation = new_type_attr.validation.unwrap_or_else(|| {
-        syn::parse_quote! {
-            |_| true
-        }
-    });
-    let descriptor_block = match new_type_attr.descriptor {
-        Some(Descriptor::Symbol(s)) => {
-            quote!(const DESCRIPTOR: Option<amqp_types::Descriptor> = Some(amqp_types::Descriptor::symbol(#s));)
-        }
-        Some(Descriptor::Numeric(i, j)) => {
-            quote!(const DESCRIPTOR: Option<amqp_types::Descriptor> = Some(amqp_types::Descriptor::numeric((((#i as u64) << 32) | (#j as u64))));)
-        }
-        None => quote!(
-            const DESCRIPTOR: Option<amqp_types::Descriptor> = None;
-        ),
-    };
-
-    let expanded = quote! {
-        impl amqp_types::types::Type for #name {
-            #descriptor_block
-            type Source = #field_ty;
-            const FORMAT_CODE: amqp_types::FormatCode = Self::Source::FORMAT_CODE;
-            fn as_data(&self) -> amqp_types::bytes::Bytes {
-                self.unrestrict().as_data()
-            }
-
-            fn from_primitive(value: amqp_types::Primitive) -> Option<Self::Source> {
-                Self::Source::from_primitive(value)
-            }
-
-            fn restrict(source: Self::Source) -> Option<Self> {
-                if (#validation)(&source) {
-                    Some(Self(source))
-                } else {
-                    None
-                }
+    let validation = new_type_attr
+        .validation
+        .unwrap_or_else(|| parse_quote!(|_| true));
+    let restricted = Restricted {
+        name: &name,
+        generics: &input.generics,
+        source: &field_ty,
+        descriptor: new_type_attr.descriptor,
+        restrict: quote! {
+            match (#validation)(&source) {
+                true => Ok(Self(source)),
+                false => Err(source),
             }
-
-            fn unrestrict(&self) -> &Self::Source {
-                &self.0
-            }
-        }
+        },
+        source_of: quote!(self.0),
+        source_of_ref: quote!(&self.0),
+        error: quote!(amqp_types::error::INVALID_RESTRICTED_VALUE),
     };
-    Ok(expanded)
+    Ok(restricted.into_token_stream())
 }
 
 fn derive_types_for_enum(input: DeriveInput) -> syn::Result<TokenStream> {
     let name = input.ident;
     let data = input.data;
-    let data = match data {
-        Data::Enum(data) => data,
-        _ => panic!("Type can only be derived for enums"),
+    let Data::Enum(data) = data else {
+        unreachable!("only called for enums")
     };
     let descriptor = input
         .attrs
@@ -210,9 +245,13 @@ fn derive_types_for_enum(input: DeriveInput) -> syn::Result<TokenStream> {
                         if nested.path.is_ident("source") {
                             let value = nested.value()?;
                             source = Some(value.parse::<syn::Type>()?);
+                            Ok(())
+                        } else {
+                            Err(nested.error("expected `source`"))
                         }
-                        Ok(())
                     })?;
+                } else {
+                    return Err(nested.error("expected `descriptor` or `restrict`"));
                 }
                 Ok(())
             })
@@ -220,111 +259,315 @@ fn derive_types_for_enum(input: DeriveInput) -> syn::Result<TokenStream> {
         })
         .transpose()?
         .ok_or(syn::Error::new_spanned(
-            "",
-            "expected `amqp(descriptor = <descriptor>)`",
+            &name,
+            "expected `#[amqp(restrict(source = <type>))]`, or `#[amqp(described)]` for variants \
+             wrapping described types",
         ))?;
 
-    let variants = data
-        .variants
-        .iter()
-        .map(|v| {
-            let choice = v
-                .attrs
-                .iter()
-                .find_map(|attr| {
-                    if attr.path().is_ident(AMQP_DOMAIN) {
-                        let mut expr = None;
-                        let _result = attr.parse_nested_meta(|nested| {
-                            if nested.path.is_ident("choice") {
-                                let value = nested.value()?;
-                                let value = value.parse::<Expr>()?;
-                                expr.replace(value);
-                            }
-                            Ok(())
-                        });
-                        return expr;
-                    }
-                    None
-                })
-                .ok_or(syn::Error::new_spanned(
+    let mut variants = Vec::new();
+    let mut other = None;
+    for v in &data.variants {
+        let mut choice = None;
+        let mut is_other = None;
+        for attr in v.attrs.iter().filter(|a| a.path().is_ident(AMQP_DOMAIN)) {
+            attr.parse_nested_meta(|nested| {
+                if nested.path.is_ident("choice") {
+                    let value = nested.value()?;
+                    choice = Some(value.parse::<Expr>()?);
+                    Ok(())
+                } else if nested.path.is_ident("other") {
+                    is_other = Some(nested.path.clone());
+                    Ok(())
+                } else {
+                    Err(nested.error("expected `choice` or `other`"))
+                }
+            })?;
+        }
+        match (is_other, &v.fields) {
+            (Some(path), _) if other.is_some() => {
+                return Err(syn::Error::new_spanned(
+                    path,
+                    "only one variant may be `#[amqp(other)]`",
+                ))
+            }
+            (Some(_), _) if choice.is_some() => {
+                return Err(syn::Error::new_spanned(
+                    choice,
+                    "the `#[amqp(other)]` variant takes no choice",
+                ))
+            }
+            (Some(_), Fields::Unnamed(fields)) if fields.unnamed.len() == 1 => {
+                other = Some(v.ident.clone());
+            }
+            (Some(_), _) => {
+                return Err(syn::Error::new_spanned(
                     v,
-                    "expected `amqp(choice = <expr>)`",
+                    "the `#[amqp(other)]` variant holds the source value, as in `Other(<source>)`",
+                ))
+            }
+            (None, Fields::Unit) => {
+                let choice = choice.ok_or(syn::Error::new_spanned(
+                    v,
+                    "expected `#[amqp(choice = <expr>)]`",
                 ))?;
-            Ok((v.ident.clone(), choice))
-        })
-        .collect::<syn::Result<Vec<_>>>()?;
-
-    let descriptor_block = match descriptor.descriptor {
-        Some(Descriptor::Symbol(s)) => {
-            quote!(const DESCRIPTOR: Option<amqp_types::Descriptor> = Some(amqp_types::Descriptor::symbol(#s));)
-        }
-        Some(Descriptor::Numeric(i, j)) => {
-            quote!(const DESCRIPTOR: Option<amqp_types::Descriptor> = Some(amqp_types::Descriptor::numeric((((#i as u64) << 32) | (#j as u64))));)
-        }
-        None => quote!(
-            const DESCRIPTOR: Option<amqp_types::Descriptor> = None;
-        ),
-    };
-    let source = descriptor.source.unwrap_or_else(|| {
-        syn::parse_quote! {
-            u8
+                variants.push((v.ident.clone(), choice));
+            }
+            (None, fields) => {
+                return Err(syn::Error::new_spanned(
+                    fields,
+                    "only the `#[amqp(other)]` variant may hold a value",
+                ))
+            }
         }
-    });
+    }
+    let source = descriptor
+        .source
+        .clone()
+        .unwrap_or_else(|| parse_quote!(u8));
+    validate_choices(&source, &variants)?;
+
     let restrict_match = variants.iter().map(|(ident, choice)| {
         quote! {
-            if #choice == source {
-                return Some(#name::#ident)
+            if source == #choice {
+                return Ok(Self::#ident)
             }
         }
     });
-    let unrestrict_match = variants.iter().map(|(ident, choice)| {
-        quote! {
-            &#name::#ident => {
-                static choice: #source = #choice;
-                return &choice
-            },
-        }
-    });
-
-    let from_primitive_block = quote! {
-        Self::Source::from_primitive(value)
+    let choices = variants
+        .iter()
+        .map(|(ident, choice)| quote!(Self::#ident => #choice,))
+        .collect::<Vec<_>>();
+    // values out of the choices are kept by the `other` variant, or rejected
+    let (unknown, other_source, other_source_ref) = match &other {
+        Some(ident) => (
+            quote!(Ok(Self::#ident(source))),
+            quote!(Self::#ident(source) => source,),
+            quote!(Self::#ident(source) => ::core::clone::Clone::clone(source),),
+        ),
+        None => (quote!(Err(source)), quote!(), quote!()),
     };
-
-    let expanded = quote! {
-        impl amqp_types::types::Type for #name {
-            #descriptor_block
-            type Source = #source;
-            const FORMAT_CODE: amqp_types::FormatCode = Self::Source::FORMAT_CODE;
-            fn as_data(&self) -> amqp_types::bytes::Bytes {
-                self.unrestrict().as_data()
+    let restricted = Restricted {
+        name: &name,
+        generics: &input.generics,
+        source: &source,
+        descriptor: descriptor.descriptor,
+        restrict: quote! {
+            #(#restrict_match)*
+            #unknown
+        },
+        source_of: quote! {
+            match self {
+                #(#choices)*
+                #other_source
             }
-
-            fn constructor(&self) -> amqp_types::Constructor {
-                self.unrestrict().constructor()
+        },
+        source_of_ref: quote! {
+            &match self {
+                #(#choices)*
+                #other_source_ref
             }
+        },
+        error: quote!(amqp_types::error::UNKNOWN_CHOICE),
+    };
+    Ok(restricted.into_token_stream())
+}
 
-            fn from_primitive(value: amqp_types::Primitive) -> Option<Self::Source> {
-                #from_primitive_block
-            }
+/// A type restricting a source type, encoded as its source.
+struct Restricted<'a> {
+    name: &'a syn::Ident,
+    generics: &'a syn::Generics,
+    source: &'a syn::Type,
+    descriptor: Option<Descriptor>,
+    /// body of `Restrict::restrict`, from `source`
+    restrict: TokenStream,
+    /// the source of `self`, by value
+    source_of: TokenStream,
+    /// a reference to the source of `self`, to measure it
+    source_of_ref: TokenStream,
+    /// the error decoding fails with if `restrict` rejects the source
+    error: TokenStream,
+}
 
-            fn restrict(source: Self::Source) -> Option<Self> {
-                #(
-                    #restrict_match
-                )*
-                return None
+impl Restricted<'_> {
+    fn into_token_stream(self) -> TokenStream {
+        let Restricted {
+            name,
+            generics,
+            source,
+            descriptor,
+            restrict,
+            source_of,
+            source_of_ref,
+            error,
+        } = self;
+        let descriptor_block = match descriptor {
+            Some(Descriptor::Symbol(s)) => quote! {
+                const DESCRIPTOR: Option<amqp_types::Descriptor<'static>> =
+                    Some(amqp_types::Descriptor::symbol(#s));
+            },
+            Some(Descriptor::Numeric(i, j)) => quote! {
+                const DESCRIPTOR: Option<amqp_types::Descriptor<'static>> =
+                    Some(amqp_types::Descriptor::numeric((((#i as u64) << 32) | (#j as u64))));
+            },
+            None => quote!(),
+        };
+        let (r#impl, r#type, r#where) = generics.split_for_impl();
+        // `Type` is implemented for any `'amqp` the source may be read with
+        let mut amqp_generics = generics.clone();
+        if !generics.lifetimes().any(|def| def.lifetime.ident == "amqp") {
+            amqp_generics.params.insert(0, parse_quote!('amqp));
+        }
+        amqp_generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote!(#source: amqp_types::types::Type<'amqp>));
+        let (amqp_impl, _, amqp_where) = amqp_generics.split_for_impl();
+        quote! {
+            impl #r#impl amqp_types::types::Restrict for #name #r#type #r#where {
+                type Source = #source;
+                fn restrict(source: Self::Source) -> Result<Self, Self::Source> {
+                    #restrict
+                }
+                fn source(self) -> Self::Source {
+                    #source_of
+                }
             }
-
-            fn unrestrict(&self) -> &Self::Source {
-                match self {
-                    #(
-                        #unrestrict_match
-                    )*
+            impl #r#impl amqp_types::types::Multiple for #name #r#type #r#where {}
+            // typed by the source since the choices of an enum may be untyped literals
+            impl #r#impl amqp_types::codec::Encode for #name #r#type #r#where {
+                #descriptor_block
+                const ENCODE_DEFAULT_FORMAT_CODE: amqp_types::FormatCode =
+                    <#source as amqp_types::codec::Encode>::ENCODE_DEFAULT_FORMAT_CODE;
+                fn encode_data<W: amqp_types::codec::Sink>(self, format_code: amqp_types::FormatCode, writer: &mut W) -> std::io::Result<()> {
+                    <#source as amqp_types::codec::Encode>::encode_data(#source_of, format_code, writer)
+                }
+                fn items_size(&self, mode: amqp_types::codec::EncodeMode) -> Option<(usize, usize)> {
+                    <#source as amqp_types::codec::Encode>::items_size(#source_of_ref, mode)
+                }
+                fn is_null(&self) -> bool {
+                    <#source as amqp_types::codec::Encode>::is_null(#source_of_ref)
+                }
+                fn data_size(&self, format_code: amqp_types::FormatCode, mode: amqp_types::codec::EncodeMode) -> usize {
+                    <#source as amqp_types::codec::Encode>::data_size(#source_of_ref, format_code, mode)
+                }
+                fn compact_format_code(&self) -> amqp_types::FormatCode {
+                    <#source as amqp_types::codec::Encode>::compact_format_code(#source_of_ref)
+                }
+            }
+            impl #amqp_impl amqp_types::types::Type<'amqp> for #name #r#type #amqp_where {
+                fn try_from_value(value: amqp_types::Value<'amqp>) -> Result<Self, std::io::Error> {
+                    let source = <#source as amqp_types::types::Type>::try_from_value(value)?;
+                    <Self as amqp_types::types::Restrict>::restrict(source)
+                        .map_err(|_| std::io::Error::other(#error))
                 }
             }
         }
+    }
+}
+
+/// Check the literal choices fit the `source` type and no two variants share one.
+///
+/// Choices given by other expressions, like `sym("amqp:not-found")`, are only checked for
+/// duplicates.
+fn validate_choices(source: &syn::Type, variants: &[(syn::Ident, Expr)]) -> syn::Result<()> {
+    let source_name = match source {
+        syn::Type::Path(path) => path.path.get_ident().map(|ident| ident.to_string()),
+        syn::Type::Reference(reference) => match &*reference.elem {
+            syn::Type::Path(path) if path.path.is_ident("str") => Some("str".to_owned()),
+            _ => None,
+        },
+        _ => None,
     };
+    let mut seen = Vec::<(String, &syn::Ident)>::new();
+    for (ident, choice) in variants {
+        let lit = match choice {
+            Expr::Lit(expr) => Some(&expr.lit),
+            _ => None,
+        };
+        if let (Some(lit), Some(source_name)) = (lit, &source_name) {
+            check_choice_lit(lit, source_name)?;
+        }
+        let key = match lit {
+            Some(Lit::Int(int)) => int
+                .base10_parse::<i128>()
+                .map(|n| n.to_string())
+                .unwrap_or_else(|_| int.base10_digits().to_owned()),
+            Some(Lit::Str(s)) => format!("{:?}", s.value()),
+            _ => quote!(#choice).to_string(),
+        };
+        if let Some((_, first)) = seen.iter().find(|(seen, _)| *seen == key) {
+            return Err(syn::Error::new_spanned(
+                choice,
+                format!("duplicate choice, `{first}` already uses it"),
+            ));
+        }
+        seen.push((key, ident));
+    }
+    Ok(())
+}
 
-    Ok(expanded)
+fn check_choice_lit(lit: &Lit, source: &str) -> syn::Result<()> {
+    macro_rules! fits {
+        ($int: ident, $($ty: ident)*) => {
+            match source {
+                $(stringify!($ty) => $int.base10_parse::<$ty>().is_ok(),)*
+                _ => unreachable!(),
+            }
+        };
+    }
+    let fits = match (lit, source) {
+        (Lit::Int(int), "u8" | "u16" | "u32" | "u64" | "i8" | "i16" | "i32" | "i64") => {
+            if !int.suffix().is_empty() && int.suffix() != source {
+                false
+            } else if fits!(int, u8 u16 u32 u64 i8 i16 i32 i64) {
+                true
+            } else {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    format!("choice out of range for the `{source}` source"),
+                ));
+            }
+        }
+        (Lit::Float(_), "f32" | "f64")
+        | (Lit::Bool(_), "bool")
+        | (Lit::Char(_), "char")
+        | (Lit::Str(_), "str" | "String") => true,
+        // the source is another type, it can't be checked here
+        (_, source) if !PRIMITIVE_SOURCES.contains(&source) => true,
+        _ => false,
+    };
+    match fits {
+        true => Ok(()),
+        false => Err(syn::Error::new_spanned(
+            lit,
+            format!("expected a `{source}` choice, the source of the enum"),
+        )),
+    }
+}
+
+const PRIMITIVE_SOURCES: &[&str] = &[
+    "u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64", "f32", "f64", "bool", "char", "str",
+    "String",
+];
+
+/// The enum is marked `#[amqp(described)]`, its variants wrap described types.
+fn is_described_union(attrs: &[syn::Attribute]) -> bool {
+    attrs
+        .iter()
+        .filter(|a| a.path().is_ident(AMQP_DOMAIN))
+        .filter_map(|a| {
+            a.parse_args_with(Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated)
+                .ok()
+        })
+        .flatten()
+        .any(|meta| meta.path().is_ident("described"))
+}
+
+fn derive_types_for_described_enum(input: DeriveInput) -> syn::Result<TokenStream> {
+    let receiver = enum_described::EnumDescribedOpts::from_derive_input(&input)?;
+    receiver.validate()?;
+    let tokens = quote!(#receiver);
+    Ok(tokens)
 }
 
 fn derive_types_for_struct(input: DeriveInput) -> syn::Result<TokenStream> {
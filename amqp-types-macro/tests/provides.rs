@@ -0,0 +1,161 @@
+use amqp_types::{
+    codec::{BufWriter, Decode, Encode, EncodeMode, Sink},
+    error::DescriptorMismatch,
+    provides::{archetype, Registry, Require, Requires},
+    types::Type,
+    Descriptor, FormatCode, Type, Value,
+};
+
+pub struct DeliveryState;
+
+impl Require for DeliveryState {
+    const ARCHETYPE: &'static str = archetype::DELIVERY_STATE;
+}
+
+pub struct Outcome;
+
+impl Require for Outcome {
+    const ARCHETYPE: &'static str = archetype::OUTCOME;
+}
+
+#[derive(Debug, Type)]
+#[amqp(descriptor(name = "amqp:accepted:list", code = 0x0000_0000_0000_0024))]
+#[amqp(provides(DeliveryState, Outcome))]
+pub struct Accepted {}
+
+#[derive(Debug, Type)]
+#[amqp(descriptor(name = "amqp:received:list", code = 0x0000_0000_0000_0023))]
+#[amqp(provides = "delivery-state")]
+pub struct Received {
+    #[amqp(mandatory)]
+    pub section_number: u32,
+    #[amqp(mandatory)]
+    pub section_offset: u64,
+}
+
+#[derive(Debug, Type)]
+#[amqp(descriptor = 0x0000_0000_0000_0015)]
+pub struct Disposition<'amqp> {
+    #[amqp(mandatory)]
+    pub first: u32,
+    pub state: Option<Requires<'amqp, DeliveryState>>,
+}
+
+/// A registry of the tests' own, the one of the process being shared by the threads running them.
+fn registry() -> Registry {
+    let registry = Registry::new();
+    registry.register::<Accepted>();
+    registry.register::<Received>();
+    registry
+}
+
+#[test]
+fn registered() {
+    let registry = registry();
+    registry.register::<Accepted>();
+    let outcomes = registry.providers(archetype::OUTCOME);
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!(outcomes[0].descriptor.code, Some(0x24));
+    assert_eq!(registry.providers(archetype::DELIVERY_STATE).len(), 2);
+    assert!(registry
+        .lookup(archetype::OUTCOME, &Descriptor::Numeric(0x23))
+        .is_none());
+    let symbol = Descriptor::symbol(b"amqp:received:list");
+    assert!(registry
+        .lookup(archetype::DELIVERY_STATE, &symbol)
+        .is_some());
+}
+
+#[test]
+fn known_provider() {
+    let disposition = Disposition {
+        first: 1,
+        state: Some(
+            Requires::write(Received {
+                section_number: 2,
+                section_offset: 3,
+            })
+            .unwrap(),
+        ),
+    };
+    let mut writer = BufWriter::new(Vec::new());
+    writer.write_amqp_value(disposition).unwrap();
+    let buffer = writer.into_inner();
+
+    let value = Value::decode(&mut buffer.as_slice()).unwrap();
+    let disposition = Disposition::try_from_value(value).unwrap();
+    let state = disposition.state.unwrap();
+    let provider = state.provider_in(&registry()).unwrap();
+    assert!(provider.type_name.ends_with("Received"));
+    assert!(state.is::<Received>());
+    assert!(!state.is::<Accepted>());
+    let received = state.get::<Received>().unwrap();
+    assert_eq!(received.section_number, 2);
+    assert_eq!(received.section_offset, 3);
+    let error = state.get::<Accepted>().unwrap_err();
+    assert!(DescriptorMismatch::from_io(&error).is_some());
+}
+
+#[test]
+fn unknown_descriptor_is_kept() {
+    let mut buffer = vec![
+        0x00, 0x53, 0x15, // disposition
+        0xc0, 0x00, 0x02, // list8, size patched below
+        0x52, 0x07, // first
+        0x00, 0xa3, 0x0e, // a delivery state from an extension
+    ];
+    buffer.extend_from_slice(b"example:custom");
+    buffer.extend_from_slice(&[0xa1, 0x02, b'o', b'k']);
+    buffer[4] = (buffer.len() - 5) as u8;
+
+    let value = Value::decode(&mut buffer.as_slice()).unwrap();
+    let disposition = Disposition::try_from_value(value).unwrap();
+    assert_eq!(disposition.first, 7);
+    let state = disposition.state.unwrap();
+    assert!(state.provider_in(&registry()).is_none());
+    assert!(
+        matches!(state.descriptor(), Some(Descriptor::Symbol(s)) if s.as_bytes() == b"example:custom")
+    );
+    assert!(state.get::<Accepted>().is_err());
+
+    // and sent back as it came
+    let disposition = Disposition {
+        first: 7,
+        state: Some(state),
+    };
+    let mut writer = BufWriter::new(Vec::new());
+    writer.write_amqp_value(disposition).unwrap();
+    let written = writer.into_inner();
+    let value = Value::decode(&mut written.as_slice()).unwrap();
+    let state = Disposition::try_from_value(value).unwrap().state.unwrap();
+    assert!(
+        matches!(state.descriptor(), Some(Descriptor::Symbol(s)) if s.as_bytes() == b"example:custom")
+    );
+    assert_eq!(state.value().data.into_inner(), &buffer[buffer.len() - 3..]);
+}
+
+#[test]
+fn written_as_held() {
+    let state = Requires::<DeliveryState>::write(Accepted {}).unwrap();
+    // the constructor is the one the value was written with, in any mode
+    assert_eq!(
+        state.compact_format_code(),
+        state.value().constructor.format_code
+    );
+    // its data follows its own format code only
+    let format_code = state.value().constructor.format_code;
+    let mut writer = BufWriter::new(Vec::new());
+    state.clone().encode_data(format_code, &mut writer).unwrap();
+    assert_eq!(writer.into_inner(), state.value().data.into_inner());
+    let mut writer = BufWriter::new(Vec::new());
+    assert!(state
+        .clone()
+        .encode_data(FormatCode::MAP32, &mut writer)
+        .is_err());
+    for mode in [EncodeMode::Default, EncodeMode::Compact] {
+        let size = state.encoded_size(mode);
+        let mut writer = BufWriter::new(Vec::new()).with_mode(mode);
+        writer.write_amqp_value(state.clone()).unwrap();
+        assert_eq!(writer.into_inner().len(), size);
+    }
+}
@@ -1,6 +1,6 @@
 use amqp_types::{
-    codec::{Decode, Encode, Writer},
-    primitive::Array,
+    codec::{BufWriter, Decode, Encode, EncodeMode, Sink, Writer},
+    primitive::Symbol,
     types::Type,
     Data, Primitive, Type, Value,
 };
@@ -14,27 +14,522 @@ pub struct Open<'amqp> {
     #[amqp(default = 12)]
     pub(crate) channel_max: u16,
     pub(crate) idle_timeout: Option<u32>,
-    pub(crate) outgoing_locales: Array<'amqp, &'amqp str>,
-    pub(crate) incoming_locales: Array<'amqp, &'amqp str>,
-    pub(crate) offered_capabilities: Array<'amqp, &'amqp str>,
-    pub(crate) desired_capabilities: Array<'amqp, &'amqp str>,
+    #[amqp(multiple)]
+    pub(crate) outgoing_locales: Vec<&'amqp str>,
+    #[amqp(multiple)]
+    pub(crate) incoming_locales: Vec<&'amqp str>,
+    #[amqp(multiple)]
+    pub(crate) offered_capabilities: Vec<&'amqp str>,
+    #[amqp(multiple)]
+    pub(crate) desired_capabilities: Vec<&'amqp str>,
 }
 
 #[test]
 fn test() {
     let open = Open {
         container_id: "test",
-        incoming_locales: Array::new_write(["hello", "amqp"]),
+        incoming_locales: vec!["hello", "amqp"],
         ..Default::default()
     };
-    let mut buffer = vec![0; 128];
-    let mut writer = Writer::new(&mut buffer);
+    let mut writer = BufWriter::new(Vec::new());
     writer.write_amqp_value(open).unwrap();
+    let buffer = writer.into_inner();
     println!("{:?}", Data::new(&buffer));
     let value = dbg!(Value::decode(&mut buffer.as_slice()).unwrap());
     dbg!(value.clone().construct().unwrap());
     let new_open = dbg!(Open::try_from_value(value).unwrap());
     let s = new_open.container_id;
+    assert_eq!(new_open.incoming_locales, ["hello", "amqp"]);
+    assert!(new_open.outgoing_locales.is_empty());
     dbg!(new_open);
     assert_eq!(s, "test");
 }
+
+#[test]
+fn test_compact() {
+    let open = Open {
+        container_id: "test",
+        max_frame_size: 512,
+        ..Default::default()
+    };
+    let mut buffer = vec![0; 128];
+    let size = open.encoded_size(EncodeMode::Compact);
+    let mut writer = Writer::new(&mut buffer).with_mode(EncodeMode::Compact);
+    writer.write_amqp_value(open).unwrap();
+    let len = writer.position();
+    assert_eq!(len, size);
+    assert_eq!(
+        buffer[..len],
+        [
+            0x00, 0x80, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x02, // descriptor
+            0xc0, 0x10, 0x04, // list8, the trailing nulls left out
+            0xa1, 0x04, b't', b'e', b's', b't', // container-id
+            0x40, // hostname
+            0x70, 0x00, 0x00, 0x02, 0x00, // max-frame-size
+            0x60, 0x00, 0x00, // channel-max
+        ][..]
+    );
+    let value = Value::decode(&mut &buffer[..len]).unwrap();
+    let open = Open::try_from_value(value).unwrap();
+    assert_eq!(open.container_id, "test");
+    assert_eq!(open.max_frame_size, 512);
+}
+
+#[derive(Debug, Type)]
+#[amqp(descriptor = "amqp:accepted:list")]
+pub struct Accepted {}
+
+#[derive(Debug, Type)]
+#[amqp(descriptor(name = "amqp:received:list", code = 0x0000_0000_0000_0023))]
+pub struct Received {
+    pub section_number: u32,
+    pub section_offset: u64,
+}
+
+#[test]
+fn test_symbolic_descriptor() {
+    use amqp_types::{primitive::Symbol, types::Described, Descriptor};
+
+    let mut writer = BufWriter::new(Vec::new());
+    writer.write_amqp_value(Accepted {}).unwrap();
+    let buffer = writer.into_inner();
+    // 0x00 [sym32 "amqp:accepted:list"] [list32]
+    assert_eq!(buffer[..6], [0x00, 0xb3, 0x00, 0x00, 0x00, 0x12]);
+    assert_eq!(&buffer[6..24], b"amqp:accepted:list");
+    assert_eq!(buffer[24], 0xd0);
+    let value = Value::decode(&mut buffer.as_slice()).unwrap();
+    assert!(Accepted::DESCRIPTOR_ID.matches(value.constructor.descriptor.as_ref().unwrap()));
+    Accepted::try_from_value(value).unwrap();
+
+    // the code is written when there is one, and either form is recognized
+    let id = Received::DESCRIPTOR_ID;
+    assert!(matches!(id.descriptor(), Descriptor::Numeric(0x23)));
+    assert!(id.matches(&Descriptor::Numeric(0x23)));
+    assert!(id.matches(&Descriptor::Symbol(Symbol::new(b"amqp:received:list"))));
+    assert!(!id.matches(&Descriptor::Symbol(Symbol::new(b"amqp:accepted:list"))));
+    assert!(!id.matches(&Descriptor::Numeric(0x24)));
+
+    // received:list sent with its symbolic descriptor
+    let mut buffer = vec![0x00, 0xa3, 18];
+    buffer.extend_from_slice(b"amqp:received:list");
+    buffer.extend_from_slice(&[0xc0, 0x04, 0x02, 0x52, 0x01, 0x44]);
+    let value = Value::decode(&mut buffer.as_slice()).unwrap();
+    let received = Received::try_from_value(value).unwrap();
+    assert_eq!(received.section_number, 1);
+    assert_eq!(received.section_offset, 0);
+}
+
+#[test]
+fn test_descriptor_mismatch() {
+    use amqp_types::{error::DescriptorMismatch, types::Described, OwnedDescriptor};
+
+    // a received:list body decoded as an accepted
+    let mut writer = BufWriter::new(Vec::new());
+    writer
+        .write_amqp_value(Received {
+            section_number: 1,
+            section_offset: 0,
+        })
+        .unwrap();
+    let buffer = writer.into_inner();
+    let value = Value::decode(&mut buffer.as_slice()).unwrap();
+    let error = Accepted::try_from_value(value).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(
+        DescriptorMismatch::from_io(&error),
+        Some(&DescriptorMismatch {
+            expected: Accepted::DESCRIPTOR_ID,
+            found: Some(OwnedDescriptor::Numeric(0x23)),
+        })
+    );
+
+    // a bare list isn't a described type at all
+    let value = Value::decode(&mut [0x45].as_slice()).unwrap();
+    let error = Accepted::try_from_value(value).unwrap_err();
+    let mismatch = DescriptorMismatch::from_io(&error).unwrap();
+    assert_eq!(mismatch.found, None);
+}
+
+#[derive(Debug, Type)]
+#[amqp(descriptor = 0x0000_0000_0000_0015)]
+pub struct Disposition<'amqp> {
+    #[amqp(mandatory)]
+    pub role: bool,
+    #[amqp(mandatory)]
+    pub first: u32,
+    pub last: Option<u32>,
+    #[amqp(default = false)]
+    pub settled: bool,
+    pub state: Option<Value<'amqp>>,
+    #[amqp(default = false)]
+    pub batchable: bool,
+}
+
+#[test]
+fn test_mandatory_and_trailing_nulls() {
+    let disposition = Disposition {
+        role: true,
+        first: 3,
+        last: None,
+        settled: false,
+        state: None,
+        batchable: false,
+    };
+    let size = disposition.encoded_size(EncodeMode::Compact);
+    let mut writer = BufWriter::new(Vec::new()).with_mode(EncodeMode::Compact);
+    writer.write_amqp_value(disposition).unwrap();
+    let buffer = writer.into_inner();
+    assert_eq!(buffer.len(), size);
+    // only the mandatory fields are left
+    assert_eq!(
+        buffer,
+        [0x00, 0x53, 0x15, 0xc0, 0x04, 0x02, 0x41, 0x52, 0x03]
+    );
+    let value = Value::decode(&mut buffer.as_slice()).unwrap();
+    let decoded = Disposition::try_from_value(value).unwrap();
+    assert!(decoded.role);
+    assert_eq!(decoded.first, 3);
+    assert_eq!(decoded.last, None);
+    assert!(!decoded.settled);
+    assert!(decoded.state.is_none());
+    assert!(!decoded.batchable);
+
+    // nulls before a written field stay
+    let mut writer = BufWriter::new(Vec::new()).with_mode(EncodeMode::Compact);
+    writer
+        .write_amqp_value(Disposition {
+            role: false,
+            first: 0,
+            last: None,
+            settled: true,
+            state: None,
+            batchable: false,
+        })
+        .unwrap();
+    let buffer = writer.into_inner();
+    assert_eq!(
+        buffer,
+        [0x00, 0x53, 0x15, 0xc0, 0x05, 0x04, 0x42, 0x43, 0x40, 0x41]
+    );
+
+    // `first` absent
+    let value = Value::decode(&mut [0x00, 0x53, 0x15, 0xc0, 0x02, 0x01, 0x41].as_slice()).unwrap();
+    let error = Disposition::try_from_value(value).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        amqp_types::error::MANDATORY_FIELD_MISSING
+    );
+    // `first` null
+    let value =
+        Value::decode(&mut [0x00, 0x53, 0x15, 0xc0, 0x03, 0x02, 0x41, 0x40].as_slice()).unwrap();
+    assert!(Disposition::try_from_value(value).is_err());
+}
+
+#[test]
+fn test_multiple() {
+    let mut buffer = vec![
+        0x00, 0x80, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x02, // descriptor
+        0xc0, 0x00, 0x09, // list8, size patched below
+        0xa1, 0x01, b'c', // container-id
+        0x40, 0x52, 0x00, 0x60, 0x00, 0x00, 0x40, // hostname to idle-time-out
+        0xa1, 0x02, b'e', b'n', // outgoing-locales as a single value
+        0xe0, 0x08, 0x02, 0xa1, 0x02, b'e', b'n', 0x02, b'f',
+        b'r', // incoming-locales as an array
+        0x40, // offered-capabilities as null
+        0xe0, 0x02, 0x00, 0xa1, // desired-capabilities as an empty array
+    ];
+    buffer[11] = (buffer.len() - 12) as u8;
+    let value = Value::decode(&mut buffer.as_slice()).unwrap();
+    let open = Open::try_from_value(value).unwrap();
+    assert_eq!(open.outgoing_locales, ["en"]);
+    assert_eq!(open.incoming_locales, ["en", "fr"]);
+    assert!(open.offered_capabilities.is_empty());
+    assert!(open.desired_capabilities.is_empty());
+
+    // written back in the canonical form, with the trailing empty ones left out
+    for mode in [EncodeMode::Default, EncodeMode::Compact] {
+        let value = Value::decode(&mut buffer.as_slice()).unwrap();
+        let open = Open::try_from_value(value).unwrap();
+        let size = open.encoded_size(mode);
+        let mut writer = BufWriter::new(Vec::new()).with_mode(mode);
+        writer.write_amqp_value(open).unwrap();
+        let written = writer.into_inner();
+        assert_eq!(written.len(), size);
+        let open = Open::try_from_value(Value::decode(&mut written.as_slice()).unwrap()).unwrap();
+        assert_eq!(open.outgoing_locales, ["en"]);
+        assert_eq!(open.incoming_locales, ["en", "fr"]);
+        assert!(open.offered_capabilities.is_empty());
+        assert!(open.desired_capabilities.is_empty());
+    }
+    let mut writer = BufWriter::new(Vec::new()).with_mode(EncodeMode::Compact);
+    writer
+        .write_amqp_value(
+            Open::try_from_value(Value::decode(&mut buffer.as_slice()).unwrap()).unwrap(),
+        )
+        .unwrap();
+    assert_eq!(
+        writer.get_ref()[10..],
+        [
+            0xc0, 0x1e, 0x07, // list8
+            0xa1, 0x01, b'c', // container-id
+            0x40, 0x43, 0x60, 0x00, 0x00, 0x40, // hostname to idle-time-out
+            0xa1, 0x02, b'e', b'n', // a single value
+            0xe0, 0x0e, 0x02, 0xb1, // an array
+            0x00, 0x00, 0x00, 0x02, b'e', b'n', 0x00, 0x00, 0x00, 0x02, b'f', b'r',
+        ][..]
+    );
+}
+
+#[derive(Debug, Type)]
+#[amqp(described)]
+pub enum DeliveryState<'amqp> {
+    Accepted(Accepted),
+    Received(Received),
+    #[amqp(other)]
+    Unknown(Value<'amqp>),
+}
+
+#[derive(Debug, Type)]
+#[amqp(described)]
+pub enum Outcome {
+    Accepted(Accepted),
+}
+
+#[test]
+fn test_described_enum() {
+    let received = Received {
+        section_number: 1,
+        section_offset: 2,
+    };
+    let size = received.encoded_size(EncodeMode::Compact);
+    let mut writer = BufWriter::new(Vec::new()).with_mode(EncodeMode::Compact);
+    writer
+        .write_amqp_value(DeliveryState::Received(received))
+        .unwrap();
+    let buffer = writer.into_inner();
+    assert_eq!(buffer.len(), size);
+    let value = Value::decode(&mut buffer.as_slice()).unwrap();
+    let DeliveryState::Received(received) = DeliveryState::try_from_value(value).unwrap() else {
+        panic!("expected received")
+    };
+    assert_eq!(received.section_number, 1);
+    assert_eq!(received.section_offset, 2);
+
+    let mut writer = BufWriter::new(Vec::new());
+    writer
+        .write_amqp_value(Outcome::Accepted(Accepted {}))
+        .unwrap();
+    let buffer = writer.into_inner();
+    let value = Value::decode(&mut buffer.as_slice()).unwrap();
+    let state = DeliveryState::try_from_value(value.clone()).unwrap();
+    assert!(matches!(state, DeliveryState::Accepted(_)));
+    assert!(matches!(
+        Outcome::try_from_value(value).unwrap(),
+        Outcome::Accepted(_)
+    ));
+
+    // unknown descriptors go to the catch-all, or fail without one
+    let buffer = [0x00, 0x53, 0x99, 0x45];
+    let value = Value::decode(&mut buffer.as_slice()).unwrap();
+    let DeliveryState::Unknown(unknown) = DeliveryState::try_from_value(value.clone()).unwrap()
+    else {
+        panic!("expected unknown")
+    };
+    assert!(matches!(
+        unknown.constructor.descriptor,
+        Some(amqp_types::Descriptor::Numeric(0x99))
+    ));
+    let error = Outcome::try_from_value(value).unwrap_err();
+    assert_eq!(error.to_string(), amqp_types::error::UNKNOWN_DESCRIPTOR);
+    let value = Value::decode(&mut [0x45].as_slice()).unwrap();
+    assert!(DeliveryState::try_from_value(value).is_err());
+}
+
+/// A window which can't be closed.
+#[derive(Debug, PartialEq)]
+pub struct Window(u32);
+
+impl amqp_types::types::Restrict for Window {
+    type Source = u32;
+    fn restrict(source: u32) -> Result<Self, u32> {
+        match source {
+            0 => Err(source),
+            _ => Ok(Self(source)),
+        }
+    }
+    fn source(self) -> u32 {
+        self.0
+    }
+}
+
+impl Encode for Window {
+    const ENCODE_DEFAULT_FORMAT_CODE: amqp_types::FormatCode = u32::ENCODE_DEFAULT_FORMAT_CODE;
+    fn encode_data<W: Sink>(
+        self,
+        format_code: amqp_types::FormatCode,
+        writer: &mut W,
+    ) -> std::io::Result<()> {
+        self.0.encode_data(format_code, writer)
+    }
+}
+
+impl<'a> Type<'a> for Window {
+    fn try_from_value(value: Value<'a>) -> std::io::Result<Self> {
+        use amqp_types::types::Restrict;
+        Self::restrict(u32::try_from_value(value)?).map_err(|_| std::io::Error::other("closed"))
+    }
+}
+
+mod seconds {
+    use amqp_types::{
+        codec::{Encode, EncodeMode, Sink},
+        types::Type,
+        Value,
+    };
+    use std::{io, time::Duration};
+
+    pub fn decode(value: Value<'_>) -> io::Result<Duration> {
+        u32::try_from_value(value).map(|secs| Duration::from_secs(secs.into()))
+    }
+    pub fn encode<W: Sink>(value: Duration, writer: &mut W) -> io::Result<()> {
+        writer.write_amqp_value(value.as_secs() as u32)
+    }
+    pub fn encoded_size(value: &Duration, mode: EncodeMode) -> usize {
+        (value.as_secs() as u32).encoded_size(mode)
+    }
+}
+
+const CLOSED_WINDOW: &str = "session window must not be zero";
+
+#[derive(Debug, Type)]
+#[amqp(descriptor = "example:settings:list")]
+pub struct Settings {
+    #[amqp(
+        rename = "session-window",
+        restrict_err = CLOSED_WINDOW
+    )]
+    pub window: Window,
+    #[amqp(skip)]
+    pub cached: Option<String>,
+    #[amqp(with = seconds)]
+    pub timeout: std::time::Duration,
+    pub max_links: Option<u32>,
+}
+
+#[test]
+fn test_field_attributes() {
+    use amqp_types::types::Composite;
+    use std::time::Duration;
+
+    assert_eq!(Settings::FIELDS, ["session-window", "timeout", "max-links"]);
+    let settings = Settings {
+        window: Window(16),
+        cached: Some("not sent".into()),
+        timeout: Duration::from_secs(30),
+        max_links: None,
+    };
+    let size = settings.encoded_size(EncodeMode::Compact);
+    let mut writer = BufWriter::new(Vec::new()).with_mode(EncodeMode::Compact);
+    writer.write_amqp_value(settings).unwrap();
+    let buffer = writer.into_inner();
+    assert_eq!(buffer.len(), size);
+    // window and timeout, the skipped field isn't in the list and the trailing null is left out
+    assert_eq!(
+        buffer[buffer.len() - 10..],
+        [0xc0, 0x08, 0x02, 0x70, 0x00, 0x00, 0x00, 0x10, 0x52, 0x1e][..]
+    );
+    let value = Value::decode(&mut buffer.as_slice()).unwrap();
+    let settings = Settings::try_from_value(value).unwrap();
+    assert_eq!(settings.window, Window(16));
+    assert_eq!(settings.cached, None);
+    assert_eq!(settings.timeout, Duration::from_secs(30));
+    assert_eq!(settings.max_links, None);
+
+    let mut buffer = vec![0x00, 0xa3, 21];
+    buffer.extend_from_slice(b"example:settings:list");
+    buffer.extend_from_slice(&[0xc0, 0x04, 0x02, 0x43, 0x52, 0x1e]);
+    let value = Value::decode(&mut buffer.as_slice()).unwrap();
+    let error = Settings::try_from_value(value).unwrap_err();
+    assert_eq!(error.to_string(), CLOSED_WINDOW);
+}
+
+#[derive(Debug, PartialEq, Type)]
+#[amqp(restrict(source = u8))]
+pub enum SettleMode {
+    #[amqp(choice = 0)]
+    Unsettled,
+    #[amqp(choice = 1)]
+    Settled,
+}
+
+#[derive(Debug, PartialEq, Type)]
+#[amqp(restrict(source = Symbol<'amqp>))]
+pub enum Condition<'amqp> {
+    #[amqp(choice = Symbol::new(b"amqp:not-found"))]
+    NotFound,
+    #[amqp(choice = Symbol::new(b"amqp:decode-error"))]
+    DecodeError,
+    #[amqp(other)]
+    Other(Symbol<'amqp>),
+}
+
+#[derive(Debug, Type)]
+#[amqp(descriptor = "example:failure:list")]
+pub struct Failure<'amqp> {
+    #[amqp(mandatory)]
+    pub condition: Condition<'amqp>,
+    pub mode: Option<SettleMode>,
+}
+
+#[test]
+fn test_restricted_enum() {
+    let mut writer = BufWriter::new(Vec::new());
+    writer.write_amqp_value(SettleMode::Settled).unwrap();
+    writer.write_amqp_value(Condition::NotFound).unwrap();
+    let buffer = writer.into_inner();
+    let mut expected = vec![0x50, 0x01, 0xb3, 0x00, 0x00, 0x00, 14];
+    expected.extend_from_slice(b"amqp:not-found");
+    assert_eq!(buffer, expected);
+
+    let value = Value::decode(&mut [0x50, 0x00].as_slice()).unwrap();
+    assert_eq!(
+        SettleMode::try_from_value(value).unwrap(),
+        SettleMode::Unsettled
+    );
+    let value = Value::decode(&mut [0x50, 0x02].as_slice()).unwrap();
+    let error = SettleMode::try_from_value(value).unwrap_err();
+    assert_eq!(error.to_string(), amqp_types::error::UNKNOWN_CHOICE);
+}
+
+#[test]
+fn test_restricted_enum_other() {
+    // a vendor condition, unknown to the enum
+    let mut buffer = vec![0x00, 0xa3, 20];
+    buffer.extend_from_slice(b"example:failure:list");
+    buffer.extend_from_slice(&[0xc0, 0x1e, 0x02, 0xa3, 0x19]);
+    buffer.extend_from_slice(b"com.microsoft:server-busy");
+    buffer.extend_from_slice(&[0x50, 0x01]);
+    let value = Value::decode(&mut buffer.as_slice()).unwrap();
+    let failure = Failure::try_from_value(value).unwrap();
+    assert_eq!(
+        failure.condition,
+        Condition::Other(Symbol::new(b"com.microsoft:server-busy"))
+    );
+    assert_eq!(failure.mode, Some(SettleMode::Settled));
+
+    // and sent back as it came
+    let size = failure.encoded_size(EncodeMode::Compact);
+    let mut writer = BufWriter::new(Vec::new()).with_mode(EncodeMode::Compact);
+    writer.write_amqp_value(failure).unwrap();
+    let written = writer.into_inner();
+    assert_eq!(written.len(), size);
+    assert_eq!(written, buffer);
+
+    let mut buffer = vec![0xa3, 17];
+    buffer.extend_from_slice(b"amqp:decode-error");
+    let value = Value::decode(&mut buffer.as_slice()).unwrap();
+    assert_eq!(
+        Condition::try_from_value(value).unwrap(),
+        Condition::DecodeError
+    );
+}
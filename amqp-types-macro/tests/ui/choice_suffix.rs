@@ -0,0 +1,10 @@
+use amqp_types::Type;
+
+#[derive(Type)]
+#[amqp(restrict(source = u8))]
+pub enum SenderSettleMode {
+    #[amqp(choice = 0u32)]
+    Unsettled,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use amqp_types::Type;
+
+#[derive(Type)]
+#[amqp(restrict(source = bool))]
+pub enum Role {
+    #[amqp(choice = 0)]
+    Sender,
+    #[amqp(choice = true)]
+    Receiver,
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use amqp_types::Type;
+
+#[derive(Type)]
+#[amqp(restrict(source = u8))]
+pub enum SenderSettleMode {
+    #[amqp(choice = 0)]
+    Unsettled,
+    #[amqp(choice = 1)]
+    Settled,
+    #[amqp(choice = 0x01)]
+    Mixed,
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use amqp_types::{Type, Value};
+
+#[derive(Type)]
+#[amqp(descriptor = 0x0000_0000_0000_0024)]
+pub struct Accepted {}
+
+#[derive(Type)]
+#[amqp(described)]
+pub enum Outcome<'amqp> {
+    Accepted(Accepted),
+    #[amqp(other)]
+    Unknown(Value<'amqp>),
+    #[amqp(other)]
+    Other(Value<'amqp>),
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use amqp_types::Type;
+
+#[derive(Type)]
+#[amqp(restrict(source = u8))]
+pub enum ReceiverSettleMode {
+    #[amqp(choice = 0)]
+    First,
+    Second,
+}
+
+fn main() {}
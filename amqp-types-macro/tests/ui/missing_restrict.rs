@@ -0,0 +1,11 @@
+use amqp_types::Type;
+
+#[derive(Type)]
+pub enum Role {
+    #[amqp(choice = false)]
+    Sender,
+    #[amqp(choice = true)]
+    Receiver,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use amqp_types::Type;
+
+#[derive(Type)]
+#[amqp(descriptor = 0x0000_0000_0000_0024)]
+#[amqp(provides = "delivery state")]
+pub struct Accepted {}
+
+fn main() {}
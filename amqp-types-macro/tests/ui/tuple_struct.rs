@@ -0,0 +1,6 @@
+use amqp_types::Type;
+
+#[derive(Type)]
+pub struct Range(u32, u32);
+
+fn main() {}
@@ -0,0 +1,13 @@
+use amqp_types::{Type, Value};
+
+#[derive(Type)]
+#[amqp(descriptor = 0x0000_0000_0000_0024)]
+pub struct Accepted {}
+
+#[derive(Type)]
+pub enum Outcome<'amqp> {
+    Accepted(Accepted),
+    Other(Value<'amqp>),
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use amqp_types::Type;
+
+#[derive(Type)]
+pub union Bits {
+    small: u8,
+    large: u64,
+}
+
+fn main() {}
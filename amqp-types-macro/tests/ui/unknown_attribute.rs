@@ -0,0 +1,12 @@
+use amqp_types::Type;
+
+#[derive(Type)]
+#[amqp(descriptor = 0x0000_0000_0000_0010)]
+pub struct Open {
+    #[amqp(mandatory)]
+    pub container_id: String,
+    #[amqp(optional)]
+    pub hostname: Option<String>,
+}
+
+fn main() {}
@@ -5,7 +5,7 @@ use crate::{
     descriptor::Descriptor,
     error::*,
     format_code::FormatCode,
-    primitive::{Primitive, Uuid},
+    primitive::{d128, d32, d64, Primitive, Uuid},
     value::Value,
 };
 pub trait DecodeExt: Sized {
@@ -117,7 +117,8 @@ impl<'de> Decode<'de> for Descriptor<'de> {
         Ok(match primitive {
             Primitive::ULong(code) => Descriptor::Numeric(code),
             Primitive::Symbol(s) => Descriptor::Symbol(s),
-            _ => Descriptor::Reserved(),
+            // the other descriptor values are reserved, section 1.5
+            _ => return Err(io::Error::other(RESERVED_DESCRIPTOR)),
         })
     }
 }
@@ -153,7 +154,7 @@ impl<'de> Decode<'de> for char {
         let code_point = data.peek_n::<4>()?;
         match char::from_u32(u32::from_be_bytes(code_point)) {
             Some(c) => {
-                *data = data.split_to(4);
+                data.split_to(4);
                 Ok(c)
             }
             None => Err(io::Error::other(INVALID_UTF32_CODE_POINT)),
@@ -174,6 +175,22 @@ impl<'de> Decode<'de> for Uuid {
     }
 }
 
+macro_rules! derive_decimals {
+    ($($dt: ty)*) => {
+        $(
+            impl<'de> Decode<'de> for $dt {
+                fn decode(data: &mut &'de [u8]) -> io::Result<Self> {
+                    data.try_eat_n().map(<$dt>::from_be_bytes)
+                }
+            }
+        )*
+    };
+}
+
+derive_decimals! {
+    d32 d64 d128
+}
+
 pub fn decode_str<'de>(data: &mut &'de [u8], size: usize) -> io::Result<&'de str> {
     let str_bytes = data.try_eat(size)?;
     match std::str::from_utf8(str_bytes) {
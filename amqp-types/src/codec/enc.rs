@@ -1,71 +1,53 @@
-use std::{fmt::Debug, io, mem::size_of};
+use std::{collections::HashMap, fmt::Debug, io};
+
+use bytes::{BufMut, BytesMut};
 
 use crate::{
     constructor::Constructor,
     descriptor::Descriptor,
     format_code::FormatCode,
-    primitive::{Array, Binary, Map, Symbol, Ts, Uuid},
+    primitive::{d128, d32, d64, Array, Binary, Map, OwnedSymbol, Symbol, Ts, Uuid},
     types::{Multiple, Type},
     value::Value,
 };
-#[derive(Debug)]
-pub struct Writer<'a> {
-    marker: std::marker::PhantomData<&'a mut [u8]>,
-    from: *const u8,
-    to: *const u8,
-    w_ptr: *mut u8,
+
+/// How [`Sink::write_amqp_value`] chooses the constructor of each value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EncodeMode {
+    /// every value of a type takes the type's `ENCODE_DEFAULT_FORMAT_CODE`
+    #[default]
+    Default,
+    /// every value takes the smallest constructor able to carry it, see [`Encode::encode_compact`]
+    Compact,
 }
 
 macro_rules! write_be {
     ($($f: ident: $T: ty)*) => {
         $(
-            pub fn $f(&mut self, value: $T) -> io::Result<()> {
-                self.remaining(size_of::<$T>())?;
-                unsafe {
-                    self.w_ptr
-                        .copy_from_nonoverlapping(&value.to_be_bytes() as *const u8, size_of::<$T>());
-                    self.w_ptr = self.w_ptr.add(size_of::<$T>());
-                }
-                Ok(())
+            #[inline]
+            fn $f(&mut self, value: $T) -> io::Result<()> {
+                self.write_slice(&value.to_be_bytes())
             }
         )*
     };
 }
 
-impl<'a> Writer<'a> {
-    pub fn new(writer: &'a mut [u8]) -> Self {
-        let from = writer.as_ptr();
-        let to = unsafe { from.add(writer.len()) };
-        let w_ptr = writer.as_mut_ptr();
-        Self {
-            marker: std::marker::PhantomData,
-            from,
-            to,
-            w_ptr,
-        }
-    }
-    pub fn finish(self) -> &'a mut [u8] {
-        unsafe {
-            std::slice::from_raw_parts_mut(
-                self.from as *mut u8,
-                self.to.offset_from(self.from) as usize,
-            )
-        }
-    }
-    pub fn remaining(&self, count: usize) -> io::Result<()> {
-        if unsafe { self.w_ptr.add(count) as *const _ } <= self.to {
-            Ok(())
-        } else {
-            Err(io::Error::new(io::ErrorKind::WriteZero, "no enough space"))
-        }
-    }
-    pub fn write_u8(&mut self, value: u8) -> io::Result<()> {
-        self.remaining(1)?;
-        unsafe {
-            self.w_ptr.write(value);
-            self.w_ptr = self.w_ptr.add(1);
-        }
-        Ok(())
+/// Something AMQP values are encoded into.
+///
+/// Implementors only provide raw byte access, the encoding helpers are all provided methods.
+pub trait Sink: Sized {
+    fn mode(&self) -> EncodeMode;
+    /// Number of bytes written so far.
+    fn position(&self) -> usize;
+    fn write_slice(&mut self, value: &[u8]) -> io::Result<()>;
+    /// All bytes written so far, to fill in sizes once they are known.
+    fn written_mut(&mut self) -> &mut [u8];
+    /// Drop everything written after `position`.
+    fn truncate(&mut self, position: usize);
+
+    #[inline]
+    fn write_u8(&mut self, value: u8) -> io::Result<()> {
+        self.write_slice(&[value])
     }
     write_be! {
         write_i8: i8
@@ -75,82 +57,123 @@ impl<'a> Writer<'a> {
         write_u16: u16
         write_u32: u32
         write_u64: u64
+        write_u128: u128
         write_f32: f32
         write_f64: f64
     }
     #[inline]
-    pub fn write_char(&mut self, value: char) -> io::Result<()> {
+    fn write_char(&mut self, value: char) -> io::Result<()> {
         self.write_u32(value as u32)
     }
     #[inline]
-    pub fn write_bool(&mut self, value: bool) -> io::Result<()> {
+    fn write_bool(&mut self, value: bool) -> io::Result<()> {
         self.write_u8(if value { 1 } else { 0 })
     }
-    pub fn write_slice(&mut self, value: &[u8]) -> io::Result<()> {
-        let count = value.len();
-        self.remaining(count)?;
-        unsafe {
-            self.w_ptr.copy_from_nonoverlapping(value.as_ptr(), count);
-            self.w_ptr = self.w_ptr.add(count);
-        }
-        Ok(())
-    }
-    pub fn write_variable_8(&mut self, value: &[u8]) -> io::Result<()> {
+    fn write_variable_8(&mut self, value: &[u8]) -> io::Result<()> {
         debug_assert!(u8::try_from(value.len()).is_ok());
         self.write_u8(value.len() as _)?;
         self.write_slice(value)?;
         Ok(())
     }
-    pub fn write_variable_32(&mut self, value: &[u8]) -> io::Result<()> {
+    fn write_variable_32(&mut self, value: &[u8]) -> io::Result<()> {
         debug_assert!(u32::try_from(value.len()).is_ok());
         self.write_u32(value.len() as _)?;
         self.write_slice(value)?;
         Ok(())
     }
-    pub fn write_items_8(
+    /// Reserve the 8-bit size and count of a compound value, returns the position to pass to
+    /// [`Sink::end_items_8`] once all items are written.
+    fn begin_items_8(&mut self) -> io::Result<usize> {
+        let start = self.position();
+        self.write_u8(0)?;
+        self.write_u8(0)?;
+        Ok(start)
+    }
+    fn end_items_8(&mut self, start: usize, count: usize) {
+        let size = self.position() - start - 1;
+        debug_assert!(u8::try_from(size).is_ok());
+        debug_assert!(u8::try_from(count).is_ok());
+        let written = self.written_mut();
+        written[start] = size as u8;
+        written[start + 1] = count as u8;
+    }
+    /// Reserve the 32-bit size and count of a compound value, returns the position to pass to
+    /// [`Sink::end_items_32`] once all items are written.
+    fn begin_items_32(&mut self) -> io::Result<usize> {
+        let start = self.position();
+        self.write_u32(0)?;
+        self.write_u32(0)?;
+        Ok(start)
+    }
+    fn end_items_32(&mut self, start: usize, count: usize) {
+        let size = self.position() - start - 4;
+        debug_assert!(u32::try_from(size).is_ok());
+        debug_assert!(u32::try_from(count).is_ok());
+        let written = self.written_mut();
+        written[start..start + 4].copy_from_slice(&(size as u32).to_be_bytes());
+        written[start + 4..start + 8].copy_from_slice(&(count as u32).to_be_bytes());
+    }
+    /// Narrow the compound value of the 32-bit `format_code` whose size starts at `start` to its
+    /// 8-bit form, or to `list0` if it's an empty list. Other format codes are left untouched.
+    ///
+    /// The constructor must be the byte right before `start`. This is for values which can't be
+    /// measured before they are written, [`Encode::encode_compact`] picks the narrow form upfront.
+    fn compact_items(&mut self, format_code: FormatCode, start: usize) {
+        let narrow = match format_code {
+            FormatCode::LIST32 => FormatCode::LIST8,
+            FormatCode::MAP32 => FormatCode::MAP8,
+            FormatCode::ARRAY32 => FormatCode::ARRAY8,
+            _ => return,
+        };
+        let end = self.position();
+        let written = self.written_mut();
+        let count = u32::from_be_bytes([
+            written[start + 4],
+            written[start + 5],
+            written[start + 6],
+            written[start + 7],
+        ]) as usize;
+        let items = end - start - 8;
+        let shrink = if count == 0 && format_code == FormatCode::LIST32 {
+            written[start - 1] = FormatCode::LIST0.into_u8();
+            8
+        } else if items < u8::MAX as usize && count <= u8::MAX as usize {
+            written[start - 1] = narrow.into_u8();
+            written[start] = (items + 1) as u8;
+            written[start + 1] = count as u8;
+            written.copy_within(start + 8..end, start + 2);
+            6
+        } else {
+            return;
+        };
+        self.truncate(end - shrink);
+    }
+    fn write_items_8(
         &mut self,
         fwrite: impl FnOnce(&mut Self) -> io::Result<usize>,
     ) -> io::Result<()> {
-        let size_ptr = self.w_ptr;
-        self.write_u8(0)?;
-        let count_ptr = self.w_ptr;
-        self.write_u8(0)?;
+        let start = self.begin_items_8()?;
         let count = fwrite(self)?;
-        unsafe {
-            let size = self.w_ptr.byte_offset_from(count_ptr as _) as usize;
-            debug_assert!(u8::try_from(size).is_ok());
-            debug_assert!(u8::try_from(count).is_ok());
-            size_ptr.write(size as u8);
-            count_ptr.write(count as u8);
-        }
+        self.end_items_8(start, count);
         Ok(())
     }
-    pub fn write_items_32(
+    fn write_items_32(
         &mut self,
         fwrite: impl FnOnce(&mut Self) -> io::Result<usize>,
     ) -> io::Result<()> {
-        let size_ptr = self.w_ptr;
-        self.write_u32(0)?;
-        let count_ptr = self.w_ptr;
-        self.write_u32(0)?;
+        let start = self.begin_items_32()?;
         let count = fwrite(self)?;
-        unsafe {
-            let size = dbg!(self.w_ptr.byte_offset_from(count_ptr as _) as usize);
-            debug_assert!(u32::try_from(size).is_ok());
-            debug_assert!(u32::try_from(count).is_ok());
-            (size_ptr as *mut u32).write((size as u32).to_be());
-            (count_ptr as *mut u32).write((count as u32).to_be());
-        }
+        self.end_items_32(start, count);
         Ok(())
     }
-    pub fn write_constructor(&mut self, constructor: Constructor) -> io::Result<()> {
+    fn write_constructor(&mut self, constructor: Constructor) -> io::Result<()> {
         if let Some(descriptor) = constructor.descriptor {
             self.write_u8(0x00)?;
             self.write_descriptor(descriptor)?
         }
         self.write_format_code(constructor.format_code)
     }
-    pub fn write_format_code(&mut self, format_code: FormatCode) -> io::Result<()> {
+    fn write_format_code(&mut self, format_code: FormatCode) -> io::Result<()> {
         match format_code {
             FormatCode::Primitive(p) => self.write_u8(p),
             FormatCode::Ext(c, e) => {
@@ -159,19 +182,162 @@ impl<'a> Writer<'a> {
             }
         }
     }
-    pub fn write_descriptor(&mut self, descriptor: Descriptor) -> io::Result<()> {
+    fn write_descriptor(&mut self, descriptor: Descriptor) -> io::Result<()> {
         match descriptor {
             Descriptor::Symbol(s) => self.write_amqp_value(s)?,
             Descriptor::Numeric(n) => self.write_amqp_value(n)?,
-            Descriptor::Reserved() => {
-                unimplemented!();
-            }
         };
         Ok(())
     }
     #[inline]
-    pub fn write_amqp_value(&mut self, value: impl Encode) -> io::Result<()> {
-        value.encode_default(self)
+    fn write_amqp_value(&mut self, value: impl Encode) -> io::Result<()> {
+        match self.mode() {
+            EncodeMode::Default => value.encode_default(self),
+            EncodeMode::Compact => value.encode_compact(self),
+        }
+    }
+}
+
+/// Encode into a fixed slice, fails with [`io::ErrorKind::WriteZero`] once it's full.
+#[derive(Debug)]
+pub struct Writer<'a> {
+    marker: std::marker::PhantomData<&'a mut [u8]>,
+    from: *const u8,
+    to: *const u8,
+    w_ptr: *mut u8,
+    mode: EncodeMode,
+}
+
+impl<'a> Writer<'a> {
+    pub fn new(writer: &'a mut [u8]) -> Self {
+        let from = writer.as_ptr();
+        let to = unsafe { from.add(writer.len()) };
+        let w_ptr = writer.as_mut_ptr();
+        Self {
+            marker: std::marker::PhantomData,
+            from,
+            to,
+            w_ptr,
+            mode: EncodeMode::Default,
+        }
+    }
+    pub fn with_mode(mut self, mode: EncodeMode) -> Self {
+        self.mode = mode;
+        self
+    }
+    pub fn finish(self) -> &'a mut [u8] {
+        unsafe {
+            std::slice::from_raw_parts_mut(
+                self.from as *mut u8,
+                self.to.offset_from(self.from) as usize,
+            )
+        }
+    }
+    pub fn remaining(&self, count: usize) -> io::Result<()> {
+        if unsafe { self.w_ptr.add(count) as *const _ } <= self.to {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::WriteZero, "no enough space"))
+        }
+    }
+}
+
+impl Sink for Writer<'_> {
+    fn mode(&self) -> EncodeMode {
+        self.mode
+    }
+    fn position(&self) -> usize {
+        unsafe { self.w_ptr.offset_from(self.from) as usize }
+    }
+    fn write_slice(&mut self, value: &[u8]) -> io::Result<()> {
+        let count = value.len();
+        self.remaining(count)?;
+        unsafe {
+            self.w_ptr.copy_from_nonoverlapping(value.as_ptr(), count);
+            self.w_ptr = self.w_ptr.add(count);
+        }
+        Ok(())
+    }
+    fn written_mut(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.from as *mut u8, self.position()) }
+    }
+    fn truncate(&mut self, position: usize) {
+        debug_assert!(position <= self.position());
+        self.w_ptr = unsafe { (self.from as *mut u8).add(position) };
+    }
+}
+
+/// A growable buffer that can be written back into, like `Vec<u8>` and `BytesMut`.
+pub trait GrowableBuf: BufMut + AsRef<[u8]> + AsMut<[u8]> {
+    fn truncate(&mut self, len: usize);
+}
+
+impl GrowableBuf for Vec<u8> {
+    fn truncate(&mut self, len: usize) {
+        Vec::truncate(self, len)
+    }
+}
+
+impl GrowableBuf for BytesMut {
+    fn truncate(&mut self, len: usize) {
+        BytesMut::truncate(self, len)
+    }
+}
+
+impl<B: GrowableBuf + ?Sized> GrowableBuf for &mut B {
+    fn truncate(&mut self, len: usize) {
+        B::truncate(self, len)
+    }
+}
+
+/// Encode at the end of a growable buffer, which is extended as needed.
+///
+/// Bytes already in the buffer are left untouched, [`Sink::position`] counts from its end at the
+/// time the writer is created.
+#[derive(Debug)]
+pub struct BufWriter<B> {
+    buf: B,
+    from: usize,
+    mode: EncodeMode,
+}
+
+impl<B: GrowableBuf> BufWriter<B> {
+    pub fn new(buf: B) -> Self {
+        let from = buf.as_ref().len();
+        Self {
+            buf,
+            from,
+            mode: EncodeMode::Default,
+        }
+    }
+    pub fn with_mode(mut self, mode: EncodeMode) -> Self {
+        self.mode = mode;
+        self
+    }
+    pub fn get_ref(&self) -> &B {
+        &self.buf
+    }
+    pub fn into_inner(self) -> B {
+        self.buf
+    }
+}
+
+impl<B: GrowableBuf> Sink for BufWriter<B> {
+    fn mode(&self) -> EncodeMode {
+        self.mode
+    }
+    fn position(&self) -> usize {
+        self.buf.as_ref().len() - self.from
+    }
+    fn write_slice(&mut self, value: &[u8]) -> io::Result<()> {
+        self.buf.put_slice(value);
+        Ok(())
+    }
+    fn written_mut(&mut self) -> &mut [u8] {
+        &mut self.buf.as_mut()[self.from..]
+    }
+    fn truncate(&mut self, position: usize) {
+        self.buf.truncate(self.from + position)
     }
 }
 
@@ -179,19 +345,141 @@ pub trait Encode: Sized {
     const DESCRIPTOR: Option<Descriptor<'static>> = None;
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode;
     /// we should panic here if the format code is invalid since the format code is determained by ourselves.
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()>;
-    fn encode(self, constructor: Constructor, writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()>;
+    /// Number of items of a compound value and the size they take in `mode`, the size and count
+    /// header excluded. `None` for other values.
+    ///
+    /// Compound values only need to provide this, their sizes and compact format code follow.
+    fn items_size(&self, mode: EncodeMode) -> Option<(usize, usize)> {
+        let _ = mode;
+        None
+    }
+    /// Whether this value is written as a null, those may be left out at the end of a list.
+    fn is_null(&self) -> bool {
+        false
+    }
+    /// Size of the data [`Encode::encode_data`] writes for `format_code`, values nested in
+    /// compound values being measured as [`Sink::write_amqp_value`] writes them in `mode`.
+    ///
+    /// Defaults to the width of fixed width format codes and to the header and items of compound
+    /// values, variable width values must override it.
+    fn data_size(&self, format_code: FormatCode, mode: EncodeMode) -> usize {
+        match self.items_size(mode) {
+            Some((_, items_size)) => compound_header_size(format_code) + items_size,
+            None => format_code
+                .fixed_width()
+                .unwrap_or_else(|| panic!("no data size for format code {format_code:?}")),
+        }
+    }
+    /// Exact number of bytes [`Sink::write_amqp_value`] writes for this value in `mode`.
+    fn encoded_size(&self, mode: EncodeMode) -> usize {
+        let (format_code, data_size) = match (self.items_size(mode), mode) {
+            (Some((count, items_size)), mode) => {
+                compound_size(Self::ENCODE_DEFAULT_FORMAT_CODE, count, items_size, mode)
+            }
+            (None, EncodeMode::Default) => {
+                let format_code = Self::ENCODE_DEFAULT_FORMAT_CODE;
+                (format_code, self.data_size(format_code, mode))
+            }
+            (None, EncodeMode::Compact) => {
+                let format_code = self.compact_format_code();
+                (format_code, self.data_size(format_code, mode))
+            }
+        };
+        let constructor = Constructor {
+            descriptor: Self::DESCRIPTOR,
+            format_code,
+        };
+        constructor.encoded_size(mode) + data_size
+    }
+    fn encode<W: Sink>(self, constructor: Constructor, writer: &mut W) -> io::Result<()> {
         let format_code = constructor.format_code;
         writer.write_constructor(constructor)?;
         self.encode_data(format_code, writer)
     }
-    fn encode_default(self, writer: &mut Writer) -> io::Result<()> {
+    fn encode_default<W: Sink>(self, writer: &mut W) -> io::Result<()> {
         writer.write_constructor(Constructor {
             descriptor: Self::DESCRIPTOR,
             format_code: Self::ENCODE_DEFAULT_FORMAT_CODE,
         })?;
         self.encode_data(Self::ENCODE_DEFAULT_FORMAT_CODE, writer)
     }
+    /// The smallest format code able to carry this value.
+    fn compact_format_code(&self) -> FormatCode {
+        match self.items_size(EncodeMode::Compact) {
+            Some((count, items_size)) => {
+                let format_code = Self::ENCODE_DEFAULT_FORMAT_CODE;
+                compound_size(format_code, count, items_size, EncodeMode::Compact).0
+            }
+            None => Self::ENCODE_DEFAULT_FORMAT_CODE,
+        }
+    }
+    fn encode_compact<W: Sink>(self, writer: &mut W) -> io::Result<()> {
+        let format_code = self.compact_format_code();
+        writer.write_constructor(Constructor {
+            descriptor: Self::DESCRIPTOR,
+            format_code,
+        })?;
+        self.encode_data(format_code, writer)
+    }
+}
+
+impl Constructor<'_> {
+    /// Number of bytes [`Sink::write_constructor`] writes for this constructor in `mode`.
+    pub fn encoded_size(&self, mode: EncodeMode) -> usize {
+        let descriptor = match &self.descriptor {
+            Some(Descriptor::Symbol(s)) => 1 + s.encoded_size(mode),
+            Some(Descriptor::Numeric(n)) => 1 + n.encoded_size(mode),
+            None => 0,
+        };
+        descriptor + self.format_code.size()
+    }
+}
+
+/// The format code and the data size of a compound value holding `count` items, which take
+/// `items_size` bytes, given its 32-bit `format_code`.
+///
+/// In compact mode the 8-bit form is used when the items fit, and `list0` for empty lists.
+pub fn compound_size(
+    format_code: FormatCode,
+    count: usize,
+    items_size: usize,
+    mode: EncodeMode,
+) -> (FormatCode, usize) {
+    let narrow = match format_code {
+        FormatCode::LIST32 => FormatCode::LIST8,
+        FormatCode::MAP32 => FormatCode::MAP8,
+        FormatCode::ARRAY32 => FormatCode::ARRAY8,
+        code => panic!("invalid format code {code:?}"),
+    };
+    match mode {
+        EncodeMode::Compact if count == 0 && format_code == FormatCode::LIST32 => {
+            (FormatCode::LIST0, 0)
+        }
+        EncodeMode::Compact if items_size < u8::MAX as usize && count <= u8::MAX as usize => {
+            (narrow, 2 + items_size)
+        }
+        _ => (format_code, 8 + items_size),
+    }
+}
+
+/// Size of the data of a variable width value of `len` bytes.
+fn variable_size(format_code: FormatCode, len: usize) -> usize {
+    match format_code.into_u8() & 0xf0 {
+        0xa0 => 1 + len,
+        0xb0 => 4 + len,
+        _ => panic!("invalid format code {format_code:?}"),
+    }
+}
+
+/// Size of the size and count header of a compound value.
+pub(crate) fn compound_header_size(format_code: FormatCode) -> usize {
+    match format_code {
+        FormatCode::LIST0 => 0,
+        FormatCode::LIST8 | FormatCode::MAP8 | FormatCode::ARRAY8 => 2,
+        FormatCode::LIST32 | FormatCode::MAP32 | FormatCode::ARRAY32 => 8,
+        code => panic!("invalid format code {code:?}"),
+    }
 }
 
 // fn write_descriptor<'b>(writer: &'b mut [u8], descriptor: Descriptor) -> io::Result<&'b mut [u8]> {
@@ -208,7 +496,7 @@ pub trait Encode: Sized {
 impl Encode for u8 {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::UBYTE;
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
         debug_assert_eq!(format_code, Self::ENCODE_DEFAULT_FORMAT_CODE);
         writer.write_u8(self)?;
         Ok(())
@@ -218,7 +506,7 @@ impl Encode for u8 {
 impl Encode for i8 {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::BYTE;
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
         debug_assert_eq!(format_code, Self::ENCODE_DEFAULT_FORMAT_CODE);
         writer.write_i8(self)?;
         Ok(())
@@ -228,7 +516,7 @@ impl Encode for i8 {
 impl Encode for u16 {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::USHORT;
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
         debug_assert_eq!(format_code, Self::ENCODE_DEFAULT_FORMAT_CODE);
         writer.write_u16(self)?;
         Ok(())
@@ -238,7 +526,7 @@ impl Encode for u16 {
 impl Encode for i16 {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::SHORT;
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
         debug_assert_eq!(format_code, Self::ENCODE_DEFAULT_FORMAT_CODE);
         writer.write_i16(self)?;
         Ok(())
@@ -247,8 +535,15 @@ impl Encode for i16 {
 
 impl Encode for u32 {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::UINT;
+    fn compact_format_code(&self) -> FormatCode {
+        match *self {
+            0 => FormatCode::UINT_0,
+            1..=0xff => FormatCode::SMALL_UINT,
+            _ => FormatCode::UINT,
+        }
+    }
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
         match format_code {
             FormatCode::UINT => writer.write_u32(self)?,
             FormatCode::UINT_0 => {
@@ -266,8 +561,14 @@ impl Encode for u32 {
 
 impl Encode for i32 {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::INT;
+    fn compact_format_code(&self) -> FormatCode {
+        match i8::try_from(*self) {
+            Ok(_) => FormatCode::SMALL_INT,
+            Err(_) => FormatCode::INT,
+        }
+    }
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
         match format_code {
             FormatCode::INT => writer.write_i32(self)?,
             FormatCode::SMALL_INT => {
@@ -282,8 +583,15 @@ impl Encode for i32 {
 
 impl Encode for u64 {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::ULONG;
+    fn compact_format_code(&self) -> FormatCode {
+        match *self {
+            0 => FormatCode::ULONG_0,
+            1..=0xff => FormatCode::SMALL_ULONG,
+            _ => FormatCode::ULONG,
+        }
+    }
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
         match format_code {
             FormatCode::ULONG => writer.write_u64(self)?,
             FormatCode::ULONG_0 => {
@@ -301,8 +609,14 @@ impl Encode for u64 {
 
 impl Encode for i64 {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::LONG;
+    fn compact_format_code(&self) -> FormatCode {
+        match i8::try_from(*self) {
+            Ok(_) => FormatCode::SMALL_LONG,
+            Err(_) => FormatCode::LONG,
+        }
+    }
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
         match format_code {
             FormatCode::LONG => writer.write_i64(self)?,
             FormatCode::SMALL_LONG => {
@@ -318,7 +632,7 @@ impl Encode for i64 {
 impl Encode for f32 {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::FLOAT;
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
         debug_assert_eq!(format_code, Self::ENCODE_DEFAULT_FORMAT_CODE);
         writer.write_f32(self)?;
         Ok(())
@@ -328,7 +642,7 @@ impl Encode for f32 {
 impl Encode for f64 {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::DOUBLE;
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
         debug_assert_eq!(format_code, Self::ENCODE_DEFAULT_FORMAT_CODE);
         writer.write_f64(self)?;
         Ok(())
@@ -337,8 +651,15 @@ impl Encode for f64 {
 
 impl Encode for bool {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::BOOLEAN;
+    fn compact_format_code(&self) -> FormatCode {
+        if *self {
+            FormatCode::BOOLEAN_TRUE
+        } else {
+            FormatCode::BOOLEAN_FALSE
+        }
+    }
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
         match format_code {
             FormatCode::BOOLEAN => {
                 writer.write_u8(self as u8)?;
@@ -354,17 +675,39 @@ impl Encode for bool {
 impl Encode for char {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::CHAR;
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
         debug_assert_eq!(format_code, Self::ENCODE_DEFAULT_FORMAT_CODE);
         writer.write_u32(self as u32)?;
         Ok(())
     }
 }
 
+macro_rules! encode_decimals {
+    ($($dt: ty = $code: ident)*) => {
+        $(
+            impl Encode for $dt {
+                const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::$code;
+
+                fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
+                    debug_assert_eq!(format_code, Self::ENCODE_DEFAULT_FORMAT_CODE);
+                    writer.write_slice(&self.to_be_bytes())?;
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+encode_decimals! {
+    d32 = DECIMAL32
+    d64 = DECIMAL64
+    d128 = DECIMAL128
+}
+
 impl Encode for Uuid {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::UUID;
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
         debug_assert_eq!(format_code, Self::ENCODE_DEFAULT_FORMAT_CODE);
         writer.write_slice(&self.0)?;
         Ok(())
@@ -374,17 +717,28 @@ impl Encode for Uuid {
 impl Encode for Ts {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::TIMESTAMP;
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
         debug_assert_eq!(format_code, Self::ENCODE_DEFAULT_FORMAT_CODE);
-        writer.write_u64(self.0)?;
+        writer.write_i64(self.0)?;
         Ok(())
     }
 }
 
 impl<'a> Encode for &'a str {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::STRING32_UTF8;
+    fn compact_format_code(&self) -> FormatCode {
+        if self.len() <= u8::MAX as usize {
+            FormatCode::STRING8_UTF8
+        } else {
+            FormatCode::STRING32_UTF8
+        }
+    }
+
+    fn data_size(&self, format_code: FormatCode, _mode: EncodeMode) -> usize {
+        variable_size(format_code, self.len())
+    }
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
         match format_code {
             FormatCode::STRING8_UTF8 => writer.write_variable_8(self.as_bytes())?,
             FormatCode::STRING32_UTF8 => writer.write_variable_32(self.as_bytes())?,
@@ -395,8 +749,19 @@ impl<'a> Encode for &'a str {
 }
 impl<'a> Encode for Symbol<'a> {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::SYMBOL32;
+    fn compact_format_code(&self) -> FormatCode {
+        if self.0.len() <= u8::MAX as usize {
+            FormatCode::SYMBOL8
+        } else {
+            FormatCode::SYMBOL32
+        }
+    }
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
+    fn data_size(&self, format_code: FormatCode, _mode: EncodeMode) -> usize {
+        variable_size(format_code, self.0.len())
+    }
+
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
         match format_code {
             FormatCode::SYMBOL8 => writer.write_variable_8(self.0)?,
             FormatCode::SYMBOL32 => writer.write_variable_32(self.0)?,
@@ -406,10 +771,40 @@ impl<'a> Encode for Symbol<'a> {
     }
 }
 
+impl Encode for OwnedSymbol {
+    const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::SYMBOL32;
+    fn compact_format_code(&self) -> FormatCode {
+        if self.as_bytes().len() <= u8::MAX as usize {
+            FormatCode::SYMBOL8
+        } else {
+            FormatCode::SYMBOL32
+        }
+    }
+
+    fn data_size(&self, format_code: FormatCode, _mode: EncodeMode) -> usize {
+        variable_size(format_code, self.as_bytes().len())
+    }
+
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
+        self.as_symbol().encode_data(format_code, writer)
+    }
+}
+
 impl<'a> Encode for Binary<'a> {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::BINARY32;
+    fn compact_format_code(&self) -> FormatCode {
+        if self.0.len() <= u8::MAX as usize {
+            FormatCode::BINARY8
+        } else {
+            FormatCode::BINARY32
+        }
+    }
+
+    fn data_size(&self, format_code: FormatCode, _mode: EncodeMode) -> usize {
+        variable_size(format_code, self.0.len())
+    }
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
         match format_code {
             FormatCode::BINARY8 => writer.write_variable_8(self.0)?,
             FormatCode::BINARY32 => writer.write_variable_32(self.0)?,
@@ -422,8 +817,17 @@ impl<'a> Encode for Binary<'a> {
 impl<'a, T: Multiple + Type<'a>> Encode for Array<'a, T> {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::ARRAY32;
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
-        let fwrite = move |writer: &mut Writer| {
+    fn items_size(&self, mode: EncodeMode) -> Option<(usize, usize)> {
+        let constructor = Constructor {
+            descriptor: T::DESCRIPTOR,
+            format_code: T::ENCODE_DEFAULT_FORMAT_CODE,
+        };
+        let (count, items_size) = Array::items_size(self, mode);
+        Some((count, constructor.encoded_size(mode) + items_size))
+    }
+
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
+        let fwrite = move |writer: &mut W| {
             writer.write_constructor(Constructor {
                 descriptor: T::DESCRIPTOR,
                 format_code: T::ENCODE_DEFAULT_FORMAT_CODE,
@@ -452,13 +856,17 @@ impl<'a, T: Multiple + Type<'a>> Encode for Array<'a, T> {
 impl<'a, K: Type<'a>, V: Type<'a>> Encode for Map<'a, K, V> {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::MAP32;
 
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
-        let fwrite = move |writer: &mut Writer| {
+    fn items_size(&self, mode: EncodeMode) -> Option<(usize, usize)> {
+        Some(Map::items_size(self, mode))
+    }
+
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
+        let fwrite = move |writer: &mut W| {
             let mut count: usize = 0;
             for item in self {
                 let (k, v) = item?;
-                k.encode_default(writer)?;
-                v.encode_default(writer)?;
+                writer.write_amqp_value(k)?;
+                writer.write_amqp_value(v)?;
                 count += 2;
             }
             io::Result::Ok(count)
@@ -476,10 +884,47 @@ impl<'a, K: Type<'a>, V: Type<'a>> Encode for Map<'a, K, V> {
     }
 }
 
+impl<K: Encode, V: Encode> Encode for HashMap<K, V> {
+    const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::MAP32;
+
+    fn items_size(&self, mode: EncodeMode) -> Option<(usize, usize)> {
+        let size = self
+            .iter()
+            .map(|(k, v)| k.encoded_size(mode) + v.encoded_size(mode))
+            .sum();
+        Some((self.len() * 2, size))
+    }
+
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
+        let fwrite = move |writer: &mut W| {
+            let count = self.len() * 2;
+            for (k, v) in self {
+                writer.write_amqp_value(k)?;
+                writer.write_amqp_value(v)?;
+            }
+            io::Result::Ok(count)
+        };
+        match format_code {
+            FormatCode::MAP8 => {
+                writer.write_items_8(fwrite)?;
+            }
+            FormatCode::MAP32 => {
+                writer.write_items_32(fwrite)?;
+            }
+            code => panic!("invalid format code {code:?}"),
+        }
+        Ok(())
+    }
+}
+
 impl Encode for () {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::NULL;
 
-    fn encode_data(self, format_code: FormatCode, _writer: &mut Writer) -> io::Result<()> {
+    fn is_null(&self) -> bool {
+        true
+    }
+
+    fn encode_data<W: Sink>(self, format_code: FormatCode, _writer: &mut W) -> io::Result<()> {
         debug_assert_eq!(format_code, Self::ENCODE_DEFAULT_FORMAT_CODE);
         Ok(())
     }
@@ -488,40 +933,88 @@ impl Encode for () {
 impl<'a, T: Type<'a>> Encode for Option<T> {
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = T::ENCODE_DEFAULT_FORMAT_CODE;
     const DESCRIPTOR: Option<Descriptor<'static>> = T::DESCRIPTOR;
-    fn encode_data(self, format_code: FormatCode, writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
         match self {
             Some(v) => v.encode_data(format_code, writer),
             None => ().encode_data(FormatCode::NULL, writer),
         }
     }
 
-    fn encode(self, constructor: Constructor, writer: &mut Writer) -> io::Result<()> {
+    fn encode<W: Sink>(self, constructor: Constructor, writer: &mut W) -> io::Result<()> {
         match self {
             Some(v) => v.encode(constructor, writer),
             None => ().encode_default(writer),
         }
     }
 
-    fn encode_default(self, writer: &mut Writer) -> io::Result<()> {
+    fn encode_default<W: Sink>(self, writer: &mut W) -> io::Result<()> {
         match self {
             Some(v) => v.encode_default(writer),
             None => ().encode_default(writer),
         }
     }
+
+    fn is_null(&self) -> bool {
+        match self {
+            Some(v) => v.is_null(),
+            None => true,
+        }
+    }
+
+    fn data_size(&self, format_code: FormatCode, mode: EncodeMode) -> usize {
+        match self {
+            Some(v) => v.data_size(format_code, mode),
+            None => 0,
+        }
+    }
+
+    fn encoded_size(&self, mode: EncodeMode) -> usize {
+        match self {
+            Some(v) => v.encoded_size(mode),
+            None => ().encoded_size(mode),
+        }
+    }
+
+    fn compact_format_code(&self) -> FormatCode {
+        match self {
+            Some(v) => v.compact_format_code(),
+            None => FormatCode::NULL,
+        }
+    }
+
+    fn encode_compact<W: Sink>(self, writer: &mut W) -> io::Result<()> {
+        match self {
+            Some(v) => v.encode_compact(writer),
+            None => ().encode_default(writer),
+        }
+    }
 }
 
 impl<'a> Encode for Value<'a> {
     const DESCRIPTOR: Option<Descriptor<'static>> = unreachable!();
     const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = unreachable!();
-    fn encode_data(self, _format_code: FormatCode, _writer: &mut Writer) -> io::Result<()> {
+    fn encode_data<W: Sink>(self, _format_code: FormatCode, _writer: &mut W) -> io::Result<()> {
         unreachable!("don't encode value's data directly, use encode instead")
     }
-    fn encode(self, _constructor: Constructor, writer: &mut Writer) -> io::Result<()> {
+    fn encode<W: Sink>(self, _constructor: Constructor, writer: &mut W) -> io::Result<()> {
         self.encode_default(writer)
     }
-    fn encode_default(self, writer: &mut Writer) -> io::Result<()> {
+    fn encode_default<W: Sink>(self, writer: &mut W) -> io::Result<()> {
         writer.write_constructor(self.constructor)?;
         writer.write_slice(self.data.into_inner())?;
         Ok(())
     }
+    fn is_null(&self) -> bool {
+        self.constructor.descriptor.is_none() && self.constructor.format_code == FormatCode::NULL
+    }
+    /// a value is already encoded, it's copied as it is
+    fn encode_compact<W: Sink>(self, writer: &mut W) -> io::Result<()> {
+        self.encode_default(writer)
+    }
+    fn data_size(&self, _format_code: FormatCode, _mode: EncodeMode) -> usize {
+        self.data.len()
+    }
+    fn encoded_size(&self, mode: EncodeMode) -> usize {
+        self.constructor.encoded_size(mode) + self.data.len()
+    }
 }
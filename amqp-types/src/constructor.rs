@@ -1,5 +1,5 @@
 use crate::codec::{decode_str, Decode, DecodeExt};
-use crate::primitive::{ArrayIter, Binary, ListIter, Symbol};
+use crate::primitive::{ArrayIter, Binary, ListIter, Symbol, Ts};
 use crate::{
     data::Data, descriptor::Descriptor, error::UNKNOWN_AMQP_TYPE, format_code::FormatCode,
     primitive::Primitive,
@@ -29,26 +29,18 @@ impl<'frame> Constructor<'frame> {
             FormatCode::SMALL_INT => Primitive::Int(i8::decode(&mut data)? as i32),
             FormatCode::SMALL_LONG => Primitive::Long(i8::decode(&mut data)? as i64),
             FormatCode::USHORT => Primitive::UShort(Decode::decode(&mut data)?),
-            FormatCode::SHORT => Primitive::UShort(Decode::decode(&mut data)?),
+            FormatCode::SHORT => Primitive::Short(Decode::decode(&mut data)?),
             FormatCode::UINT => Primitive::UInt(Decode::decode(&mut data)?),
             FormatCode::INT => Primitive::Int(Decode::decode(&mut data)?),
             FormatCode::FLOAT => Primitive::Float(Decode::decode(&mut data)?),
             FormatCode::CHAR => Primitive::Char(Decode::decode(&mut data)?),
-            // FormatCode::DECIMAL32 => {
-            //     todo!()
-            // }
+            FormatCode::DECIMAL32 => Primitive::Decimal32(Decode::decode(&mut data)?),
             FormatCode::ULONG => Primitive::ULong(Decode::decode(&mut data)?),
             FormatCode::LONG => Primitive::Long(Decode::decode(&mut data)?),
             FormatCode::DOUBLE => Primitive::Double(Decode::decode(&mut data)?),
-            // FormatCode::DECIMAL64 => {
-            //     todo!()
-            // }
-            // FormatCode::DECIMAL128 => {
-            //     todo!()
-            // }
-            // FormatCode::TIMESTAMP => {
-            //     todo!()
-            // }
+            FormatCode::DECIMAL64 => Primitive::Decimal64(Decode::decode(&mut data)?),
+            FormatCode::DECIMAL128 => Primitive::Decimal128(Decode::decode(&mut data)?),
+            FormatCode::TIMESTAMP => Primitive::Timestamp(Ts(Decode::decode(&mut data)?)),
             FormatCode::UUID => Primitive::Uuid(Decode::decode(&mut data)?),
             FormatCode::BINARY8 => {
                 let size = u8::decode(&mut data)? as usize;
@@ -59,7 +51,7 @@ impl<'frame> Constructor<'frame> {
                 Primitive::Binary(Binary(data.try_eat(size)?))
             }
             FormatCode::STRING8_UTF8 => {
-                let size = u32::decode(&mut data)? as usize;
+                let size = u8::decode(&mut data)? as usize;
                 decode_str(&mut data, size).map(Primitive::String)?
             }
             FormatCode::STRING32_UTF8 => {
@@ -1,9 +1,9 @@
-use crate::primitive::Symbol;
+use crate::error::DescriptorMismatch;
+use crate::primitive::{OwnedSymbol, Symbol};
 #[derive(Debug, Clone)]
 pub enum Descriptor<'a> {
     Symbol(Symbol<'a>),
     Numeric(u64),
-    Reserved(/* Box<Value<'frame>> */),
 }
 
 impl<'a> Descriptor<'a> {
@@ -13,4 +13,84 @@ impl<'a> Descriptor<'a> {
     pub const fn numeric(id: u64) -> Self {
         Self::Numeric(id)
     }
+    pub fn into_owned(self) -> OwnedDescriptor {
+        match self {
+            Descriptor::Symbol(s) => OwnedDescriptor::Symbol(s.into_owned()),
+            Descriptor::Numeric(n) => OwnedDescriptor::Numeric(n),
+        }
+    }
+}
+
+/// The owned counterpart of [`Descriptor`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OwnedDescriptor {
+    Symbol(OwnedSymbol),
+    Numeric(u64),
+}
+
+impl OwnedDescriptor {
+    pub fn as_descriptor(&self) -> Descriptor<'_> {
+        match self {
+            OwnedDescriptor::Symbol(s) => Descriptor::Symbol(s.as_symbol()),
+            OwnedDescriptor::Numeric(n) => Descriptor::Numeric(*n),
+        }
+    }
+}
+
+/// How a described type is identified, by its symbolic name, its numeric code, or both, eg.
+/// `amqp:open:list` and `0x00000000:0x00000010`.
+///
+/// Peers may send either form, [`DescriptorId::matches`] accepts both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DescriptorId {
+    pub name: Option<&'static str>,
+    pub code: Option<u64>,
+}
+
+impl DescriptorId {
+    pub const fn name(name: &'static str) -> Self {
+        Self {
+            name: Some(name),
+            code: None,
+        }
+    }
+    pub const fn code(code: u64) -> Self {
+        Self {
+            name: None,
+            code: Some(code),
+        }
+    }
+    pub const fn new(name: &'static str, code: u64) -> Self {
+        Self {
+            name: Some(name),
+            code: Some(code),
+        }
+    }
+    /// The form written on encode, the numeric code if there is one since it's shorter.
+    pub const fn descriptor(&self) -> Descriptor<'static> {
+        match (self.code, self.name) {
+            (Some(code), _) => Descriptor::Numeric(code),
+            (None, Some(name)) => Descriptor::Symbol(Symbol::new(name.as_bytes())),
+            (None, None) => panic!("a descriptor id needs a name or a code"),
+        }
+    }
+    /// Check the `descriptor` of a received value identifies this type.
+    pub fn verify(&self, descriptor: Option<&Descriptor>) -> Result<(), DescriptorMismatch> {
+        match descriptor {
+            Some(descriptor) if self.matches(descriptor) => Ok(()),
+            found => Err(DescriptorMismatch {
+                expected: *self,
+                found: found.cloned().map(Descriptor::into_owned),
+            }),
+        }
+    }
+    /// Whether a received `descriptor` identifies this type, in either form.
+    pub fn matches(&self, descriptor: &Descriptor) -> bool {
+        match descriptor {
+            Descriptor::Symbol(s) => self
+                .name
+                .is_some_and(|name| name.as_bytes() == s.as_bytes()),
+            Descriptor::Numeric(n) => self.code == Some(*n),
+        }
+    }
 }
@@ -1,5 +1,49 @@
+use crate::descriptor::{DescriptorId, OwnedDescriptor};
+
 pub const UNEXPECTED_TYPE: &str = "unexpected amqp type";
 pub const UNKNOWN_AMQP_TYPE: &str = "unknown AMQP type";
+pub const UNKNOWN_DESCRIPTOR: &str = "unknown descriptor";
+pub const RESERVED_DESCRIPTOR: &str = "descriptor is neither a symbol nor a ulong";
 pub const BUFFER_OVERFLOW: &str = "buffer overflow";
 pub const BUFFER_SIZE_ERROR: &str = "buffer size error";
 pub const INVALID_UTF32_CODE_POINT: &str = "invalid utf32 code point";
+pub const TIMESTAMP_OUT_OF_RANGE: &str = "timestamp out of range";
+pub const MANDATORY_FIELD_MISSING: &str = "mandatory field is missing or null";
+pub const HETEROGENEOUS_ARRAY: &str = "array items must share one constructor";
+pub const UNKNOWN_CHOICE: &str = "value is none of the choices of the restricted type";
+pub const INVALID_RESTRICTED_VALUE: &str = "value is rejected by the restricted type";
+
+/// A described value didn't carry the descriptor of the type it was decoded as.
+///
+/// Returned as the inner error of an [`std::io::Error`] of kind `InvalidData`, get it back with
+/// [`DescriptorMismatch::from_io`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescriptorMismatch {
+    pub expected: DescriptorId,
+    /// `None` if the value wasn't described at all
+    pub found: Option<OwnedDescriptor>,
+}
+
+impl DescriptorMismatch {
+    pub fn from_io(error: &std::io::Error) -> Option<&Self> {
+        error.get_ref()?.downcast_ref()
+    }
+}
+
+impl std::fmt::Display for DescriptorMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "expected descriptor {:?}, found {:?}",
+            self.expected, self.found
+        )
+    }
+}
+
+impl std::error::Error for DescriptorMismatch {}
+
+impl From<DescriptorMismatch> for std::io::Error {
+    fn from(value: DescriptorMismatch) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, value)
+    }
+}
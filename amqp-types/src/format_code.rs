@@ -188,6 +188,25 @@ pub enum ArraySubcategory {
 }
 
 impl FormatCode {
+    /// Width of the data of a fixed width format code, `None` for the other format codes.
+    pub const fn fixed_width(&self) -> Option<usize> {
+        match self.into_u8() & 0xf0 {
+            0x40 => Some(0),
+            0x50 => Some(1),
+            0x60 => Some(2),
+            0x70 => Some(4),
+            0x80 => Some(8),
+            0x90 => Some(16),
+            _ => None,
+        }
+    }
+    /// Number of bytes the format code itself takes.
+    pub const fn size(&self) -> usize {
+        match self {
+            FormatCode::Primitive(_) => 1,
+            FormatCode::Ext(_, _) => 2,
+        }
+    }
     pub fn peek_size(&self, bytes: &[u8]) -> io::Result<usize> {
         let fb = match self {
             FormatCode::Primitive(b) => b,
@@ -11,8 +11,13 @@ pub mod primitive;
 pub use primitive::Primitive;
 mod value;
 pub use value::Value;
+mod owned;
+pub use owned::OwnedValue;
 pub mod codec;
 pub mod error;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod provides;
 pub mod types;
 
 pub use amqp_types_macro::Type;
\ No newline at end of file
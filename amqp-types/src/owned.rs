@@ -0,0 +1,537 @@
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    io,
+};
+
+use bytes::Bytes;
+
+use crate::{
+    codec::{compound_header_size, compound_size, Encode, EncodeMode, Sink},
+    constructor::Constructor,
+    descriptor::OwnedDescriptor,
+    error::{HETEROGENEOUS_ARRAY, UNEXPECTED_TYPE},
+    format_code::FormatCode,
+    primitive::{d128, d32, d64, Binary, OwnedSymbol, Primitive, Ts, Uuid},
+    value::Value,
+};
+
+/// An owned AMQP value tree, which can outlive the frame buffer it was decoded from.
+///
+/// Unlike [`Value`], which keeps the encoded bytes and decodes lazily, an `OwnedValue` is fully
+/// decoded. Two values are equal if they hold the same data, no matter which constructor was used
+/// to encode them, so they can be used as map keys. Floating point values are compared by their
+/// [total order](f64::total_cmp), and maps compare their entries in order.
+#[derive(Debug, Clone, Default)]
+pub enum OwnedValue {
+    #[default]
+    Null,
+    Boolean(bool),
+    UByte(u8),
+    UShort(u16),
+    UInt(u32),
+    ULong(u64),
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Decimal32(d32),
+    Decimal64(d64),
+    Decimal128(d128),
+    Char(char),
+    Timestamp(Ts),
+    Uuid(Uuid),
+    String(String),
+    Binary(Bytes),
+    Symbol(OwnedSymbol),
+    List(Vec<OwnedValue>),
+    Map(Vec<(OwnedValue, OwnedValue)>),
+    Array(Vec<OwnedValue>),
+    Described(OwnedDescriptor, Box<OwnedValue>),
+}
+
+impl OwnedValue {
+    pub fn is_null(&self) -> bool {
+        matches!(self, OwnedValue::Null)
+    }
+    pub fn descriptor(&self) -> Option<&OwnedDescriptor> {
+        match self {
+            OwnedValue::Described(descriptor, _) => Some(descriptor),
+            _ => None,
+        }
+    }
+    /// The format code used to encode this value, the one of the inner value for described values.
+    pub fn format_code(&self) -> FormatCode {
+        match self {
+            OwnedValue::Null => FormatCode::NULL,
+            OwnedValue::Boolean(_) => FormatCode::BOOLEAN,
+            OwnedValue::UByte(_) => FormatCode::UBYTE,
+            OwnedValue::UShort(_) => FormatCode::USHORT,
+            OwnedValue::UInt(_) => FormatCode::UINT,
+            OwnedValue::ULong(_) => FormatCode::ULONG,
+            OwnedValue::Byte(_) => FormatCode::BYTE,
+            OwnedValue::Short(_) => FormatCode::SHORT,
+            OwnedValue::Int(_) => FormatCode::INT,
+            OwnedValue::Long(_) => FormatCode::LONG,
+            OwnedValue::Float(_) => FormatCode::FLOAT,
+            OwnedValue::Double(_) => FormatCode::DOUBLE,
+            OwnedValue::Decimal32(_) => FormatCode::DECIMAL32,
+            OwnedValue::Decimal64(_) => FormatCode::DECIMAL64,
+            OwnedValue::Decimal128(_) => FormatCode::DECIMAL128,
+            OwnedValue::Char(_) => FormatCode::CHAR,
+            OwnedValue::Timestamp(_) => FormatCode::TIMESTAMP,
+            OwnedValue::Uuid(_) => FormatCode::UUID,
+            OwnedValue::String(_) => FormatCode::STRING32_UTF8,
+            OwnedValue::Binary(_) => FormatCode::BINARY32,
+            OwnedValue::Symbol(_) => FormatCode::SYMBOL32,
+            OwnedValue::List(_) => FormatCode::LIST32,
+            OwnedValue::Map(_) => FormatCode::MAP32,
+            OwnedValue::Array(_) => FormatCode::ARRAY32,
+            OwnedValue::Described(_, value) => value.format_code(),
+        }
+    }
+    pub fn constructor(&self) -> Constructor<'_> {
+        Constructor {
+            descriptor: self.descriptor().map(OwnedDescriptor::as_descriptor),
+            format_code: self.format_code(),
+        }
+    }
+    /// position of the variant, orders values of different types
+    fn rank(&self) -> u8 {
+        match self {
+            OwnedValue::Null => 0,
+            OwnedValue::Boolean(_) => 1,
+            OwnedValue::UByte(_) => 2,
+            OwnedValue::UShort(_) => 3,
+            OwnedValue::UInt(_) => 4,
+            OwnedValue::ULong(_) => 5,
+            OwnedValue::Byte(_) => 6,
+            OwnedValue::Short(_) => 7,
+            OwnedValue::Int(_) => 8,
+            OwnedValue::Long(_) => 9,
+            OwnedValue::Float(_) => 10,
+            OwnedValue::Double(_) => 11,
+            OwnedValue::Decimal32(_) => 12,
+            OwnedValue::Decimal64(_) => 13,
+            OwnedValue::Decimal128(_) => 14,
+            OwnedValue::Char(_) => 15,
+            OwnedValue::Timestamp(_) => 16,
+            OwnedValue::Uuid(_) => 17,
+            OwnedValue::String(_) => 18,
+            OwnedValue::Binary(_) => 19,
+            OwnedValue::Symbol(_) => 20,
+            OwnedValue::List(_) => 21,
+            OwnedValue::Map(_) => 22,
+            OwnedValue::Array(_) => 23,
+            OwnedValue::Described(_, _) => 24,
+        }
+    }
+}
+
+impl<'frame> Value<'frame> {
+    pub fn into_owned(self) -> io::Result<OwnedValue> {
+        let descriptor = self.constructor.descriptor.clone();
+        let value = self.construct()?.into_owned()?;
+        Ok(match descriptor {
+            Some(descriptor) => OwnedValue::Described(descriptor.into_owned(), Box::new(value)),
+            None => value,
+        })
+    }
+}
+
+impl<'frame> Primitive<'frame> {
+    pub fn into_owned(self) -> io::Result<OwnedValue> {
+        let value = match self {
+            Primitive::Null => OwnedValue::Null,
+            Primitive::Boolean(v) => OwnedValue::Boolean(v),
+            Primitive::UByte(v) => OwnedValue::UByte(v),
+            Primitive::UShort(v) => OwnedValue::UShort(v),
+            Primitive::UInt(v) => OwnedValue::UInt(v),
+            Primitive::ULong(v) => OwnedValue::ULong(v),
+            Primitive::Byte(v) => OwnedValue::Byte(v),
+            Primitive::Short(v) => OwnedValue::Short(v),
+            Primitive::Int(v) => OwnedValue::Int(v),
+            Primitive::Long(v) => OwnedValue::Long(v),
+            Primitive::Float(v) => OwnedValue::Float(v),
+            Primitive::Double(v) => OwnedValue::Double(v),
+            Primitive::Decimal32(v) => OwnedValue::Decimal32(v),
+            Primitive::Decimal64(v) => OwnedValue::Decimal64(v),
+            Primitive::Decimal128(v) => OwnedValue::Decimal128(v),
+            Primitive::Char(v) => OwnedValue::Char(v),
+            Primitive::Timestamp(v) => OwnedValue::Timestamp(v),
+            Primitive::Uuid(v) => OwnedValue::Uuid(v),
+            Primitive::String(v) => OwnedValue::String(v.to_owned()),
+            Primitive::Binary(v) => OwnedValue::Binary(v.into_owned()),
+            Primitive::Symbol(v) => OwnedValue::Symbol(v.into_owned()),
+            Primitive::List(items) => OwnedValue::List(
+                items
+                    .map(|item| item?.into_owned())
+                    .collect::<io::Result<_>>()?,
+            ),
+            Primitive::Map(entries) => OwnedValue::Map(
+                entries
+                    .map(|entry| {
+                        let (k, v) = entry?;
+                        Ok((k.into_owned()?, v.into_owned()?))
+                    })
+                    .collect::<io::Result<_>>()?,
+            ),
+            Primitive::Array(items) => OwnedValue::Array(
+                items
+                    .map(|item| item?.into_owned())
+                    .collect::<io::Result<_>>()?,
+            ),
+        };
+        Ok(value)
+    }
+}
+
+impl Ord for OwnedValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        use OwnedValue::*;
+        match (self, other) {
+            (Null, Null) => Ordering::Equal,
+            (Boolean(a), Boolean(b)) => a.cmp(b),
+            (UByte(a), UByte(b)) => a.cmp(b),
+            (UShort(a), UShort(b)) => a.cmp(b),
+            (UInt(a), UInt(b)) => a.cmp(b),
+            (ULong(a), ULong(b)) => a.cmp(b),
+            (Byte(a), Byte(b)) => a.cmp(b),
+            (Short(a), Short(b)) => a.cmp(b),
+            (Int(a), Int(b)) => a.cmp(b),
+            (Long(a), Long(b)) => a.cmp(b),
+            (Float(a), Float(b)) => a.total_cmp(b),
+            (Double(a), Double(b)) => a.total_cmp(b),
+            (Decimal32(a), Decimal32(b)) => a.to_bits().cmp(&b.to_bits()),
+            (Decimal64(a), Decimal64(b)) => a.to_bits().cmp(&b.to_bits()),
+            (Decimal128(a), Decimal128(b)) => a.to_bits().cmp(&b.to_bits()),
+            (Char(a), Char(b)) => a.cmp(b),
+            (Timestamp(a), Timestamp(b)) => a.cmp(b),
+            (Uuid(a), Uuid(b)) => a.cmp(b),
+            (String(a), String(b)) => a.cmp(b),
+            (Binary(a), Binary(b)) => a.cmp(b),
+            (Symbol(a), Symbol(b)) => a.cmp(b),
+            (List(a), List(b)) => a.cmp(b),
+            (Map(a), Map(b)) => a.cmp(b),
+            (Array(a), Array(b)) => a.cmp(b),
+            (Described(da, a), Described(db, b)) => da.cmp(db).then_with(|| a.cmp(b)),
+            (a, b) => a.rank().cmp(&b.rank()),
+        }
+    }
+}
+
+impl PartialOrd for OwnedValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for OwnedValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OwnedValue {}
+
+impl Hash for OwnedValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            OwnedValue::Null => {}
+            OwnedValue::Boolean(v) => v.hash(state),
+            OwnedValue::UByte(v) => v.hash(state),
+            OwnedValue::UShort(v) => v.hash(state),
+            OwnedValue::UInt(v) => v.hash(state),
+            OwnedValue::ULong(v) => v.hash(state),
+            OwnedValue::Byte(v) => v.hash(state),
+            OwnedValue::Short(v) => v.hash(state),
+            OwnedValue::Int(v) => v.hash(state),
+            OwnedValue::Long(v) => v.hash(state),
+            OwnedValue::Float(v) => v.to_bits().hash(state),
+            OwnedValue::Double(v) => v.to_bits().hash(state),
+            OwnedValue::Decimal32(v) => v.hash(state),
+            OwnedValue::Decimal64(v) => v.hash(state),
+            OwnedValue::Decimal128(v) => v.hash(state),
+            OwnedValue::Char(v) => v.hash(state),
+            OwnedValue::Timestamp(v) => v.hash(state),
+            OwnedValue::Uuid(v) => v.hash(state),
+            OwnedValue::String(v) => v.hash(state),
+            OwnedValue::Binary(v) => v.hash(state),
+            OwnedValue::Symbol(v) => v.hash(state),
+            OwnedValue::List(v) => v.hash(state),
+            OwnedValue::Map(v) => v.hash(state),
+            OwnedValue::Array(v) => v.hash(state),
+            OwnedValue::Described(d, v) => {
+                d.hash(state);
+                v.hash(state);
+            }
+        }
+    }
+}
+
+impl Encode for OwnedValue {
+    /// not used, the format code depends on the value, see [`OwnedValue::format_code`]
+    const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::NULL;
+
+    fn is_null(&self) -> bool {
+        OwnedValue::is_null(self)
+    }
+
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
+        match self {
+            OwnedValue::Null => ().encode_data(format_code, writer),
+            OwnedValue::Boolean(v) => v.encode_data(format_code, writer),
+            OwnedValue::UByte(v) => v.encode_data(format_code, writer),
+            OwnedValue::UShort(v) => v.encode_data(format_code, writer),
+            OwnedValue::UInt(v) => v.encode_data(format_code, writer),
+            OwnedValue::ULong(v) => v.encode_data(format_code, writer),
+            OwnedValue::Byte(v) => v.encode_data(format_code, writer),
+            OwnedValue::Short(v) => v.encode_data(format_code, writer),
+            OwnedValue::Int(v) => v.encode_data(format_code, writer),
+            OwnedValue::Long(v) => v.encode_data(format_code, writer),
+            OwnedValue::Float(v) => v.encode_data(format_code, writer),
+            OwnedValue::Double(v) => v.encode_data(format_code, writer),
+            OwnedValue::Decimal32(v) => v.encode_data(format_code, writer),
+            OwnedValue::Decimal64(v) => v.encode_data(format_code, writer),
+            OwnedValue::Decimal128(v) => v.encode_data(format_code, writer),
+            OwnedValue::Char(v) => v.encode_data(format_code, writer),
+            OwnedValue::Timestamp(v) => v.encode_data(format_code, writer),
+            OwnedValue::Uuid(v) => v.encode_data(format_code, writer),
+            OwnedValue::String(v) => v.as_str().encode_data(format_code, writer),
+            OwnedValue::Binary(v) => Binary(&v).encode_data(format_code, writer),
+            OwnedValue::Symbol(v) => v.as_symbol().encode_data(format_code, writer),
+            OwnedValue::List(items) => {
+                let fwrite = move |writer: &mut W| {
+                    let count = items.len();
+                    for item in items {
+                        writer.write_amqp_value(item)?;
+                    }
+                    io::Result::Ok(count)
+                };
+                match format_code {
+                    FormatCode::LIST0 => Ok(()),
+                    FormatCode::LIST8 => writer.write_items_8(fwrite),
+                    FormatCode::LIST32 => writer.write_items_32(fwrite),
+                    code => panic!("invalid format code {code:?}"),
+                }
+            }
+            OwnedValue::Map(entries) => {
+                let fwrite = move |writer: &mut W| {
+                    let count = entries.len() * 2;
+                    for (k, v) in entries {
+                        writer.write_amqp_value(k)?;
+                        writer.write_amqp_value(v)?;
+                    }
+                    io::Result::Ok(count)
+                };
+                match format_code {
+                    FormatCode::MAP8 => writer.write_items_8(fwrite),
+                    FormatCode::MAP32 => writer.write_items_32(fwrite),
+                    code => panic!("invalid format code {code:?}"),
+                }
+            }
+            OwnedValue::Array(items) => {
+                let fwrite = move |writer: &mut W| {
+                    let count = items.len();
+                    let Some(first) = items.first() else {
+                        writer.write_format_code(FormatCode::NULL)?;
+                        return Ok(0);
+                    };
+                    let (descriptor, item_format_code) = (first.descriptor(), first.format_code());
+                    if items.iter().any(|item| {
+                        item.descriptor() != descriptor || item.format_code() != item_format_code
+                    }) {
+                        return Err(io::Error::other(HETEROGENEOUS_ARRAY));
+                    }
+                    writer.write_constructor(first.constructor())?;
+                    for item in items {
+                        item.encode_data(item_format_code, writer)?;
+                    }
+                    io::Result::Ok(count)
+                };
+                match format_code {
+                    FormatCode::ARRAY8 => writer.write_items_8(fwrite),
+                    FormatCode::ARRAY32 => writer.write_items_32(fwrite),
+                    code => panic!("invalid format code {code:?}"),
+                }
+            }
+            OwnedValue::Described(_, value) => value.encode_data(format_code, writer),
+        }
+    }
+
+    fn encode<W: Sink>(self, _constructor: Constructor, writer: &mut W) -> io::Result<()> {
+        self.encode_default(writer)
+    }
+
+    fn encode_default<W: Sink>(self, writer: &mut W) -> io::Result<()> {
+        let format_code = self.format_code();
+        writer.write_constructor(self.constructor())?;
+        self.encode_data(format_code, writer)
+    }
+
+    fn items_size(&self, mode: EncodeMode) -> Option<(usize, usize)> {
+        match self {
+            OwnedValue::List(items) => Some((
+                items.len(),
+                items.iter().map(|item| item.encoded_size(mode)).sum(),
+            )),
+            OwnedValue::Map(entries) => Some((
+                entries.len() * 2,
+                entries
+                    .iter()
+                    .map(|(k, v)| k.encoded_size(mode) + v.encoded_size(mode))
+                    .sum(),
+            )),
+            OwnedValue::Array(items) => {
+                let size = match items.first() {
+                    Some(first) => {
+                        let format_code = first.format_code();
+                        first.constructor().encoded_size(mode)
+                            + items
+                                .iter()
+                                .map(|item| item.data_size(format_code, mode))
+                                .sum::<usize>()
+                    }
+                    // the null constructor of an empty array
+                    None => 1,
+                };
+                Some((items.len(), size))
+            }
+            OwnedValue::Described(_, value) => value.items_size(mode),
+            _ => None,
+        }
+    }
+
+    fn data_size(&self, format_code: FormatCode, mode: EncodeMode) -> usize {
+        match self {
+            OwnedValue::String(v) => v.as_str().data_size(format_code, mode),
+            OwnedValue::Binary(v) => Binary(v).data_size(format_code, mode),
+            OwnedValue::Symbol(v) => v.data_size(format_code, mode),
+            OwnedValue::Described(_, value) => value.data_size(format_code, mode),
+            value => match value.items_size(mode) {
+                Some((_, items_size)) => compound_header_size(format_code) + items_size,
+                None => format_code
+                    .fixed_width()
+                    .unwrap_or_else(|| panic!("no data size for format code {format_code:?}")),
+            },
+        }
+    }
+
+    fn encoded_size(&self, mode: EncodeMode) -> usize {
+        let (format_code, data_size) = match (self.items_size(mode), mode) {
+            (Some((count, items_size)), mode) => {
+                compound_size(self.format_code(), count, items_size, mode)
+            }
+            (None, EncodeMode::Default) => {
+                let format_code = self.format_code();
+                (format_code, self.data_size(format_code, mode))
+            }
+            (None, EncodeMode::Compact) => {
+                let format_code = self.compact_format_code();
+                (format_code, self.data_size(format_code, mode))
+            }
+        };
+        let constructor = Constructor {
+            descriptor: self.descriptor().map(OwnedDescriptor::as_descriptor),
+            format_code,
+        };
+        constructor.encoded_size(mode) + data_size
+    }
+
+    fn compact_format_code(&self) -> FormatCode {
+        match self {
+            OwnedValue::Boolean(v) => v.compact_format_code(),
+            OwnedValue::UInt(v) => v.compact_format_code(),
+            OwnedValue::ULong(v) => v.compact_format_code(),
+            OwnedValue::Int(v) => v.compact_format_code(),
+            OwnedValue::Long(v) => v.compact_format_code(),
+            OwnedValue::String(v) => v.as_str().compact_format_code(),
+            OwnedValue::Binary(v) => Binary(v).compact_format_code(),
+            OwnedValue::Symbol(v) => v.compact_format_code(),
+            OwnedValue::Described(_, value) => value.compact_format_code(),
+            value => match value.items_size(EncodeMode::Compact) {
+                Some((count, items_size)) => {
+                    compound_size(value.format_code(), count, items_size, EncodeMode::Compact).0
+                }
+                None => value.format_code(),
+            },
+        }
+    }
+
+    fn encode_compact<W: Sink>(self, writer: &mut W) -> io::Result<()> {
+        let format_code = self.compact_format_code();
+        writer.write_constructor(Constructor {
+            descriptor: self.descriptor().map(OwnedDescriptor::as_descriptor),
+            format_code,
+        })?;
+        self.encode_data(format_code, writer)
+    }
+}
+
+impl<'a> crate::types::Type<'a> for OwnedValue {
+    fn try_from_value(value: Value<'a>) -> Result<Self, io::Error> {
+        value.into_owned()
+    }
+}
+
+macro_rules! derive_from_and_try_into {
+    ($($id:ident: $Type: ty)*) => {
+        $(
+            impl From<$Type> for OwnedValue {
+                fn from(value: $Type) -> Self {
+                    OwnedValue::$id(value)
+                }
+            }
+            impl TryFrom<OwnedValue> for $Type {
+                type Error = io::Error;
+                fn try_from(value: OwnedValue) -> Result<Self, Self::Error> {
+                    match value {
+                        OwnedValue::$id(v) => Ok(v),
+                        _ => Err(io::Error::other(UNEXPECTED_TYPE)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+derive_from_and_try_into! {
+    Boolean: bool
+    UByte: u8
+    UShort: u16
+    UInt: u32
+    ULong: u64
+    Byte: i8
+    Short: i16
+    Int: i32
+    Long: i64
+    Float: f32
+    Double: f64
+    Decimal32: d32
+    Decimal64: d64
+    Decimal128: d128
+    Char: char
+    Timestamp: Ts
+    Uuid: Uuid
+    String: String
+    Binary: Bytes
+    Symbol: OwnedSymbol
+}
+
+impl From<&str> for OwnedValue {
+    fn from(value: &str) -> Self {
+        OwnedValue::String(value.to_owned())
+    }
+}
+
+impl<T> From<Option<T>> for OwnedValue
+where
+    OwnedValue: From<T>,
+{
+    fn from(value: Option<T>) -> Self {
+        value.map_or(OwnedValue::Null, OwnedValue::from)
+    }
+}
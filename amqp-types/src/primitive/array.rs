@@ -1,3 +1,4 @@
+use crate::codec::{DecodeExt, EncodeMode};
 use crate::error::UNEXPECTED_TYPE;
 use crate::types::{Multiple, Type};
 use crate::Data;
@@ -14,23 +15,13 @@ pub struct ArrayIter<'frame> {
     pub items_data: Data<'frame>,
 }
 
-fn try_take_n<'b>(bytes: &mut &'b [u8], size: usize) -> io::Result<&'b [u8]> {
-    if bytes.len() > size {
-        Err(io::Error::other("invalid amqp data: no enough bytes"))
-    } else {
-        let (take, rest) = bytes.split_at(size);
-        *bytes = rest;
-        Ok(take)
-    }
-}
-
 impl<'frame> ArrayIter<'frame> {
     #[inline]
     unsafe fn next_unchecked(&mut self) -> io::Result<Value<'frame>> {
         self.count -= 1;
         let data = self.items_data.deref_mut();
         let size = self.constructor.peek_size(data)?;
-        let item_data = try_take_n(data, size)?;
+        let item_data = data.try_eat(size)?;
         Ok(Value::new(self.constructor.clone(), item_data))
     }
 }
@@ -71,9 +62,9 @@ impl<'a, T> std::fmt::Debug for Array<'a, T> {
 }
 
 impl<'a, T: Multiple> Array<'a, T> {
-    pub fn new_write(iter: (impl IntoIterator<Item = T> + 'a)) -> Self {
+    pub fn new_write(iter: impl IntoIterator<Item = T>) -> Self {
         Self {
-            iter: ArrayInner::Iter(Box::new(iter.into_iter())),
+            iter: ArrayInner::Iter(iter.into_iter().collect::<Vec<_>>().into_iter()),
         }
     }
     pub fn new_read(iter: ArrayIter<'a>) -> Self {
@@ -93,7 +84,31 @@ impl<'a, T> From<ArrayIter<'a>> for Array<'a, T> {
 
 pub enum ArrayInner<'a, T> {
     Data(ArrayIter<'a>),
-    Iter(Box<(dyn Iterator<Item = T> + 'a)>),
+    Iter(std::vec::IntoIter<T>),
+}
+
+impl<'a, T: Type<'a>> Array<'a, T> {
+    /// Number of items left, and the size of their data encoded with `T`'s default format code.
+    ///
+    /// Items which fail to decode are not counted, encoding fails on them anyway.
+    pub(crate) fn items_size(&self, mode: EncodeMode) -> (usize, usize) {
+        let format_code = T::ENCODE_DEFAULT_FORMAT_CODE;
+        match &self.iter {
+            ArrayInner::Data(data) => data
+                .clone()
+                .filter_map(|item| item.and_then(T::try_from_value).ok())
+                .fold((0, 0), |(count, size), item| {
+                    (count + 1, size + item.data_size(format_code, mode))
+                }),
+            ArrayInner::Iter(iter) => (
+                iter.len(),
+                iter.as_slice()
+                    .iter()
+                    .map(|item| item.data_size(format_code, mode))
+                    .sum(),
+            ),
+        }
+    }
 }
 
 impl<'a, T: Type<'a>> Iterator for Array<'a, T> {
@@ -1,2 +1,16 @@
-#[derive(Debug, Clone)]
-pub struct Binary<'frame>(pub(crate) &'frame [u8]);
\ No newline at end of file
+use bytes::Bytes;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Binary<'frame>(pub(crate) &'frame [u8]);
+
+impl<'frame> Binary<'frame> {
+    pub const fn new(bytes: &'frame [u8]) -> Self {
+        Binary(bytes)
+    }
+    pub const fn as_bytes(&self) -> &'frame [u8] {
+        self.0
+    }
+    pub fn into_owned(self) -> Bytes {
+        Bytes::copy_from_slice(self.0)
+    }
+}
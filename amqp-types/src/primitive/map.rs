@@ -1,6 +1,6 @@
 use std::io;
 
-use crate::{error::UNEXPECTED_TYPE, types::Type, value::Value};
+use crate::{codec::EncodeMode, error::UNEXPECTED_TYPE, types::Type, value::Value};
 
 use super::{ListIter, Primitive};
 #[derive(Debug, Clone)]
@@ -45,13 +45,13 @@ impl<'a, K, V> From<MapIter<'a>> for Map<'a, K, V> {
 
 pub enum MapInner<'a, K, V> {
     Read(MapIter<'a>),
-    Write(&'a mut dyn Iterator<Item = (K, V)>),
+    Write(std::vec::IntoIter<(K, V)>),
 }
 
 impl<'a, K: Type<'a>, V: Type<'a>> Map<'a, K, V> {
-    pub fn new_write(iter: &'a mut impl Iterator<Item = (K, V)>) -> Self {
+    pub fn new_write(iter: impl IntoIterator<Item = (K, V)>) -> Self {
         Self {
-            inner: MapInner::Write(iter as &mut dyn Iterator<Item = (K, V)>),
+            inner: MapInner::Write(iter.into_iter().collect::<Vec<_>>().into_iter()),
         }
     }
     pub fn new_read(iter: MapIter<'a>) -> Self {
@@ -59,6 +59,20 @@ impl<'a, K: Type<'a>, V: Type<'a>> Map<'a, K, V> {
             inner: MapInner::Read(iter),
         }
     }
+    /// Number of keys and values left, and the size they take once encoded in `mode`.
+    ///
+    /// Entries which fail to decode are not counted, encoding fails on them anyway.
+    pub(crate) fn items_size(&self, mode: EncodeMode) -> (usize, usize) {
+        let entry_size = |(k, v): &(K, V)| k.encoded_size(mode) + v.encoded_size(mode);
+        match &self.inner {
+            MapInner::Read(iter) => Map::<K, V>::from(iter.clone())
+                .filter_map(Result::ok)
+                .fold((0, 0), |(count, size), entry| {
+                    (count + 2, size + entry_size(&entry))
+                }),
+            MapInner::Write(iter) => (iter.len() * 2, iter.as_slice().iter().map(entry_size).sum()),
+        }
+    }
 }
 
 impl<'a, K: Type<'a>, V: Type<'a>> Iterator for Map<'a, K, V> {
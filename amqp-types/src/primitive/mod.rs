@@ -1,5 +1,6 @@
 pub use array::*;
 pub use binary::*;
+pub use decimal::{d128, d32, d64};
 pub use list::*;
 pub use map::*;
 pub use symbol::*;
@@ -28,9 +29,9 @@ pub enum Primitive<'frame> {
     Long(i64),
     Float(f32),
     Double(f64),
-    Decimal32(),
-    Decimal64(),
-    Decimal128(),
+    Decimal32(d32),
+    Decimal64(d64),
+    Decimal128(d128),
     Char(char),
     Timestamp(Ts),
     Uuid(Uuid),
@@ -112,6 +113,24 @@ impl<'a> Primitive<'a> {
             _ => None,
         }
     }
+    pub fn as_d32(&self) -> Option<d32> {
+        match self {
+            Primitive::Decimal32(v) => Some(*v),
+            _ => None,
+        }
+    }
+    pub fn as_d64(&self) -> Option<d64> {
+        match self {
+            Primitive::Decimal64(v) => Some(*v),
+            _ => None,
+        }
+    }
+    pub fn as_d128(&self) -> Option<d128> {
+        match self {
+            Primitive::Decimal128(v) => Some(*v),
+            _ => None,
+        }
+    }
     pub fn as_char(&self) -> Option<char> {
         match self {
             Primitive::Char(v) => Some(*v),
@@ -221,9 +240,9 @@ derive_from_and_try_into! {
     Float: f32
     Double: f64
     Timestamp: Ts
-    // Decimal32: ()
-    // Decimal64: ()
-    // Decimal128: ()
+    Decimal32: d32
+    Decimal64: d64
+    Decimal128: d128
     Char: char
     Uuid: Uuid
 
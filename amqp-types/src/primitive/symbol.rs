@@ -1,8 +1,50 @@
-#[derive(Debug, Clone, PartialEq, Eq)]
+use bytes::Bytes;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Symbol<'frame>(pub(crate) &'frame [u8]);
 
 impl<'frame> Symbol<'frame> {
     pub const fn new(bytes: &'frame [u8]) -> Self {
         Symbol(bytes)
     }
+    pub const fn as_bytes(&self) -> &'frame [u8] {
+        self.0
+    }
+    pub fn into_owned(self) -> OwnedSymbol {
+        OwnedSymbol(Bytes::copy_from_slice(self.0))
+    }
+}
+
+/// An owned symbol, which can outlive the frame it was decoded from.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OwnedSymbol(pub(crate) Bytes);
+
+impl OwnedSymbol {
+    pub const fn from_static(bytes: &'static [u8]) -> Self {
+        OwnedSymbol(Bytes::from_static(bytes))
+    }
+    pub fn as_symbol(&self) -> Symbol<'_> {
+        Symbol(&self.0)
+    }
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Bytes> for OwnedSymbol {
+    fn from(value: Bytes) -> Self {
+        OwnedSymbol(value)
+    }
+}
+
+impl From<&str> for OwnedSymbol {
+    fn from(value: &str) -> Self {
+        OwnedSymbol(Bytes::copy_from_slice(value.as_bytes()))
+    }
+}
+
+impl From<Symbol<'_>> for OwnedSymbol {
+    fn from(value: Symbol<'_>) -> Self {
+        value.into_owned()
+    }
 }
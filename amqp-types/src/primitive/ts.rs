@@ -1,28 +1,103 @@
-use std::time::SystemTime;
+use std::{
+    io,
+    time::{Duration, SystemTime},
+};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct Ts(pub(crate) u64);
+use crate::error::TIMESTAMP_OUT_OF_RANGE;
+
+/// An absolute point in time, as milliseconds since the unix epoch.
+///
+/// Negative values are points in time before 1970-01-01T00:00:00Z.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ts(pub(crate) i64);
 
 impl Ts {
-    pub fn into_system_time(self) -> SystemTime {
-        self.into()
+    pub const UNIX_EPOCH: Self = Ts(0);
+    pub const fn from_millis(millis: i64) -> Self {
+        Ts(millis)
+    }
+    pub const fn as_millis(self) -> i64 {
+        self.0
+    }
+    pub fn now() -> Self {
+        SystemTime::now().into()
+    }
+    pub fn into_system_time(self) -> io::Result<SystemTime> {
+        self.try_into()
+    }
+}
+
+/// Fails for times the platform can't represent.
+impl TryFrom<Ts> for SystemTime {
+    type Error = io::Error;
+    fn try_from(value: Ts) -> Result<Self, Self::Error> {
+        let offset = Duration::from_millis(value.0.unsigned_abs());
+        if value.0 >= 0 {
+            SystemTime::UNIX_EPOCH.checked_add(offset)
+        } else {
+            SystemTime::UNIX_EPOCH.checked_sub(offset)
+        }
+        .ok_or(io::Error::other(TIMESTAMP_OUT_OF_RANGE))
     }
 }
 
-impl From<Ts> for SystemTime {
-    fn from(val: Ts) -> Self {
-        std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_millis(val.0)
+/// Sub-millisecond precision is truncated towards the past, times beyond the range of `i64`
+/// milliseconds saturate.
+impl From<SystemTime> for Ts {
+    fn from(value: SystemTime) -> Self {
+        let millis = match value.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(after) => i64::try_from(after.as_millis()).unwrap_or(i64::MAX),
+            Err(before) => {
+                let before = before.duration();
+                let millis = before.as_millis() + (before.subsec_nanos() % 1_000_000 != 0) as u128;
+                i64::try_from(millis).map_or(i64::MIN, |millis| -millis)
+            }
+        };
+        Ts(millis)
     }
 }
 
-impl From<u64> for Ts {
-    fn from(value: u64) -> Self {
+impl From<i64> for Ts {
+    fn from(value: i64) -> Self {
         Self(value)
     }
 }
 
-impl From<Ts> for u64 {
+impl From<Ts> for i64 {
     fn from(value: Ts) -> Self {
         value.0
     }
 }
+
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> From<chrono::DateTime<Tz>> for Ts {
+    fn from(value: chrono::DateTime<Tz>) -> Self {
+        Ts(value.timestamp_millis())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<Ts> for chrono::DateTime<chrono::Utc> {
+    type Error = io::Error;
+    fn try_from(value: Ts) -> Result<Self, Self::Error> {
+        chrono::DateTime::from_timestamp_millis(value.0)
+            .ok_or(io::Error::other(TIMESTAMP_OUT_OF_RANGE))
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for Ts {
+    fn from(value: time::OffsetDateTime) -> Self {
+        let millis = value.unix_timestamp_nanos().div_euclid(1_000_000);
+        Ts(millis as i64)
+    }
+}
+
+#[cfg(feature = "time")]
+impl TryFrom<Ts> for time::OffsetDateTime {
+    type Error = io::Error;
+    fn try_from(value: Ts) -> Result<Self, Self::Error> {
+        time::OffsetDateTime::from_unix_timestamp_nanos(value.0 as i128 * 1_000_000)
+            .map_err(|_| io::Error::other(TIMESTAMP_OUT_OF_RANGE))
+    }
+}
@@ -1,11 +1,14 @@
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Uuid(pub(crate) [u8; 16]);
 
-
-impl From<[u8; 16]> for Uuid 
-{
+impl From<[u8; 16]> for Uuid {
     fn from(value: [u8; 16]) -> Self {
         Uuid(value)
     }
-}
\ No newline at end of file
+}
+
+impl Uuid {
+    pub const fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+}
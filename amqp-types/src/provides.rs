@@ -0,0 +1,259 @@
+//! Archetypes, the `requires` and `provides` of the specification.
+//!
+//! A field may require an archetype, like the `source` of an attach, rather than a type. Any
+//! described type providing the archetype may fill it, including ones defined by extensions, so
+//! the set is open: the types providing an archetype are [`register`]ed at runtime and a
+//! [`Requires`] field keeps the value it received, the provider its descriptor belongs to being
+//! looked up when asked for.
+use std::{fmt, io, marker::PhantomData, sync::RwLock};
+
+use crate::{
+    codec::{BufWriter, Decode, Encode, EncodeMode, Sink},
+    error::UNEXPECTED_TYPE,
+    types::{Described, Restrict, Type},
+    Constructor, Descriptor, DescriptorId, FormatCode, Value,
+};
+
+/// Names of the archetypes of the specification.
+pub mod archetype {
+    pub const ADDRESS: &str = "address";
+    pub const DELIVERY_STATE: &str = "delivery-state";
+    pub const OUTCOME: &str = "outcome";
+    pub const SOURCE: &str = "source";
+    pub const TARGET: &str = "target";
+    pub const ERROR_CONDITION: &str = "error-condition";
+    pub const DISTRIBUTION_MODE: &str = "distribution-mode";
+    pub const MESSAGE_ID: &str = "message-id";
+    pub const GLOBAL_TX_ID: &str = "global-tx-id";
+    pub const TXN_CAPABILITY: &str = "txn-capability";
+}
+
+/// The Rust type standing for an archetype, used as the `R` of a [`Requires<R>`] field.
+pub trait Require {
+    /// The name of the archetype, see [`archetype`].
+    const ARCHETYPE: &'static str;
+}
+
+/// A described type which provides archetypes.
+///
+/// The composites derived with `#[amqp(provides = "delivery-state, outcome")]` implement it,
+/// along with [`Provide`] for each archetype listed, `DeliveryState` and `Outcome` being the
+/// [`Require`] types in scope. `#[amqp(provides(DeliveryState, Outcome))]` names them instead.
+pub trait Provides: Described {
+    /// The names of the archetypes provided, see [`archetype`].
+    const PROVIDES: &'static [&'static str];
+}
+
+/// `Self` may be read out of a field requiring `R`, see [`Requires::get`].
+pub trait Provide<R: Require> {}
+
+/// A type registered as providing an archetype.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Provider {
+    pub archetype: &'static str,
+    pub descriptor: DescriptorId,
+    pub type_name: &'static str,
+}
+
+/// The types providing archetypes, to look up the descriptors of received values in.
+#[derive(Debug, Default)]
+pub struct Registry {
+    providers: RwLock<Vec<Provider>>,
+}
+
+impl Registry {
+    pub const fn new() -> Self {
+        Self {
+            providers: RwLock::new(Vec::new()),
+        }
+    }
+    /// Register `P` for every archetype it provides, registering a type twice has no effect.
+    pub fn register<P: Provides>(&self) {
+        let mut providers = self.providers.write().unwrap_or_else(|e| e.into_inner());
+        for &archetype in P::PROVIDES {
+            let provider = Provider {
+                archetype,
+                descriptor: P::DESCRIPTOR_ID,
+                type_name: std::any::type_name::<P>(),
+            };
+            if !providers.contains(&provider) {
+                providers.push(provider);
+            }
+        }
+    }
+    /// The types registered for `archetype`.
+    pub fn providers(&self, archetype: &str) -> Vec<Provider> {
+        let providers = self.providers.read().unwrap_or_else(|e| e.into_inner());
+        providers
+            .iter()
+            .filter(|provider| provider.archetype == archetype)
+            .copied()
+            .collect()
+    }
+    /// The type registered for `archetype` which `descriptor` identifies.
+    pub fn lookup(&self, archetype: &str, descriptor: &Descriptor) -> Option<Provider> {
+        let providers = self.providers.read().unwrap_or_else(|e| e.into_inner());
+        providers
+            .iter()
+            .find(|provider| {
+                provider.archetype == archetype && provider.descriptor.matches(descriptor)
+            })
+            .copied()
+    }
+}
+
+static REGISTRY: Registry = Registry::new();
+
+/// Register `P` in the registry of the process, see [`Registry::register`].
+pub fn register<P: Provides>() {
+    REGISTRY.register::<P>()
+}
+
+/// The types registered for `archetype` in the registry of the process.
+pub fn providers(archetype: &str) -> Vec<Provider> {
+    REGISTRY.providers(archetype)
+}
+
+/// The type registered for `archetype` in the registry of the process which `descriptor`
+/// identifies.
+pub fn lookup(archetype: &str, descriptor: &Descriptor) -> Option<Provider> {
+    REGISTRY.lookup(archetype, descriptor)
+}
+
+/// A value of the archetype `R`.
+///
+/// The value is kept as it was received, unknown descriptors included, read it as one of the
+/// types providing `R` with [`Requires::get`]. Values which aren't described, like the symbols
+/// of `error-condition`, have no provider but may be read all the same.
+pub struct Requires<'a, R> {
+    value: Held<'a>,
+    archetype: PhantomData<R>,
+}
+
+#[derive(Clone)]
+enum Held<'a> {
+    Received(Value<'a>),
+    /// the encoding of a value to send, which decodes as it was checked to when written
+    Written(Vec<u8>),
+}
+
+impl<'a, R: Require> Requires<'a, R> {
+    pub fn new(value: Value<'a>) -> Self {
+        Self {
+            value: Held::Received(value),
+            archetype: PhantomData,
+        }
+    }
+    /// Write `provider`, to send it as a value of `R`.
+    pub fn write<P: Provide<R> + Encode>(provider: P) -> io::Result<Self> {
+        let mut writer = BufWriter::new(Vec::new());
+        writer.write_amqp_value(provider)?;
+        let buffer = writer.into_inner();
+        Value::decode(&mut buffer.as_slice())?;
+        Ok(Self {
+            value: Held::Written(buffer),
+            archetype: PhantomData,
+        })
+    }
+    /// The type registered in the registry of the process the descriptor of the value belongs
+    /// to, `None` if it's unknown.
+    pub fn provider(&self) -> Option<Provider> {
+        self.provider_in(&REGISTRY)
+    }
+    /// The type registered in `registry` the descriptor of the value belongs to.
+    pub fn provider_in(&self, registry: &Registry) -> Option<Provider> {
+        self.descriptor()
+            .and_then(|descriptor| registry.lookup(R::ARCHETYPE, &descriptor))
+    }
+    pub fn descriptor(&self) -> Option<Descriptor<'_>> {
+        self.value().constructor.descriptor
+    }
+    /// Whether the value is described as a `P`.
+    pub fn is<P: Described>(&self) -> bool {
+        self.descriptor()
+            .is_some_and(|descriptor| P::DESCRIPTOR_ID.matches(&descriptor))
+    }
+    /// Read the value as `P`, a derived `P` fails with a
+    /// [`DescriptorMismatch`](crate::error::DescriptorMismatch) if it's another type.
+    pub fn get<'b, P: Provide<R> + Type<'b>>(&'b self) -> io::Result<P> {
+        P::try_from_value(self.value())
+    }
+    pub fn value(&self) -> Value<'_> {
+        match &self.value {
+            Held::Received(value) => value.clone(),
+            Held::Written(buffer) => {
+                Value::decode(&mut buffer.as_slice()).expect("decoded when written")
+            }
+        }
+    }
+}
+
+impl<R> Clone for Requires<'_, R> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            archetype: PhantomData,
+        }
+    }
+}
+
+impl<R: Require> fmt::Debug for Requires<'_, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Requires")
+            .field("archetype", &R::ARCHETYPE)
+            .field("provider", &self.provider().map(|p| p.type_name))
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl<R> Restrict for Requires<'_, R> {
+    type Source = Self;
+    fn restrict(source: Self::Source) -> Result<Self, Self::Source> {
+        Ok(source)
+    }
+    fn source(self) -> Self::Source {
+        self
+    }
+}
+
+/// Written as the value it holds.
+impl<R: Require> Encode for Requires<'_, R> {
+    /// not used, the constructor is the one of the value held
+    const ENCODE_DEFAULT_FORMAT_CODE: FormatCode = FormatCode::NULL;
+    /// The data of the value held, which only follows the constructor it came with.
+    fn encode_data<W: Sink>(self, format_code: FormatCode, writer: &mut W) -> io::Result<()> {
+        let value = self.value();
+        if value.constructor.format_code != format_code {
+            return Err(io::Error::other(UNEXPECTED_TYPE));
+        }
+        writer.write_slice(value.data.into_inner())
+    }
+    fn encode<W: Sink>(self, constructor: Constructor, writer: &mut W) -> io::Result<()> {
+        self.value().encode(constructor, writer)
+    }
+    fn encode_default<W: Sink>(self, writer: &mut W) -> io::Result<()> {
+        self.value().encode_default(writer)
+    }
+    fn is_null(&self) -> bool {
+        self.value().is_null()
+    }
+    fn compact_format_code(&self) -> FormatCode {
+        self.value().constructor.format_code
+    }
+    fn encode_compact<W: Sink>(self, writer: &mut W) -> io::Result<()> {
+        self.value().encode_compact(writer)
+    }
+    fn data_size(&self, format_code: FormatCode, mode: EncodeMode) -> usize {
+        self.value().data_size(format_code, mode)
+    }
+    fn encoded_size(&self, mode: EncodeMode) -> usize {
+        self.value().encoded_size(mode)
+    }
+}
+
+impl<'a, R: Require> Type<'a> for Requires<'a, R> {
+    fn try_from_value(value: Value<'a>) -> Result<Self, io::Error> {
+        Ok(Self::new(value))
+    }
+}
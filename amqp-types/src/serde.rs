@@ -0,0 +1,62 @@
+//! [serde](::serde) support, drive the AMQP codec with `Serialize` and `Deserialize` types.
+//!
+//! The data model is mapped onto AMQP types as follows:
+//! - structs, maps and struct variants are encoded as maps, with the field names as string keys
+//! - sequences, tuples and tuple structs are encoded as lists
+//! - unit variants are encoded as symbols, other variants as a single entry map from the variant
+//!   name to the content
+//! - `None` and unit are encoded as null, newtypes as their inner value
+//!
+//! Structs can also be decoded from lists, with the fields in order, as described composites are.
+use std::{fmt, io};
+
+mod de;
+mod ser;
+
+pub use de::from_slice;
+pub use ser::{to_writer, Serializer};
+
+#[derive(Debug)]
+pub struct Error(io::Error);
+
+impl Error {
+    pub fn into_inner(self) -> io::Error {
+        self.0
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+impl ::serde::ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(io::Error::other(msg.to_string()))
+    }
+}
+
+impl ::serde::de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(io::Error::new(io::ErrorKind::InvalidData, msg.to_string()))
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error(err)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        err.0
+    }
+}
@@ -0,0 +1,211 @@
+use std::io;
+
+use ::serde::de::{self, Deserialize, DeserializeSeed, IntoDeserializer, Visitor};
+
+use super::Error;
+use crate::{
+    codec::Decode,
+    error::UNEXPECTED_TYPE,
+    format_code::FormatCode,
+    primitive::{MapIter, Primitive, Symbol},
+    value::Value,
+};
+
+/// Deserialize one AMQP value from the front of `slice`, strings and binaries are borrowed from it.
+pub fn from_slice<'de, T: Deserialize<'de>>(mut slice: &'de [u8]) -> io::Result<T> {
+    let value = Value::decode(&mut slice)?;
+    T::deserialize(value).map_err(Into::into)
+}
+
+fn symbol_str(symbol: Symbol<'_>) -> Result<&str, Error> {
+    std::str::from_utf8(symbol.as_bytes())
+        .map_err(|e| Error(io::Error::new(io::ErrorKind::InvalidData, e)))
+}
+
+impl<'de> de::Deserializer<'de> for Value<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.construct()? {
+            Primitive::Null => visitor.visit_unit(),
+            Primitive::Boolean(v) => visitor.visit_bool(v),
+            Primitive::UByte(v) => visitor.visit_u8(v),
+            Primitive::UShort(v) => visitor.visit_u16(v),
+            Primitive::UInt(v) => visitor.visit_u32(v),
+            Primitive::ULong(v) => visitor.visit_u64(v),
+            Primitive::Byte(v) => visitor.visit_i8(v),
+            Primitive::Short(v) => visitor.visit_i16(v),
+            Primitive::Int(v) => visitor.visit_i32(v),
+            Primitive::Long(v) => visitor.visit_i64(v),
+            Primitive::Float(v) => visitor.visit_f32(v),
+            Primitive::Double(v) => visitor.visit_f64(v),
+            Primitive::Decimal32(v) => visitor.visit_string(v.to_string()),
+            Primitive::Decimal64(v) => visitor.visit_string(v.to_string()),
+            Primitive::Decimal128(v) => visitor.visit_string(v.to_string()),
+            Primitive::Char(v) => visitor.visit_char(v),
+            Primitive::Timestamp(v) => visitor.visit_i64(v.as_millis()),
+            Primitive::Uuid(v) => visitor.visit_bytes(v.as_bytes()),
+            Primitive::String(v) => visitor.visit_borrowed_str(v),
+            Primitive::Binary(v) => visitor.visit_borrowed_bytes(v.as_bytes()),
+            Primitive::Symbol(v) => visitor.visit_borrowed_str(symbol_str(v)?),
+            Primitive::List(items) => visitor.visit_seq(SeqAccess(items)),
+            Primitive::Array(items) => visitor.visit_seq(SeqAccess(items)),
+            Primitive::Map(entries) => visitor.visit_map(MapAccess {
+                entries,
+                value: None,
+            }),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // only a plain null, a described one is a value of its descriptor
+        if self.constructor.format_code == FormatCode::NULL && self.constructor.descriptor.is_none()
+        {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.clone().construct()? {
+            Primitive::String(_) | Primitive::Symbol(_) => visitor.visit_enum(EnumAccess {
+                variant: self,
+                content: None,
+            }),
+            Primitive::Map(mut entries) => {
+                let (variant, content) = entries
+                    .next()
+                    .transpose()?
+                    .ok_or(Error(io::Error::other(UNEXPECTED_TYPE)))?;
+                if entries.next().is_some() {
+                    return Err(de::Error::invalid_length(2, &"a single entry map"));
+                }
+                visitor.visit_enum(EnumAccess {
+                    variant,
+                    content: Some(content),
+                })
+            }
+            _ => Err(Error(io::Error::other(UNEXPECTED_TYPE))),
+        }
+    }
+
+    ::serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf unit
+        unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value<'de> {
+    type Deserializer = Self;
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+struct SeqAccess<I>(I);
+
+impl<'de, I: Iterator<Item = io::Result<Value<'de>>>> de::SeqAccess<'de> for SeqAccess<I> {
+    type Error = Error;
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.0.next() {
+            Some(item) => seed.deserialize(item?).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+struct MapAccess<'de> {
+    entries: MapIter<'de>,
+    value: Option<Value<'de>>,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'de> {
+    type Error = Error;
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.entries.next() {
+            Some(entry) => {
+                let (key, value) = entry?;
+                self.value = Some(value);
+                seed.deserialize(key).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let value = self.value.take().ok_or(Error(io::Error::other(
+            "map value requested before its key",
+        )))?;
+        seed.deserialize(value)
+    }
+}
+
+struct EnumAccess<'de> {
+    variant: Value<'de>,
+    content: Option<Value<'de>>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = Error;
+    type Variant = VariantAccess<'de>;
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Error> {
+        let variant = seed.deserialize(self.variant)?;
+        Ok((variant, VariantAccess(self.content)))
+    }
+}
+
+struct VariantAccess<'de>(Option<Value<'de>>);
+
+impl<'de> VariantAccess<'de> {
+    fn content(self) -> Result<Value<'de>, Error> {
+        self.0.ok_or(de::Error::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"variant content",
+        ))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'de> {
+    type Error = Error;
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.0 {
+            Some(content) => <()>::deserialize(content),
+            None => Ok(()),
+        }
+    }
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self.content()?)
+    }
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_any(self.content()?, visitor)
+    }
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_any(self.content()?, visitor)
+    }
+}
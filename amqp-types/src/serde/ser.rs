@@ -0,0 +1,310 @@
+use std::io;
+
+use ::serde::ser::{self, Serialize};
+
+use super::Error;
+use crate::{
+    codec::{EncodeMode, Sink},
+    format_code::FormatCode,
+    primitive::{Binary, Symbol},
+};
+
+/// Serialize `value` as one AMQP value into `writer`.
+pub fn to_writer<W: Sink, T: Serialize + ?Sized>(writer: &mut W, value: &T) -> io::Result<()> {
+    value
+        .serialize(&mut Serializer::new(writer))
+        .map_err(Into::into)
+}
+
+pub struct Serializer<'s, W> {
+    writer: &'s mut W,
+}
+
+impl<'s, W: Sink> Serializer<'s, W> {
+    pub fn new(writer: &'s mut W) -> Self {
+        Self { writer }
+    }
+    /// open a single entry map from the variant name to the variant content
+    fn begin_variant(&mut self, variant: &'static str) -> io::Result<usize> {
+        self.writer.write_format_code(FormatCode::MAP32)?;
+        let start = self.writer.begin_items_32()?;
+        self.writer
+            .write_amqp_value(Symbol::new(variant.as_bytes()))?;
+        Ok(start)
+    }
+    fn begin_compound(
+        &mut self,
+        format_code: FormatCode,
+        variant: Option<usize>,
+    ) -> io::Result<Compound<'_, 's, W>> {
+        self.writer.write_format_code(format_code)?;
+        let start = self.writer.begin_items_32()?;
+        Ok(Compound {
+            ser: self,
+            format_code,
+            start,
+            count: 0,
+            variant,
+        })
+    }
+    /// close a compound value opened with a 32-bit `format_code`
+    fn end_items(&mut self, format_code: FormatCode, start: usize, count: usize) {
+        self.writer.end_items_32(start, count);
+        if self.writer.mode() == EncodeMode::Compact {
+            self.writer.compact_items(format_code, start);
+        }
+    }
+}
+
+/// A list or a map being serialized, possibly as the content of an enum variant.
+pub struct Compound<'c, 's, W> {
+    ser: &'c mut Serializer<'s, W>,
+    format_code: FormatCode,
+    start: usize,
+    count: usize,
+    variant: Option<usize>,
+}
+
+impl<W: Sink> Compound<'_, '_, W> {
+    fn item<T: Serialize + ?Sized>(&mut self, item: &T) -> Result<(), Error> {
+        item.serialize(&mut *self.ser)?;
+        self.count += 1;
+        Ok(())
+    }
+    fn end(self) -> Result<(), Error> {
+        self.ser.end_items(self.format_code, self.start, self.count);
+        if let Some(variant) = self.variant {
+            self.ser.end_items(FormatCode::MAP32, variant, 2);
+        }
+        Ok(())
+    }
+}
+
+impl<'c, 's, W: Sink> ser::Serializer for &'c mut Serializer<'s, W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'c, 's, W>;
+    type SerializeTuple = Compound<'c, 's, W>;
+    type SerializeTupleStruct = Compound<'c, 's, W>;
+    type SerializeTupleVariant = Compound<'c, 's, W>;
+    type SerializeMap = Compound<'c, 's, W>;
+    type SerializeStruct = Compound<'c, 's, W>;
+    type SerializeStructVariant = Compound<'c, 's, W>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        Ok(self.writer.write_amqp_value(v)?)
+    }
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        Ok(self.writer.write_amqp_value(v)?)
+    }
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        Ok(self.writer.write_amqp_value(v)?)
+    }
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        Ok(self.writer.write_amqp_value(v)?)
+    }
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        Ok(self.writer.write_amqp_value(v)?)
+    }
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        Ok(self.writer.write_amqp_value(v)?)
+    }
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        Ok(self.writer.write_amqp_value(v)?)
+    }
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        Ok(self.writer.write_amqp_value(v)?)
+    }
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        Ok(self.writer.write_amqp_value(v)?)
+    }
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        Ok(self.writer.write_amqp_value(v)?)
+    }
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        Ok(self.writer.write_amqp_value(v)?)
+    }
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        Ok(self.writer.write_amqp_value(v)?)
+    }
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        Ok(self.writer.write_amqp_value(v)?)
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        Ok(self.writer.write_amqp_value(Binary::new(v))?)
+    }
+    fn serialize_none(self) -> Result<(), Error> {
+        Ok(self.writer.write_amqp_value(())?)
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), Error> {
+        Ok(self.writer.write_amqp_value(())?)
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        Ok(self
+            .writer
+            .write_amqp_value(Symbol::new(variant.as_bytes()))?)
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let start = self.begin_variant(variant)?;
+        value.serialize(&mut *self)?;
+        self.end_items(FormatCode::MAP32, start, 2);
+        Ok(())
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Ok(self.begin_compound(FormatCode::LIST32, None)?)
+    }
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Error> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        let start = self.begin_variant(variant)?;
+        Ok(self.begin_compound(FormatCode::LIST32, Some(start))?)
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Ok(self.begin_compound(FormatCode::MAP32, None)?)
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        self.serialize_map(Some(len))
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        let start = self.begin_variant(variant)?;
+        Ok(self.begin_compound(FormatCode::MAP32, Some(start))?)
+    }
+}
+
+impl<W: Sink> ser::SerializeSeq for Compound<'_, '_, W> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.item(value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self)
+    }
+}
+
+impl<W: Sink> ser::SerializeTuple for Compound<'_, '_, W> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.item(value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self)
+    }
+}
+
+impl<W: Sink> ser::SerializeTupleStruct for Compound<'_, '_, W> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.item(value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self)
+    }
+}
+
+impl<W: Sink> ser::SerializeTupleVariant for Compound<'_, '_, W> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.item(value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self)
+    }
+}
+
+impl<W: Sink> ser::SerializeMap for Compound<'_, '_, W> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.item(key)
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.item(value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self)
+    }
+}
+
+impl<W: Sink> ser::SerializeStruct for Compound<'_, '_, W> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.item(key)?;
+        self.item(value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self)
+    }
+}
+
+impl<W: Sink> ser::SerializeStructVariant for Compound<'_, '_, W> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.item(key)?;
+        self.item(value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self)
+    }
+}
@@ -1,15 +1,28 @@
 use crate::{codec::Encode, error, primitive::*, value::Value};
-use std::io;
+use std::{collections::HashMap, hash::Hash, io};
 
 mod restrict;
 pub use restrict::Restrict;
 mod multiple;
 pub use multiple::Multiple;
+mod described;
+pub use described::Described;
+mod composite;
+pub use composite::Composite;
 
 pub trait Type<'a>: Encode + Restrict {
     fn try_from_value(value: Value<'a>) -> Result<Self, io::Error>;
 }
 
+impl<'a> Type<'a> for bool {
+    fn try_from_value(value: Value<'a>) -> Result<Self, io::Error> {
+        value
+            .construct()?
+            .as_bool()
+            .ok_or(io::Error::other(error::UNEXPECTED_TYPE))
+    }
+}
+
 impl<'a> Type<'a> for i8 {
     fn try_from_value(value: Value<'a>) -> Result<Self, io::Error> {
         value
@@ -100,6 +113,33 @@ impl<'a> Type<'a> for f64 {
     }
 }
 
+impl<'a> Type<'a> for d32 {
+    fn try_from_value(value: Value<'a>) -> Result<Self, io::Error> {
+        value
+            .construct()?
+            .as_d32()
+            .ok_or(io::Error::other(error::UNEXPECTED_TYPE))
+    }
+}
+
+impl<'a> Type<'a> for d64 {
+    fn try_from_value(value: Value<'a>) -> Result<Self, io::Error> {
+        value
+            .construct()?
+            .as_d64()
+            .ok_or(io::Error::other(error::UNEXPECTED_TYPE))
+    }
+}
+
+impl<'a> Type<'a> for d128 {
+    fn try_from_value(value: Value<'a>) -> Result<Self, io::Error> {
+        value
+            .construct()?
+            .as_d128()
+            .ok_or(io::Error::other(error::UNEXPECTED_TYPE))
+    }
+}
+
 impl<'a> Type<'a> for char {
     fn try_from_value(value: Value<'a>) -> Result<Self, io::Error> {
         value
@@ -145,6 +185,12 @@ impl<'a> Type<'a> for Symbol<'a> {
     }
 }
 
+impl<'a> Type<'a> for OwnedSymbol {
+    fn try_from_value(value: Value<'a>) -> Result<Self, io::Error> {
+        Symbol::try_from_value(value).map(Symbol::into_owned)
+    }
+}
+
 impl<'a> Type<'a> for Binary<'a> {
     fn try_from_value(value: Value<'a>) -> Result<Self, io::Error> {
         value
@@ -174,6 +220,20 @@ impl<'a, K: Type<'a>, V: Type<'a>> Type<'a> for Map<'a, K, V> {
     }
 }
 
+impl<'a, K: Type<'a> + Eq + Hash, V: Type<'a>> Type<'a> for HashMap<K, V> {
+    fn try_from_value(value: Value<'a>) -> Result<Self, io::Error> {
+        let primitive = value.construct()?;
+        let map = primitive
+            .as_map()
+            .ok_or(io::Error::other(error::UNEXPECTED_TYPE))?;
+        map.map(|entry| {
+            let (k, v) = entry?;
+            Ok((K::try_from_value(k)?, V::try_from_value(v)?))
+        })
+        .collect()
+    }
+}
+
 impl<'a, T: Type<'a>> Type<'a> for Option<T> {
     fn try_from_value(value: Value<'a>) -> Result<Self, io::Error> {
         let primitive = value.clone().construct()?;
@@ -189,4 +249,4 @@ impl<'a> Type<'a> for Value<'a> {
     fn try_from_value(value: Value<'a>) -> Result<Self, io::Error> {
         Ok(value)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,8 @@
+/// A type encoded as a list of named fields.
+///
+/// The structs deriving `Type` implement it, a field may be given its name in the specification
+/// with `#[amqp(rename = "...")]`.
+pub trait Composite {
+    /// The names of the fields, in list order.
+    const FIELDS: &'static [&'static str];
+}
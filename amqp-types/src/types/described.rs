@@ -0,0 +1,8 @@
+use crate::descriptor::DescriptorId;
+
+/// A type encoded as a described value.
+///
+/// The composites derived with `#[amqp(descriptor = ...)]` implement it.
+pub trait Described {
+    const DESCRIPTOR_ID: DescriptorId;
+}
@@ -1,7 +1,75 @@
+use std::io;
+
+use crate::codec::{compound_size, Encode, EncodeMode, Sink};
 use crate::primitive::*;
+use crate::{Constructor, FormatCode, Value};
+
+use super::Type;
 
 /// Most AMQP types should be multiple except those types could be null.
-pub trait Multiple {}
+///
+/// A field declared `multiple="true"` holds zero or more values, sent as a null, as the value
+/// itself or as an array of them. The provided methods are how `#[amqp(multiple)]` fields of the
+/// derive, typed `Vec<T>`, are read and written.
+pub trait Multiple: Sized {
+    /// Decode any of the three encodings of a multiple field.
+    fn decode_multiple<'a>(value: Value<'a>) -> io::Result<Vec<Self>>
+    where
+        Self: Type<'a>,
+    {
+        if value.constructor.descriptor.is_none() {
+            match value.constructor.format_code {
+                FormatCode::NULL => return Ok(Vec::new()),
+                FormatCode::ARRAY8 | FormatCode::ARRAY32 => {
+                    return Array::<Self>::try_from(value)?.collect()
+                }
+                _ => {}
+            }
+        }
+        Ok(vec![Self::try_from_value(value)?])
+    }
+    /// Write `values` in their canonical form, null if there are none, the value itself if there
+    /// is one and an array otherwise.
+    fn encode_multiple<'a, W: Sink>(mut values: Vec<Self>, writer: &mut W) -> io::Result<()>
+    where
+        Self: Type<'a>,
+    {
+        match values.len() {
+            0 => writer.write_amqp_value(()),
+            1 => writer.write_amqp_value(values.pop().expect("one value")),
+            _ => writer.write_amqp_value(Array::new_write(values)),
+        }
+    }
+    /// Exact number of bytes [`Multiple::encode_multiple`] writes for `values` in `mode`.
+    fn multiple_encoded_size<'a>(values: &[Self], mode: EncodeMode) -> usize
+    where
+        Self: Type<'a>,
+    {
+        match values {
+            [] => ().encoded_size(mode),
+            [value] => value.encoded_size(mode),
+            values => {
+                let format_code = Self::ENCODE_DEFAULT_FORMAT_CODE;
+                let items_constructor = Constructor {
+                    descriptor: Self::DESCRIPTOR,
+                    format_code,
+                };
+                let items_size = items_constructor.encoded_size(mode)
+                    + values
+                        .iter()
+                        .map(|value| value.data_size(format_code, mode))
+                        .sum::<usize>();
+                let (format_code, data_size) =
+                    compound_size(FormatCode::ARRAY32, values.len(), items_size, mode);
+                let constructor = Constructor {
+                    descriptor: None,
+                    format_code,
+                };
+                constructor.encoded_size(mode) + data_size
+            }
+        }
+    }
+}
 
 macro_rules! multiple {
     {$($Type: ty)*} => {
@@ -10,6 +78,7 @@ macro_rules! multiple {
 }
 
 multiple! {
+    bool
     i8
     i16
     i32
@@ -20,13 +89,18 @@ multiple! {
     u64
     f32
     f64
+    d32
+    d64
+    d128
     char
     Uuid
     Ts
     Symbol<'_>
+    OwnedSymbol
     Binary<'_>
     &str
 }
 
 impl<'a, T> Multiple for Array<'a, T> {}
 impl<'a, K, V> Multiple for Map<'a, K, V> {}
+impl<K, V> Multiple for std::collections::HashMap<K, V> {}
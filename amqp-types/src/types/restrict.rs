@@ -1,4 +1,9 @@
-use crate::{primitive::{Array, Binary, Map, Symbol, Ts, Uuid}, Value};
+use std::collections::HashMap;
+
+use crate::{
+    primitive::{d128, d32, d64, Array, Binary, Map, OwnedSymbol, Symbol, Ts, Uuid},
+    OwnedValue, Value,
+};
 pub trait Restrict: Sized {
     type Source;
     fn restrict(source: Self::Source) -> Result<Self, Self::Source>;
@@ -24,6 +29,7 @@ macro_rules! no_restrict {
 }
 
 no_restrict! {
+    bool
     i8
     i16
     i32
@@ -34,13 +40,18 @@ no_restrict! {
     u64
     f32
     f64
+    d32
+    d64
+    d128
     char
     Uuid
     Ts
     Symbol<'_>
+    OwnedSymbol
     Binary<'_>
     &str
     Value<'_>
+    OwnedValue
 }
 
 impl<'a, T: Restrict> Restrict for Array<'a, T> {
@@ -63,6 +74,16 @@ impl<'a, K: Restrict, V: Restrict> Restrict for Map<'a, K, V> {
     }
 }
 
+impl<K: Restrict, V: Restrict> Restrict for HashMap<K, V> {
+    type Source = Self;
+    fn restrict(source: Self::Source) -> Result<Self, Self::Source> {
+        Ok(source)
+    }
+    fn source(self) -> Self::Source {
+        self
+    }
+}
+
 impl<T: Restrict> Restrict for Option<T> {
     type Source = Self;
     fn restrict(source: Self::Source) -> Result<Self, Self::Source> {
@@ -71,4 +92,4 @@ impl<T: Restrict> Restrict for Option<T> {
     fn source(self) -> Self::Source {
         self
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,80 @@
+use bytes::BytesMut;
+
+use amqp_types::{
+    codec::{BufWriter, Decode, EncodeMode, Sink, Writer},
+    primitive::Symbol,
+    types::Type,
+    OwnedValue, Value,
+};
+
+fn compact(value: impl amqp_types::codec::Encode) -> Vec<u8> {
+    let mut writer = BufWriter::new(Vec::new()).with_mode(EncodeMode::Compact);
+    writer.write_amqp_value(value).unwrap();
+    writer.into_inner()
+}
+
+#[test]
+fn compact_scalars() {
+    assert_eq!(compact(0u32), [0x43]);
+    assert_eq!(compact(7u32), [0x52, 0x07]);
+    assert_eq!(compact(0x1234u32), [0x70, 0x00, 0x00, 0x12, 0x34]);
+    assert_eq!(compact(0u64), [0x44]);
+    assert_eq!(compact(0x10u64), [0x53, 0x10]);
+    assert_eq!(compact(-1i32), [0x54, 0xff]);
+    assert_eq!(compact(-1i64), [0x55, 0xff]);
+    assert_eq!(compact(true), [0x41]);
+    assert_eq!(compact(false), [0x42]);
+    assert_eq!(compact("ab"), [0xa1, 0x02, b'a', b'b']);
+    assert_eq!(compact(Symbol::new(b"ab")), [0xa3, 0x02, b'a', b'b']);
+    assert_eq!(compact(Option::<u32>::None), [0x40]);
+    assert_eq!(compact(Some(1u32)), [0x52, 0x01]);
+    let long = "a".repeat(256);
+    assert_eq!(compact(long.as_str())[..5], [0xb1, 0x00, 0x00, 0x01, 0x00]);
+}
+
+#[test]
+fn compact_compounds() {
+    assert_eq!(compact(OwnedValue::List(vec![])), [0x45]);
+    // nested lists are narrowed before their parent is measured
+    let value = OwnedValue::List(vec![
+        OwnedValue::UInt(0),
+        OwnedValue::List(vec![OwnedValue::Boolean(true)]),
+    ]);
+    let bytes = compact(value.clone());
+    assert_eq!(bytes, [0xc0, 0x06, 0x02, 0x43, 0xc0, 0x02, 0x01, 0x41]);
+    let decoded = OwnedValue::try_from_value(Value::decode(&mut bytes.as_slice()).unwrap());
+    assert_eq!(decoded.unwrap(), value);
+
+    // items over 255 bytes keep the 32-bit form
+    let value = OwnedValue::List(vec![OwnedValue::String("a".repeat(300))]);
+    let bytes = compact(value.clone());
+    assert_eq!(
+        bytes[..9],
+        [0xd0, 0x00, 0x00, 0x01, 0x35, 0x00, 0x00, 0x00, 0x01]
+    );
+    let decoded = OwnedValue::try_from_value(Value::decode(&mut bytes.as_slice()).unwrap());
+    assert_eq!(decoded.unwrap(), value);
+}
+
+#[test]
+fn buf_writer_matches_writer() {
+    let value = OwnedValue::List(vec![
+        OwnedValue::Symbol("amqp:accepted:list".into()),
+        OwnedValue::Map(vec![(OwnedValue::from("key"), OwnedValue::Long(-3))]),
+        OwnedValue::String("x".repeat(300)),
+    ]);
+    for mode in [EncodeMode::Default, EncodeMode::Compact] {
+        let mut buffer = vec![0; 1024];
+        let mut writer = Writer::new(&mut buffer).with_mode(mode);
+        writer.write_amqp_value(value.clone()).unwrap();
+        let len = writer.position();
+
+        // the bytes already in the buffer are kept, and positions start after them
+        let mut bytes = BytesMut::from(&b"head"[..]);
+        let mut buf_writer = BufWriter::new(&mut bytes).with_mode(mode);
+        buf_writer.write_amqp_value(value.clone()).unwrap();
+        assert_eq!(buf_writer.position(), len);
+        assert_eq!(&bytes[..4], b"head");
+        assert_eq!(bytes[4..], buffer[..len]);
+    }
+}
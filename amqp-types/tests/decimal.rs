@@ -0,0 +1,31 @@
+use amqp_types::{
+    codec::{Decode, Sink, Writer},
+    primitive::{d128, d32, d64},
+    types::Type,
+    FormatCode, Value,
+};
+
+#[test]
+fn decimal_round_trip() {
+    let mut buffer = vec![0; 64];
+    let mut writer = Writer::new(&mut buffer);
+    let price = d32::from_parts(false, 12345, -2).unwrap();
+    let balance = d64::from_parts(true, 1_000_000_000_000, -6).unwrap();
+    let total = d128::from_parts(false, 1, 6000).unwrap();
+    writer.write_amqp_value(price).unwrap();
+    writer.write_amqp_value(balance).unwrap();
+    writer.write_amqp_value(total).unwrap();
+
+    let mut data = buffer.as_slice();
+    assert_eq!(&data[..5], &[0x74, 0x31, 0x80, 0x30, 0x39]);
+    let value = Value::decode(&mut data).unwrap();
+    assert_eq!(value.constructor.format_code, FormatCode::DECIMAL32);
+    assert_eq!(d32::try_from_value(value).unwrap(), price);
+    let value = Value::decode(&mut data).unwrap();
+    assert_eq!(value.constructor.format_code, FormatCode::DECIMAL64);
+    assert_eq!(d64::try_from_value(value).unwrap(), balance);
+    let value = Value::decode(&mut data).unwrap();
+    assert_eq!(value.constructor.format_code, FormatCode::DECIMAL128);
+    assert_eq!(d128::try_from_value(value.clone()).unwrap(), total);
+    assert!(d64::try_from_value(value).is_err());
+}
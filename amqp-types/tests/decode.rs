@@ -0,0 +1,46 @@
+use amqp_types::{codec::Decode, Primitive, Value};
+
+fn construct(mut bytes: &[u8]) -> Primitive<'_> {
+    Value::decode(&mut bytes).unwrap().construct().unwrap()
+}
+
+#[test]
+fn short_is_signed() {
+    assert!(matches!(
+        construct(&[0x61, 0xff, 0xfe]),
+        Primitive::Short(-2)
+    ));
+    assert!(matches!(
+        construct(&[0x60, 0xff, 0xfe]),
+        Primitive::UShort(0xfffe)
+    ));
+}
+
+#[test]
+fn str8_length_is_one_byte() {
+    assert!(matches!(
+        construct(&[0xa1, 0x02, b'a', b'b']),
+        Primitive::String("ab")
+    ));
+    // what follows the string is left to decode
+    let mut data = [0xa1, 0x01, b'a', 0x52, 0x07].as_slice();
+    Value::decode(&mut data).unwrap();
+    assert_eq!(data, [0x52, 0x07]);
+}
+
+#[test]
+fn char_is_a_code_point() {
+    assert!(matches!(
+        construct(&[0x73, 0x00, 0x01, 0xf6, 0x00]),
+        Primitive::Char('\u{1f600}')
+    ));
+    // what follows the code point is left to decode
+    let mut data = [0x00, 0x01, 0xf6, 0x00, 0x52, 0x07].as_slice();
+    assert_eq!(char::decode(&mut data).unwrap(), '\u{1f600}');
+    assert_eq!(data, [0x52, 0x07]);
+    // a surrogate isn't a char
+    let mut invalid = [0x73, 0x00, 0x00, 0xd8, 0x00].as_slice();
+    assert!(Value::decode(&mut invalid)
+        .and_then(|value| value.construct())
+        .is_err());
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use amqp_types::{
+    codec::{BufWriter, Encode, EncodeMode, Sink},
+    primitive::{Array, Binary, Map, OwnedSymbol, Symbol, Ts},
+    OwnedDescriptor, OwnedValue,
+};
+
+fn assert_size<T: Encode + Clone>(value: T) {
+    for mode in [EncodeMode::Default, EncodeMode::Compact] {
+        let size = value.encoded_size(mode);
+        let mut writer = BufWriter::new(Vec::with_capacity(size)).with_mode(mode);
+        writer.write_amqp_value(value.clone()).unwrap();
+        assert_eq!(writer.position(), size, "{mode:?}");
+    }
+}
+
+#[test]
+fn scalar_sizes() {
+    assert_size(0u32);
+    assert_size(200u32);
+    assert_size(u32::MAX);
+    assert_size(0u64);
+    assert_size(-100i64);
+    assert_size(1000i32);
+    assert_size(true);
+    assert_size('x');
+    assert_size(Ts::from_millis(1));
+    assert_size(Option::<u32>::None);
+    assert_size(Some(7u8));
+    assert_size("short");
+    assert_size(Symbol::new(b"amqp:open:list"));
+    assert_size(Binary::new(&[0; 300]));
+}
+
+#[test]
+fn compound_sizes() {
+    assert_size(HashMap::from([
+        (OwnedSymbol::from("a"), 1u64),
+        (OwnedSymbol::from("b"), 2),
+    ]));
+    assert_size(OwnedValue::List(vec![]));
+    assert_size(OwnedValue::Array(vec![]));
+    assert_size(OwnedValue::Described(
+        OwnedDescriptor::Numeric(0x24),
+        Box::new(OwnedValue::List(vec![
+            OwnedValue::List(vec![OwnedValue::UInt(0); 3]),
+            OwnedValue::Map(vec![(
+                OwnedValue::from("k"),
+                OwnedValue::String("v".repeat(260)),
+            )]),
+            OwnedValue::Array(vec![OwnedValue::Int(-1), OwnedValue::Int(1000)]),
+        ])),
+    ));
+    // `Array` and `Map` are single pass, measure a fresh one for each mode
+    for mode in [EncodeMode::Default, EncodeMode::Compact] {
+        let array = Array::new_write(["en-US", "de-DE"]);
+        let size = array.encoded_size(mode);
+        let mut writer = BufWriter::new(Vec::new()).with_mode(mode);
+        writer.write_amqp_value(array).unwrap();
+        assert_eq!(writer.position(), size);
+
+        let map = Map::new_write([(Symbol::new(b"x"), 1u32), (Symbol::new(b"y"), 300)]);
+        let size = map.encoded_size(mode);
+        let mut writer = BufWriter::new(Vec::new()).with_mode(mode);
+        writer.write_amqp_value(map).unwrap();
+        assert_eq!(writer.position(), size);
+    }
+}
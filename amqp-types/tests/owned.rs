@@ -0,0 +1,80 @@
+use std::collections::{BTreeSet, HashMap};
+
+use amqp_types::{
+    codec::{Decode, Sink, Writer},
+    primitive::OwnedSymbol,
+    types::Type,
+    OwnedDescriptor, OwnedValue, Value,
+};
+
+fn decode_owned(bytes: &[u8]) -> OwnedValue {
+    let buffer = bytes.to_vec();
+    let value = Value::decode(&mut buffer.as_slice()).unwrap();
+    // the owned value outlives the buffer it was decoded from
+    value.into_owned().unwrap()
+}
+
+#[test]
+fn owned_value_outlives_buffer() {
+    // described list: 0x00 0x53 0x70 [list8 size=8 count=3 [true] [smalluint 7] [sym8 "ab"]]
+    let owned = decode_owned(&[
+        0x00, 0x53, 0x70, 0xc0, 0x08, 0x03, 0x41, 0x52, 0x07, 0xa3, 0x02, b'a', b'b',
+    ]);
+    assert_eq!(
+        owned,
+        OwnedValue::Described(
+            OwnedDescriptor::Numeric(0x70),
+            Box::new(OwnedValue::List(vec![
+                OwnedValue::Boolean(true),
+                OwnedValue::UInt(7),
+                OwnedValue::Symbol(OwnedSymbol::from("ab")),
+            ]))
+        )
+    );
+}
+
+#[test]
+fn equality_ignores_encoding() {
+    let small = decode_owned(&[0x52, 0x07]);
+    let wide = decode_owned(&[0x70, 0x00, 0x00, 0x00, 0x07]);
+    assert_eq!(small, wide);
+    let zero = decode_owned(&[0x43]);
+    assert_ne!(small, zero);
+    assert!(zero < small);
+    let mut set = BTreeSet::new();
+    set.insert(small);
+    assert!(set.contains(&wide));
+}
+
+#[test]
+fn reserved_descriptor_is_rejected() {
+    // a descriptor which is a uint, neither a ulong nor a symbol
+    let mut data = [0x00, 0x52, 0x10, 0x45].as_slice();
+    assert!(Value::decode(&mut data).is_err());
+}
+
+#[test]
+fn owned_map_round_trip() {
+    let fields = HashMap::from([
+        (OwnedSymbol::from("x-opt-partition"), OwnedValue::Int(-3)),
+        (OwnedSymbol::from("x-opt-name"), OwnedValue::from("hello")),
+        (
+            OwnedSymbol::from("x-opt-list"),
+            OwnedValue::Array(vec![OwnedValue::Double(1.5), OwnedValue::Double(f64::NAN)]),
+        ),
+    ]);
+    let mut buffer = vec![0; 256];
+    let mut writer = Writer::new(&mut buffer);
+    writer.write_amqp_value(fields.clone()).unwrap();
+    let value = Value::decode(&mut buffer.as_slice()).unwrap();
+    let decoded = HashMap::<OwnedSymbol, OwnedValue>::try_from_value(value).unwrap();
+    assert_eq!(decoded, fields);
+}
+
+#[test]
+fn heterogeneous_array_is_rejected() {
+    let array = OwnedValue::Array(vec![OwnedValue::Int(1), OwnedValue::Long(2)]);
+    let mut buffer = vec![0; 64];
+    let mut writer = Writer::new(&mut buffer);
+    assert!(writer.write_amqp_value(array).is_err());
+}
@@ -0,0 +1,107 @@
+#![cfg(feature = "serde")]
+
+use std::collections::BTreeMap;
+
+use amqp_types::{
+    codec::{BufWriter, Decode},
+    serde::{from_slice, to_writer},
+    FormatCode, Primitive, Value,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Outcome {
+    Accepted,
+    Rejected(String),
+    Modified { failed: bool, undeliverable: bool },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Order<'a> {
+    id: u64,
+    customer: &'a str,
+    note: Option<String>,
+    lines: Vec<(u32, i16)>,
+    tags: BTreeMap<String, i64>,
+    outcome: Outcome,
+    pending: Vec<Outcome>,
+}
+
+fn encode<T: Serialize>(value: &T) -> Vec<u8> {
+    let mut writer = BufWriter::new(Vec::new());
+    to_writer(&mut writer, value).unwrap();
+    writer.into_inner()
+}
+
+#[test]
+fn serde_round_trip() {
+    let order = Order {
+        id: 42,
+        customer: "alice",
+        note: None,
+        lines: vec![(1, -2), (3, 4)],
+        tags: BTreeMap::from([("vip".to_string(), 1), ("region".to_string(), -7)]),
+        outcome: Outcome::Rejected("out of stock".to_string()),
+        pending: vec![
+            Outcome::Accepted,
+            Outcome::Modified {
+                failed: true,
+                undeliverable: false,
+            },
+        ],
+    };
+    let bytes = encode(&order);
+    let decoded: Order = from_slice(&bytes).unwrap();
+    assert_eq!(decoded, order);
+}
+
+#[test]
+fn serde_data_model() {
+    // a struct is a map keyed by field name
+    let bytes = encode(&Outcome::Modified {
+        failed: true,
+        undeliverable: false,
+    });
+    let value = Value::decode(&mut bytes.as_slice()).unwrap();
+    let Primitive::Map(mut variant) = value.construct().unwrap() else {
+        panic!("variant is not a map");
+    };
+    let (name, _) = variant.next().unwrap().unwrap();
+    assert_eq!(name.constructor.format_code, FormatCode::SYMBOL32);
+    assert!(variant.next().is_none());
+
+    // a unit variant is a bare symbol
+    let bytes = encode(&Outcome::Accepted);
+    assert_eq!(bytes[..5], [0xb3, 0x00, 0x00, 0x00, 0x08]);
+    assert_eq!(&bytes[5..], b"Accepted");
+
+    // `None` is null
+    assert_eq!(encode(&Option::<u8>::None), [0x40]);
+    assert_eq!(from_slice::<Option<()>>(&[0x40]).unwrap(), None);
+    // a described null carries its descriptor, it isn't `None`
+    assert_eq!(
+        from_slice::<Option<()>>(&[0x00, 0x53, 0x24, 0x40]).unwrap(),
+        Some(())
+    );
+    assert!(from_slice::<Option<u8>>(&[0x00, 0x53, 0x24, 0x40]).is_err());
+}
+
+#[test]
+fn serde_struct_from_list() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Flow {
+        handle: u32,
+        drain: bool,
+        echo: Option<bool>,
+    }
+    // list8 size=5 count=3 [smalluint 7] [true] [null]
+    let flow: Flow = from_slice(&[0xc0, 0x05, 0x03, 0x52, 0x07, 0x41, 0x40]).unwrap();
+    assert_eq!(
+        flow,
+        Flow {
+            handle: 7,
+            drain: true,
+            echo: None
+        }
+    );
+}
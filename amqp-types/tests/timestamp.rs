@@ -0,0 +1,66 @@
+use std::time::{Duration, SystemTime};
+
+use amqp_types::{
+    codec::{Decode, Sink, Writer},
+    primitive::Ts,
+    types::Type,
+    Primitive, Value,
+};
+
+#[test]
+fn timestamp_round_trip() {
+    for millis in [0, 1_700_000_000_123, -86_400_000, i64::MIN, i64::MAX] {
+        let mut buffer = vec![0; 9];
+        let mut writer = Writer::new(&mut buffer);
+        writer.write_amqp_value(Ts::from_millis(millis)).unwrap();
+        assert_eq!(buffer[0], 0x83);
+        assert_eq!(&buffer[1..], &millis.to_be_bytes());
+        let value = Value::decode(&mut buffer.as_slice()).unwrap();
+        assert!(
+            matches!(value.clone().construct().unwrap(), Primitive::Timestamp(ts) if ts.as_millis() == millis)
+        );
+        assert_eq!(Ts::try_from_value(value).unwrap().as_millis(), millis);
+    }
+}
+
+#[test]
+fn system_time_conversion() {
+    let before_epoch = SystemTime::UNIX_EPOCH - Duration::from_millis(1500);
+    assert_eq!(Ts::from(before_epoch).as_millis(), -1500);
+    assert_eq!(
+        Ts::from_millis(-1500).into_system_time().unwrap(),
+        before_epoch
+    );
+    // sub-millisecond precision is truncated towards the past
+    let just_before = SystemTime::UNIX_EPOCH - Duration::from_micros(1);
+    assert_eq!(Ts::from(just_before).as_millis(), -1);
+    let just_after = SystemTime::UNIX_EPOCH + Duration::from_micros(1999);
+    assert_eq!(Ts::from(just_after).as_millis(), 1);
+    // the extremes fail where the platform can't represent them, rather than panic
+    for ts in [Ts::from_millis(i64::MIN), Ts::from_millis(i64::MAX)] {
+        if let Ok(time) = SystemTime::try_from(ts) {
+            assert_eq!(Ts::from(time), ts);
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn chrono_conversion() {
+    let date_time = chrono::DateTime::parse_from_rfc3339("1969-12-31T23:59:58.500Z").unwrap();
+    let ts = Ts::from(date_time);
+    assert_eq!(ts.as_millis(), -1500);
+    let back = chrono::DateTime::<chrono::Utc>::try_from(ts).unwrap();
+    assert_eq!(back, date_time);
+    assert!(chrono::DateTime::<chrono::Utc>::try_from(Ts::from_millis(i64::MAX)).is_err());
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn time_conversion() {
+    let date_time = time::OffsetDateTime::UNIX_EPOCH - time::Duration::milliseconds(1500);
+    let ts = Ts::from(date_time);
+    assert_eq!(ts.as_millis(), -1500);
+    assert_eq!(time::OffsetDateTime::try_from(ts).unwrap(), date_time);
+    assert!(time::OffsetDateTime::try_from(Ts::from_millis(i64::MIN)).is_err());
+}
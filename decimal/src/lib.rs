@@ -1,34 +1,272 @@
-// not a key part
-
-
-
-const MASK_SIGN: u32 = 0x8000_0000;
-const MASK_COMBINATION_FIELD: u32 = 0x7fc0_0000;
-const MASK_EXPONENT_FIELD: u32 = 0x0000_ffff;
-use std::ops::{Add, AddAssign, Sub, SubAssign, Mul, MulAssign, Div, DivAssign, Neg};
-
-#[allow(non_camel_case_types)]
-pub struct d32(u32);
-
-
-impl d32 {
-    pub const ONE: Self = Self(0x3f80_0000);
-    pub const ZERO: Self = Self(0x0000_0000);
-    pub const NEGATIVE_ZERO: Self = Self(0x8000_0000);
-    pub const INFINITY: Self = Self(0x7f80_0000);
-    pub const NEGATIVE_INFINITY: Self = Self(0xff80_0000);
-    pub const NAN: Self = Self(0x7fc0_0000);
-    pub const NEGATIVE_NAN: Self = Self(0xffc0_0000);
-    pub const MIN: Self = Self(0x0000_0001);
-    pub const MAX: Self = Self(0x7f7f_ffff);
-    pub const NEGATIVE_MIN: Self = Self(0x8000_0001);
-    pub const NEGATIVE_MAX: Self = Self(0xff7f_ffff);
-    pub const MIN_POSITIVE_NORMAL: Self = Self(0x0080_0000);
-    pub fn new(int: i32, dec: u32) -> Self {
-        let sign = if int < 0 { MASK_SIGN } else { 0 };
-        let int = int.unsigned_abs();
-        let exp = 0;
-        let mantissa = int << 8 | dec;
-        Self(sign | exp << 23 | mantissa)
-    }
-}
\ No newline at end of file
+//! IEEE 754-2008 decimal floating point types, in the binary integer decimal (BID) encoding.
+//!
+//! These types are containers for the interchange formats used by AMQP `decimal32`, `decimal64`
+//! and `decimal128`, they don't implement decimal arithmetic. A value can be built from and split
+//! into its sign, coefficient and exponent, and is transferred as its raw big-endian bits.
+use std::fmt;
+
+macro_rules! decimal {
+    (
+        $(#[$meta: meta])*
+        $name: ident($Bits: ty) {
+            coefficient: $Coefficient: ty,
+            bits: $bits: expr,
+            exponent_bits: $exponent_bits: expr,
+            bias: $bias: expr,
+            max_coefficient: $max_coefficient: expr,
+        }
+    ) => {
+        $(#[$meta])*
+        #[allow(non_camel_case_types)]
+        #[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+        #[repr(transparent)]
+        pub struct $name($Bits);
+
+        impl $name {
+            const BITS: u32 = $bits;
+            const EXPONENT_BITS: u32 = $exponent_bits;
+            /// width of the coefficient when it is stored without the implicit `100` prefix
+            const SMALL_COEFFICIENT_BITS: u32 = Self::BITS - 1 - Self::EXPONENT_BITS;
+            /// width of the coefficient when it is stored with the implicit `100` prefix
+            const LARGE_COEFFICIENT_BITS: u32 = Self::SMALL_COEFFICIENT_BITS - 2;
+            const SIGN_MASK: $Bits = 1 << (Self::BITS - 1);
+            const EXPONENT_MASK: $Bits = (1 << Self::EXPONENT_BITS) - 1;
+            const INFINITY_BITS: $Bits = 0b11110 << (Self::BITS - 6);
+            const NAN_BITS: $Bits = 0b11111 << (Self::BITS - 6);
+            const SPECIAL_MASK: $Bits = 0b11111 << (Self::BITS - 6);
+            const LARGE_MASK: $Bits = 0b11 << (Self::BITS - 3);
+
+            /// the exponent bias
+            pub const BIAS: i32 = $bias;
+            /// the smallest exponent of a finite value
+            pub const MIN_EXPONENT: i32 = -Self::BIAS;
+            /// the largest exponent of a finite value
+            pub const MAX_EXPONENT: i32 = (3 << (Self::EXPONENT_BITS - 2)) - 1 - Self::BIAS;
+            /// the largest coefficient of a finite value
+            pub const MAX_COEFFICIENT: $Coefficient = $max_coefficient;
+
+            pub const ZERO: Self = Self((Self::BIAS as $Bits) << Self::SMALL_COEFFICIENT_BITS);
+            pub const NEGATIVE_ZERO: Self = Self(Self::SIGN_MASK | Self::ZERO.0);
+            pub const ONE: Self = Self(Self::ZERO.0 | 1);
+            pub const INFINITY: Self = Self(Self::INFINITY_BITS);
+            pub const NEGATIVE_INFINITY: Self = Self(Self::SIGN_MASK | Self::INFINITY_BITS);
+            pub const NAN: Self = Self(Self::NAN_BITS);
+
+            pub const fn from_bits(bits: $Bits) -> Self {
+                Self(bits)
+            }
+            pub const fn to_bits(self) -> $Bits {
+                self.0
+            }
+            pub const fn from_be_bytes(bytes: [u8; ($bits / 8)]) -> Self {
+                Self(<$Bits>::from_be_bytes(bytes))
+            }
+            pub const fn to_be_bytes(self) -> [u8; ($bits / 8)] {
+                self.0.to_be_bytes()
+            }
+
+            /// Build a finite value `(-1)^sign * coefficient * 10^exponent`.
+            ///
+            /// Return `None` if the coefficient or the exponent is out of range.
+            pub const fn from_parts(negative: bool, coefficient: $Coefficient, exponent: i32) -> Option<Self> {
+                if coefficient > Self::MAX_COEFFICIENT
+                    || exponent < Self::MIN_EXPONENT
+                    || exponent > Self::MAX_EXPONENT
+                {
+                    return None;
+                }
+                let sign = if negative { Self::SIGN_MASK } else { 0 };
+                let biased = (exponent + Self::BIAS) as $Bits;
+                let coefficient = coefficient as $Bits;
+                let bits = if coefficient >> Self::SMALL_COEFFICIENT_BITS == 0 {
+                    sign | biased << Self::SMALL_COEFFICIENT_BITS | coefficient
+                } else {
+                    let trailing = coefficient & ((1 << Self::LARGE_COEFFICIENT_BITS) - 1);
+                    sign | Self::LARGE_MASK | biased << Self::LARGE_COEFFICIENT_BITS | trailing
+                };
+                Some(Self(bits))
+            }
+
+            /// Split a finite value into `(negative, coefficient, exponent)`.
+            ///
+            /// Return `None` for infinities and NaNs. A non-canonical coefficient decodes to zero.
+            pub const fn to_parts(self) -> Option<(bool, $Coefficient, i32)> {
+                if !self.is_finite() {
+                    return None;
+                }
+                let bits = self.0;
+                let (biased, coefficient) = if bits & Self::LARGE_MASK != Self::LARGE_MASK {
+                    (
+                        (bits >> Self::SMALL_COEFFICIENT_BITS) & Self::EXPONENT_MASK,
+                        bits & ((1 << Self::SMALL_COEFFICIENT_BITS) - 1),
+                    )
+                } else {
+                    (
+                        (bits >> Self::LARGE_COEFFICIENT_BITS) & Self::EXPONENT_MASK,
+                        (0b100 << Self::LARGE_COEFFICIENT_BITS)
+                            | (bits & ((1 << Self::LARGE_COEFFICIENT_BITS) - 1)),
+                    )
+                };
+                let coefficient = coefficient as $Coefficient;
+                let coefficient = if coefficient > Self::MAX_COEFFICIENT {
+                    0
+                } else {
+                    coefficient
+                };
+                Some((self.is_sign_negative(), coefficient, biased as i32 - Self::BIAS))
+            }
+
+            pub const fn is_sign_negative(self) -> bool {
+                self.0 & Self::SIGN_MASK != 0
+            }
+            pub const fn is_nan(self) -> bool {
+                self.0 & Self::SPECIAL_MASK == Self::NAN_BITS
+            }
+            pub const fn is_infinite(self) -> bool {
+                self.0 & Self::SPECIAL_MASK == Self::INFINITY_BITS
+            }
+            pub const fn is_finite(self) -> bool {
+                !self.is_nan() && !self.is_infinite()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self.to_parts() {
+                    Some((negative, coefficient, exponent)) => {
+                        fmt_finite(f, negative, &coefficient.to_string(), exponent)
+                    }
+                    None if self.is_nan() => f.write_str("NaN"),
+                    None if self.is_sign_negative() => f.write_str("-Infinity"),
+                    None => f.write_str("Infinity"),
+                }
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, concat!(stringify!($name), "({})"), self)
+            }
+        }
+    };
+}
+
+fn fmt_finite(
+    f: &mut fmt::Formatter<'_>,
+    negative: bool,
+    digits: &str,
+    exponent: i32,
+) -> fmt::Result {
+    if negative {
+        f.write_str("-")?;
+    }
+    if exponent > 0 {
+        return write!(f, "{digits}E+{exponent}");
+    }
+    let scale = exponent.unsigned_abs() as usize;
+    if scale == 0 {
+        f.write_str(digits)
+    } else if digits.len() > scale {
+        let (int, frac) = digits.split_at(digits.len() - scale);
+        write!(f, "{int}.{frac}")
+    } else {
+        write!(f, "0.{:0>scale$}", digits)
+    }
+}
+
+decimal! {
+    /// IEEE 754-2008 decimal32, 7 digits of precision.
+    d32(u32) {
+        coefficient: u32,
+        bits: 32,
+        exponent_bits: 8,
+        bias: 101,
+        max_coefficient: 9_999_999,
+    }
+}
+
+decimal! {
+    /// IEEE 754-2008 decimal64, 16 digits of precision.
+    d64(u64) {
+        coefficient: u64,
+        bits: 64,
+        exponent_bits: 10,
+        bias: 398,
+        max_coefficient: 9_999_999_999_999_999,
+    }
+}
+
+decimal! {
+    /// IEEE 754-2008 decimal128, 34 digits of precision.
+    d128(u128) {
+        coefficient: u128,
+        bits: 128,
+        exponent_bits: 14,
+        bias: 6176,
+        max_coefficient: 9_999_999_999_999_999_999_999_999_999_999,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_encodings() {
+        assert_eq!(d32::ONE.to_bits(), 0x3280_0001);
+        assert_eq!(d64::ONE.to_bits(), 0x31c0_0000_0000_0001);
+        assert_eq!(
+            d128::ONE.to_bits(),
+            0x3040_0000_0000_0000_0000_0000_0000_0001
+        );
+        assert_eq!(
+            d32::from_parts(true, 1, 0),
+            Some(d32::from_bits(0xb280_0001))
+        );
+        assert_eq!(d32::MAX_EXPONENT, 90);
+        assert_eq!(d64::MAX_EXPONENT, 369);
+        assert_eq!(d128::MAX_EXPONENT, 6111);
+    }
+
+    #[test]
+    fn parts_round_trip() {
+        for (negative, coefficient, exponent) in [
+            (false, 0, 0),
+            (true, 12345, -2),
+            (false, d32::MAX_COEFFICIENT, d32::MAX_EXPONENT),
+            (false, 1, d32::MIN_EXPONENT),
+            // large coefficients use the `11` combination prefix
+            (false, 9_000_000, 3),
+        ] {
+            let value = d32::from_parts(negative, coefficient, exponent).unwrap();
+            assert_eq!(value.to_parts(), Some((negative, coefficient, exponent)));
+        }
+        let value = d64::from_parts(false, d64::MAX_COEFFICIENT, -5).unwrap();
+        assert_eq!(value.to_parts(), Some((false, d64::MAX_COEFFICIENT, -5)));
+        let value = d128::from_parts(true, d128::MAX_COEFFICIENT, 17).unwrap();
+        assert_eq!(value.to_parts(), Some((true, d128::MAX_COEFFICIENT, 17)));
+        assert_eq!(d32::from_parts(false, 10_000_000, 0), None);
+        assert_eq!(d32::from_parts(false, 1, 91), None);
+    }
+
+    #[test]
+    fn special_values() {
+        assert!(d64::NAN.is_nan());
+        assert!(d64::INFINITY.is_infinite());
+        assert!(d64::NEGATIVE_INFINITY.is_sign_negative());
+        assert_eq!(d64::NAN.to_parts(), None);
+        assert!(d128::ZERO.is_finite());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            d32::from_parts(true, 12345, -2).unwrap().to_string(),
+            "-123.45"
+        );
+        assert_eq!(d32::from_parts(false, 5, -3).unwrap().to_string(), "0.005");
+        assert_eq!(d64::from_parts(false, 42, 3).unwrap().to_string(), "42E+3");
+        assert_eq!(d128::NEGATIVE_INFINITY.to_string(), "-Infinity");
+        assert_eq!(format!("{:?}", d32::ONE), "d32(1)");
+    }
+}
@@ -7,6 +7,3 @@ pub mod transport;
 
 
 pub mod client;
-pub mod server;
-
-pub mod sm;
\ No newline at end of file